@@ -1,158 +1,1973 @@
 use crate::helper::ToString;
+use crate::span::Span;
+use crate::token::Token;
+use std::fmt;
 
 /**
  * ## BNF Grammar for CSS
  * <stylesheet> ::= <rule>*
- * <rule> ::= <style-rule> | <media-rule>
- * <style-rule> ::= <selectors> "{" <declarations> "}"
+ * <rule> ::= <style-rule> | <media-rule> | <supports-rule> | <keyframes-rule> | <font-face-rule> | <page-rule> | <counter-style-rule> | <property-rule> | <layer-rule> | <import-rule> | <container-rule> | <charset-rule> | <namespace-rule> | <unknown-at-rule>
+ * <style-rule> ::= <selectors> "{" <style-block> "}"
+ * <style-block> ::= [ <declaration> | <nested-rule> ]*
+ * <nested-rule> ::= <relative-selectors> "{" <style-block> "}" | <media-rule> | <supports-rule> | <container-rule> | <layer-rule> | <unknown-at-rule>
+ * <relative-selectors> ::= <relative-selector> [ "," <relative-selector> ]*
+ * <relative-selector> ::= <combinator>? <selector>
  * <media-rule> ::= <media-query> "{" <rule>* "}"
- * <selectors> ::= <selector> | <selector> <combinator> <selectors>
- * <combinator> ::= "+" | ">" | "~" | " "
- * <selector> ::= <simple-selector> | <selector> <simple-selector>
- * <simple-selector> ::= <type-selector> | <id-selector> | <class-selector> | <pseudo-class-selector> | <pseudo-element-selector>
- * <type-selector> ::= <ident>
+ * <selectors> ::= <selector> [ "," <selector> ]*
+ * <selector> ::= <compound-selector> [ <combinator>? <compound-selector> ]*
+ * <combinator> ::= ">" | "+" | "~" | "||"
+ * <compound-selector> ::= <type-selector>? <subclass-selector>* [ <pseudo-element-selector> <pseudo-class-selector>* ]*
+ * <subclass-selector> ::= <id-selector> | <class-selector> | <attribute-selector> | <pseudo-class-selector> | <pseudo-element-selector> | <nesting-selector>
+ * <type-selector> ::= <ns-prefix>? [ <ident> | "*" ]
+ * <ns-prefix> ::= [ <ident> | "*" ]? "|"
  * <id-selector> ::= "#" <ident>
  * <class-selector> ::= "." <ident>
- * <pseudo-class-selector> ::= ":" <ident>
- * <pseudo-element-selector> ::= "::" <ident>
+ * <attribute-selector> ::= "[" <wq-name> "]" | "[" <wq-name> <attr-matcher> [ <string> | <ident> ] <attr-modifier>? "]"
+ * <wq-name> ::= <ns-prefix>? <ident>
+ * <attr-matcher> ::= [ "~" | "|" | "^" | "$" | "*" ]? "="
+ * <attr-modifier> ::= "i" | "s"
+ * <pseudo-class-selector> ::= ":" <ident> | ":" <function-token> <pseudo-class-argument> ")"
+ * <pseudo-class-argument> ::= <selectors> | <relative-selectors> | <an+b> [ "of" <selectors> ]? | <any-value>
+ * <an+b> ::= "odd" | "even" | <integer> | <n-dimension> [ ["+" | "-"] <integer> ]? | ...
+ * <pseudo-element-selector> ::= "::" <ident> | ":" [ "before" | "after" | "first-line" | "first-letter" ]
+ * <nesting-selector> ::= "&"
  *
  * <declarations> ::= <declaration> | <declaration> <declarations>
- * <declaration> ::= <property> ":" <value> ";"
+ * <declaration> ::= <property> ":" <value> <important>? ";"
+ * <important> ::= "!" <whitespace>* "important"
  * <property> ::= <ident>
- * <value> ::= <ident> | <number> | <percentage> | <length> | <color> | <string> | <function> | <url>
- * <function> ::= <ident> "(" <value>* ")"
+ * <value> ::= <component-value> [ <whitespace>? [ "," | "/" ]? <whitespace>? <component-value> ]*
+ * <component-value> ::= <ident> | <number> | <percentage> | <length> | <color> | <string> | <function> | <math-function> | <url>
+ * <function> ::= <function-token> <component-value>* ")"
+ * <math-function> ::= <function-token> <calc-sum> [ "," <calc-sum> ]* ")"
+ * <calc-sum> ::= <calc-product> [ [ "+" | "-" ] <calc-product> ]*
+ * <calc-product> ::= <calc-value> [ [ "*" | "/" ] <calc-value> ]*
+ * <calc-value> ::= <number> | <dimension> | <percentage> | <ident> | "(" <calc-sum> ")" | <function> | <math-function>
  * <url> ::= "url(" <string> ")"
  *
- * <media-query> ::= "@media" <media-condition>
- * <media-condition> ::= ::= <ident> | <ident> "(" <media-feature> ")"
- * <media-feature> ::= <ident> ":" <value>
+ * <media-rule> ::= "@media" <media-query-list> "{" <rule>* "}"
+ * <media-query-list> ::= <media-query> | <media-query> "," <media-query-list>
+ * <media-query> ::= <media-condition> | [ "not" | "only" ]? <media-type> [ "and" <media-condition-without-or> ]?
+ * <media-condition> ::= <media-not> | <media-in-parens> [ <media-and>* | <media-or>* ]
+ * <media-condition-without-or> ::= <media-not> | <media-in-parens> <media-and>*
+ * <media-not> ::= "not" <media-in-parens>
+ * <media-and> ::= "and" <media-in-parens>
+ * <media-or> ::= "or" <media-in-parens>
+ * <media-in-parens> ::= "(" <media-condition> ")" | <media-feature> | <general-enclosed>
+ * <media-feature> ::= "(" [ <mf-plain> | <mf-boolean> | <mf-range> ] ")"
+ * <mf-plain> ::= <ident> ":" <mf-value>
+ * <mf-boolean> ::= <ident>
+ * <mf-range> ::= <ident> <mf-comparison> <mf-value> | <mf-value> <mf-comparison> <ident> | <mf-value> <mf-comparison> <ident> <mf-comparison> <mf-value>
+ * <mf-value> ::= <number> | <dimension> | <ident> | <number> "/" <number>
+ * <mf-comparison> ::= "<" | "<=" | ">" | ">=" | "="
+ *
+ * <supports-rule> ::= "@supports" <supports-condition> "{" <rule>* "}"
+ * <supports-condition> ::= "not" <supports-in-parens> | <supports-in-parens> [ [ "and" <supports-in-parens> ]* | [ "or" <supports-in-parens> ]* ]
+ * <supports-in-parens> ::= "(" <supports-condition> ")" | <supports-feature> | <general-enclosed>
+ * <supports-feature> ::= <supports-decl> | <supports-selector-fn>
+ * <supports-decl> ::= "(" <declaration> ")"
+ * <supports-selector-fn> ::= "selector(" <selector> ")"
+ *
+ * <keyframes-rule> ::= "@keyframes" <keyframes-name> "{" <keyframe-block>* "}"
+ * <keyframes-name> ::= <ident> | <string>
+ * <keyframe-block> ::= <keyframe-selector> [ "," <keyframe-selector> ]* "{" <declarations> "}"
+ * <keyframe-selector> ::= "from" | "to" | <percentage>
+ *
+ * <font-face-rule> ::= "@font-face" "{" <declarations> "}"
+ * <page-rule> ::= "@page" [ <page-selector> [ "," <page-selector> ]* ]? "{" [ <declaration> | <page-margin-rule> ]* "}"
+ * <page-selector> ::= <ident>? <pseudo-page>*
+ * <pseudo-page> ::= ":" [ "left" | "right" | "first" | "blank" ]
+ * <page-margin-rule> ::= <margin-at-keyword> "{" <declarations> "}"
+ * <counter-style-rule> ::= "@counter-style" <ident> "{" <declarations> "}"
+ * <property-rule> ::= "@property" <custom-property-name> "{" <declarations> "}"
+ * <syntax> ::= "*" | <syntax-component> [ "|" <syntax-component> ]*
+ * <syntax-component> ::= [ "<" <data-type-name> ">" | <ident> ] [ "+" | "#" ]?
+ *
+ * <layer-rule> ::= "@layer" <layer-name> [ "," <layer-name> ]* ";" | "@layer" <layer-name>? "{" <rule>* "}"
+ * <layer-name> ::= <ident> [ "." <ident> ]*
+ * <import-rule> ::= "@import" [ <url> | <string> ] [ "layer" | "layer(" <layer-name> ")" ]? [ "supports(" [ <supports-condition> | <declaration> ] ")" ]? <media-query-list>? ";"
+ * <charset-rule> ::= "@charset" <string> ";"
+ * <namespace-rule> ::= "@namespace" <ident>? [ <url> | <string> ] ";"
+ *
+ * <container-rule> ::= "@container" <container-condition> "{" <rule>* "}"
+ * <container-condition> ::= <container-name> | <container-name>? <container-query>
+ * <container-query> ::= "not" <query-in-parens> | <query-in-parens> [ [ "and" <query-in-parens> ]* | [ "or" <query-in-parens> ]* ]
+ * <query-in-parens> ::= "(" <container-query> ")" | <media-feature> | "style(" <style-query> ")" | <general-enclosed>
+ * <style-query> ::= "not" <style-in-parens> | <style-in-parens> [ [ "and" <style-in-parens> ]* | [ "or" <style-in-parens> ]* ] | <style-feature>
+ * <style-in-parens> ::= "(" <style-query> ")" | <general-enclosed>
+ * <style-feature> ::= <property> [ ":" <value> ]?
+ *
+ * <unknown-at-rule> ::= <at-keyword> <component-value>* [ "{" <component-value>* "}" | ";" ]
  *
  * ### Reference
  * - [CSS Syntax Module Level 3](https://www.w3.org/TR/css-syntax-3/)
+ * - [Media Queries Level 4](https://www.w3.org/TR/mediaqueries-4/)
+ * - [CSS Conditional Rules Module Level 4](https://www.w3.org/TR/css-conditional-4/)
+ * - [CSS Animations Level 1](https://www.w3.org/TR/css-animations-1/)
+ * - [CSS Fonts Module Level 4](https://www.w3.org/TR/css-fonts-4/#font-face-rule)
+ * - [CSS Paged Media Module Level 3](https://www.w3.org/TR/css-page-3/)
+ * - [CSS Counter Styles Level 3](https://www.w3.org/TR/css-counter-styles-3/)
+ * - [CSS Properties and Values API Level 1](https://www.w3.org/TR/css-properties-values-api-1/)
+ * - [CSS Cascading and Inheritance Level 5](https://www.w3.org/TR/css-cascade-5/)
+ * - [CSS Nesting Module](https://www.w3.org/TR/css-nesting-1/)
+ * - [CSS Namespaces Module Level 3](https://www.w3.org/TR/css-namespaces-3/)
+ * - [CSS Containment Module Level 3](https://www.w3.org/TR/css-contain-3/#container-rule)
  * - [Appendix G. Grammar of CSS 2.1](https://www.w3.org/TR/CSS21/grammar.html)
  * - [CSS.bnf](https://github.com/aptana/studio2/blob/master/tools/com.aptana.ide.parsing.tools/Parser%20Files/CSS.bnf)
  *
  */
 #[derive(Debug, Clone)]
-pub struct StyleSheet {
+pub struct StyleSheet {
+    pub rules: Vec<Rule>,
+    pub span: Span,
+}
+
+impl StyleSheet {
+    /// The `@import` rules in source order, i.e. the stylesheets this one depends on
+    pub fn imports(&self) -> Vec<&ImportRule> {
+        self.rules
+            .iter()
+            .filter_map(|rule| match rule {
+                Rule::Import(import_rule) => Some(import_rule),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The namespace URL declared for `prefix`, or the default namespace when `prefix` is `None`.
+    /// When a prefix is declared more than once, the last declaration wins.
+    pub fn namespace(&self, prefix: Option<&str>) -> Option<&str> {
+        self.rules.iter().rev().find_map(|rule| match rule {
+            Rule::Namespace(namespace_rule) if namespace_rule.prefix.as_deref() == prefix => {
+                Some(namespace_rule.url.as_str())
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Print the stylesheet as CSS, one top-level rule per line.
+/// Comments and formatting are not kept, but parsing the output gives the same rules.
+impl fmt::Display for StyleSheet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self.rules.iter().map(|rule| rule.to_string()).collect();
+        write!(f, "{}", rules.join("\n"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Rule {
+    StyleRule(StyleRule),
+    Media(MediaRule),
+    Supports(SupportsRule),
+    Keyframes(KeyframesRule),
+    FontFace(FontFaceRule),
+    Page(PageRule),
+    CounterStyle(CounterStyleRule),
+    Property(PropertyRule),
+    LayerStatement(LayerStatementRule),
+    LayerBlock(LayerBlockRule),
+    Import(ImportRule),
+    Container(ContainerRule),
+    Charset(CharsetRule),
+    Namespace(NamespaceRule),
+    Unknown(UnknownAtRule),
+}
+
+impl Rule {
+    pub fn span(&self) -> Span {
+        match self {
+            Rule::StyleRule(style_rule) => style_rule.span,
+            Rule::Media(media_rule) => media_rule.span,
+            Rule::Supports(supports_rule) => supports_rule.span,
+            Rule::Keyframes(keyframes_rule) => keyframes_rule.span,
+            Rule::FontFace(font_face_rule) => font_face_rule.span,
+            Rule::Page(page_rule) => page_rule.span,
+            Rule::CounterStyle(counter_style_rule) => counter_style_rule.span,
+            Rule::Property(property_rule) => property_rule.span,
+            Rule::LayerStatement(layer_statement_rule) => layer_statement_rule.span,
+            Rule::LayerBlock(layer_block_rule) => layer_block_rule.span,
+            Rule::Import(import_rule) => import_rule.span,
+            Rule::Container(container_rule) => container_rule.span,
+            Rule::Charset(charset_rule) => charset_rule.span,
+            Rule::Namespace(namespace_rule) => namespace_rule.span,
+            Rule::Unknown(unknown_at_rule) => unknown_at_rule.span,
+        }
+    }
+}
+
+impl ToString for Rule {
+    fn to_string(&self) -> String {
+        match self {
+            Rule::StyleRule(style_rule) => style_rule.to_string(),
+            Rule::Media(media_rule) => media_rule.to_string(),
+            Rule::Supports(supports_rule) => supports_rule.to_string(),
+            Rule::Keyframes(keyframes_rule) => keyframes_rule.to_string(),
+            Rule::FontFace(font_face_rule) => font_face_rule.to_string(),
+            Rule::Page(page_rule) => page_rule.to_string(),
+            Rule::CounterStyle(counter_style_rule) => counter_style_rule.to_string(),
+            Rule::Property(property_rule) => property_rule.to_string(),
+            Rule::LayerStatement(layer_statement_rule) => layer_statement_rule.to_string(),
+            Rule::LayerBlock(layer_block_rule) => layer_block_rule.to_string(),
+            Rule::Import(import_rule) => import_rule.to_string(),
+            Rule::Container(container_rule) => container_rule.to_string(),
+            Rule::Charset(charset_rule) => charset_rule.to_string(),
+            Rule::Namespace(namespace_rule) => namespace_rule.to_string(),
+            Rule::Unknown(unknown_at_rule) => unknown_at_rule.to_string(),
+        }
+    }
+}
+
+/// A block with the declarations and rules in it, e.g. `{ color: red; &:hover { color: blue; } }`
+fn block_to_string(declarations: &[Declaration], rules: &[Rule]) -> String {
+    let items: Vec<String> = declarations
+        .iter()
+        .map(|declaration| declaration.to_string())
+        .chain(rules.iter().map(|rule| rule.to_string()))
+        .collect();
+    if items.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", items.join(" "))
+    }
+}
+
+/// A string token with quotes and backslashes escaped, e.g. `"a\"b"`
+fn string_to_string(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    pub selector_text: String,
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>,
+    /// The rules nested in the style rule, e.g. `&:hover { ... }` or `@media print { ... }`.
+    /// Declarations directly in a nested conditional rule are wrapped in a style rule with the selector `&`.
+    pub rules: Vec<Rule>,
+    pub span: Span,
+}
+
+impl ToString for StyleRule {
+    fn to_string(&self) -> String {
+        let selectors: Vec<String> = self
+            .selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect();
+        format!(
+            "{} {}",
+            selectors.join(", "),
+            block_to_string(&self.declarations, &self.rules)
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaRule {
+    pub query_list: MediaQueryList,
+    pub rules: Vec<Rule>,
+    pub span: Span,
+}
+
+impl ToString for MediaRule {
+    fn to_string(&self) -> String {
+        let query_list = self.query_list.to_string();
+        let block = block_to_string(&[], &self.rules);
+        if query_list.is_empty() {
+            format!("@media {}", block)
+        } else {
+            format!("@media {} {}", query_list, block)
+        }
+    }
+}
+
+/// An empty list matches all media, as does `@media all`
+#[derive(Debug, Clone)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+    pub span: Span,
+}
+
+impl ToString for MediaQueryList {
+    fn to_string(&self) -> String {
+        self.queries
+            .iter()
+            .map(|query| query.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// e.g. `only screen and (min-width: 600px)` or `(400px <= width < 800px)`.
+/// A query which fails to parse is replaced with `not all`, as the spec requires.
+#[derive(Debug, Clone)]
+pub struct MediaQuery {
+    pub modifier: Option<MediaQueryModifier>,
+    pub media_type: Option<String>,
+    pub condition: Option<MediaCondition>,
+    pub span: Span,
+}
+
+impl ToString for MediaQuery {
+    fn to_string(&self) -> String {
+        let mut parts = Vec::new();
+        match self.modifier {
+            Some(MediaQueryModifier::Not) => parts.push("not".to_string()),
+            Some(MediaQueryModifier::Only) => parts.push("only".to_string()),
+            None => {}
+        }
+        if let Some(media_type) = &self.media_type {
+            parts.push(media_type.clone());
+        }
+        if let Some(condition) = &self.condition {
+            if self.media_type.is_some() {
+                parts.push("and".to_string());
+            }
+            parts.push(condition.to_string());
+        }
+        parts.join(" ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaQueryModifier {
+    Not,
+    Only,
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    /// `<general-enclosed>`, e.g. `(unknown-feature)` or `foo(bar)`, kept as written. It never matches.
+    GeneralEnclosed(String, Span),
+}
+
+impl MediaCondition {
+    /// The condition as a `<media-in-parens>`, wrapping `not`, `and` and `or` in parentheses
+    fn to_string_in_parens(&self) -> String {
+        match self {
+            MediaCondition::Feature(_) | MediaCondition::GeneralEnclosed(..) => self.to_string(),
+            _ => format!("({})", self.to_string()),
+        }
+    }
+}
+
+impl ToString for MediaCondition {
+    fn to_string(&self) -> String {
+        match self {
+            MediaCondition::Feature(feature) => feature.to_string(),
+            MediaCondition::Not(condition) => format!("not {}", condition.to_string_in_parens()),
+            MediaCondition::And(conditions) => conditions
+                .iter()
+                .map(|condition| condition.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" and "),
+            MediaCondition::Or(conditions) => conditions
+                .iter()
+                .map(|condition| condition.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" or "),
+            MediaCondition::GeneralEnclosed(text, _) => text.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaFeature {
+    pub kind: MediaFeatureKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaFeatureKind {
+    /// e.g. `(max-width: 600px)`
+    Plain {
+        name: String,
+        value: MediaFeatureValue,
+    },
+    /// e.g. `(hover)`
+    Boolean { name: String },
+    /// e.g. `(width >= 600px)`, `(600px <= width)` or `(400px < width <= 800px)`.
+    /// `start` is the comparison written before the name, `end` the one written after it.
+    Range {
+        name: String,
+        start: Option<(MediaFeatureValue, MediaComparison)>,
+        end: Option<(MediaComparison, MediaFeatureValue)>,
+    },
+}
+
+impl MediaFeatureKind {
+    pub fn name(&self) -> &str {
+        match self {
+            MediaFeatureKind::Plain { name, .. }
+            | MediaFeatureKind::Boolean { name }
+            | MediaFeatureKind::Range { name, .. } => name,
+        }
+    }
+}
+
+impl ToString for MediaFeature {
+    fn to_string(&self) -> String {
+        match &self.kind {
+            MediaFeatureKind::Plain { name, value } => format!("({}: {})", name, value.to_string()),
+            MediaFeatureKind::Boolean { name } => format!("({})", name),
+            MediaFeatureKind::Range { name, start, end } => {
+                let mut parts = Vec::new();
+                if let Some((value, comparison)) = start {
+                    parts.push(value.to_string());
+                    parts.push(comparison.to_string());
+                }
+                parts.push(name.clone());
+                if let Some((comparison, value)) = end {
+                    parts.push(comparison.to_string());
+                    parts.push(value.to_string());
+                }
+                format!("({})", parts.join(" "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaFeatureValue {
+    /// a number, dimension or ident
+    Literal(LiteralValue),
+    /// e.g. `16/9`
+    Ratio(Number, Number),
+}
+
+impl ToString for MediaFeatureValue {
+    fn to_string(&self) -> String {
+        match self {
+            MediaFeatureValue::Literal(value) => value.to_string(),
+            MediaFeatureValue::Ratio(numerator, denominator) => {
+                format!("{}/{}", numerator.to_string(), denominator.to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaComparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+impl ToString for MediaComparison {
+    fn to_string(&self) -> String {
+        match self {
+            MediaComparison::Lt => "<",
+            MediaComparison::Le => "<=",
+            MediaComparison::Gt => ">",
+            MediaComparison::Ge => ">=",
+            MediaComparison::Eq => "=",
+        }
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SupportsRule {
+    pub condition: SupportsCondition,
+    pub rules: Vec<Rule>,
+    pub span: Span,
+}
+
+impl ToString for SupportsRule {
+    fn to_string(&self) -> String {
+        format!(
+            "@supports {} {}",
+            self.condition.to_string(),
+            block_to_string(&[], &self.rules)
+        )
+    }
+}
+
+/// e.g. `(display: grid) and (not selector(:has(a)))`
+#[derive(Debug, Clone)]
+pub enum SupportsCondition {
+    /// e.g. `(display: grid)`
+    Declaration(SupportsDeclaration),
+    /// e.g. `selector(a > b)`
+    Selector(Selector, Span),
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    /// `<general-enclosed>`, e.g. `font-tech(color-COLRv1)` or `(unknown thing)`, kept as written. It never matches.
+    GeneralEnclosed(String, Span),
+}
+
+impl SupportsCondition {
+    /// The condition as a `<supports-in-parens>`, wrapping `not`, `and` and `or` in parentheses
+    fn to_string_in_parens(&self) -> String {
+        match self {
+            SupportsCondition::Not(_) | SupportsCondition::And(_) | SupportsCondition::Or(_) => {
+                format!("({})", self.to_string())
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl ToString for SupportsCondition {
+    fn to_string(&self) -> String {
+        match self {
+            SupportsCondition::Declaration(declaration) => {
+                format!("({}: {})", declaration.property, declaration.value)
+            }
+            SupportsCondition::Selector(selector, _) => {
+                format!("selector({})", selector.to_string())
+            }
+            SupportsCondition::Not(condition) => {
+                format!("not {}", condition.to_string_in_parens())
+            }
+            SupportsCondition::And(conditions) => conditions
+                .iter()
+                .map(|condition| condition.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" and "),
+            SupportsCondition::Or(conditions) => conditions
+                .iter()
+                .map(|condition| condition.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" or "),
+            SupportsCondition::GeneralEnclosed(text, _) => text.clone(),
+        }
+    }
+}
+
+/// A declaration tested by `@supports`, e.g. `(display: grid)`.
+/// The value may be any sequence of component values, so it is kept as written.
+#[derive(Debug, Clone)]
+pub struct SupportsDeclaration {
+    pub property: String,
+    pub value: String,
+    pub span: Span,
+}
+
+/// e.g. `@keyframes fade { from { opacity: 0; } to { opacity: 1; } }`
+#[derive(Debug, Clone)]
+pub struct KeyframesRule {
+    pub name: String,
+    /// The vendor prefix of the at-keyword, e.g. `-webkit-` for `@-webkit-keyframes`
+    pub prefix: Option<String>,
+    pub keyframes: Vec<Keyframe>,
+    pub span: Span,
+}
+
+impl ToString for KeyframesRule {
+    fn to_string(&self) -> String {
+        // CSS-wide keywords, `none` and names which are not identifiers must be quoted
+        let is_ident = self
+            .name
+            .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '-')
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || !c.is_ascii())
+            && ![
+                "none",
+                "initial",
+                "inherit",
+                "unset",
+                "revert",
+                "revert-layer",
+                "default",
+            ]
+            .iter()
+            .any(|reserved| self.name.eq_ignore_ascii_case(reserved));
+        let name = if is_ident {
+            self.name.clone()
+        } else {
+            string_to_string(&self.name)
+        };
+        let keyframes: Vec<String> = self
+            .keyframes
+            .iter()
+            .map(|keyframe| keyframe.to_string())
+            .collect();
+        format!(
+            "@{}keyframes {} {{ {} }}",
+            self.prefix.as_deref().unwrap_or(""),
+            name,
+            keyframes.join(" ")
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    pub selectors: Vec<KeyframeSelector>,
+    pub declarations: Vec<Declaration>,
+    pub span: Span,
+}
+
+impl ToString for Keyframe {
+    fn to_string(&self) -> String {
+        let selectors: Vec<String> = self
+            .selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect();
+        format!(
+            "{} {}",
+            selectors.join(", "),
+            block_to_string(&self.declarations, &[])
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyframeSelector {
+    pub kind: KeyframeSelectorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyframeSelectorKind {
+    From,
+    To,
+    /// e.g. `50%`
+    Percentage(f64),
+}
+
+impl KeyframeSelectorKind {
+    /// The offset into the animation, `from` being `0` and `to` being `100`
+    pub fn percentage(&self) -> f64 {
+        match self {
+            KeyframeSelectorKind::From => 0.0,
+            KeyframeSelectorKind::To => 100.0,
+            KeyframeSelectorKind::Percentage(percentage) => *percentage,
+        }
+    }
+}
+
+impl ToString for KeyframeSelector {
+    fn to_string(&self) -> String {
+        match self.kind {
+            KeyframeSelectorKind::From => "from".to_string(),
+            KeyframeSelectorKind::To => "to".to_string(),
+            KeyframeSelectorKind::Percentage(percentage) => format!("{}%", percentage),
+        }
+    }
+}
+
+/// e.g. `@font-face { font-family: Inter; font-display: swap; }`
+#[derive(Debug, Clone)]
+pub struct FontFaceRule {
+    pub descriptors: Vec<Declaration>,
+    pub span: Span,
+}
+
+impl ToString for FontFaceRule {
+    fn to_string(&self) -> String {
+        format!("@font-face {}", block_to_string(&self.descriptors, &[]))
+    }
+}
+
+/// e.g. `@page :first { margin: 1in; @top-center { content: none; } }`
+#[derive(Debug, Clone)]
+pub struct PageRule {
+    /// Empty when the rule applies to every page
+    pub selectors: Vec<PageSelector>,
+    pub descriptors: Vec<Declaration>,
+    pub margin_rules: Vec<PageMarginRule>,
+    pub span: Span,
+}
+
+impl ToString for PageRule {
+    fn to_string(&self) -> String {
+        let selectors: Vec<String> = self
+            .selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect();
+        let mut items: Vec<String> = self
+            .descriptors
+            .iter()
+            .map(|descriptor| descriptor.to_string())
+            .collect();
+        items.extend(self.margin_rules.iter().map(|margin_rule| {
+            format!(
+                "@{} {}",
+                margin_rule.name,
+                block_to_string(&margin_rule.descriptors, &[])
+            )
+        }));
+        let block = if items.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", items.join(" "))
+        };
+        if selectors.is_empty() {
+            format!("@page {}", block)
+        } else {
+            format!("@page {} {}", selectors.join(", "), block)
+        }
+    }
+}
+
+/// e.g. `toc:first`
+#[derive(Debug, Clone)]
+pub struct PageSelector {
+    pub name: Option<String>,
+    /// e.g. `first` for `:first`
+    pub pseudo_pages: Vec<String>,
+    pub span: Span,
+}
+
+impl ToString for PageSelector {
+    fn to_string(&self) -> String {
+        let mut text = self.name.clone().unwrap_or_default();
+        for pseudo_page in &self.pseudo_pages {
+            text.push(':');
+            text.push_str(pseudo_page);
+        }
+        text
+    }
+}
+
+/// e.g. `@top-left { content: counter(page); }`
+#[derive(Debug, Clone)]
+pub struct PageMarginRule {
+    /// The at-keyword without `@`, e.g. `top-left`
+    pub name: String,
+    pub descriptors: Vec<Declaration>,
+    pub span: Span,
+}
+
+/// e.g. `@counter-style thumbs { system: cyclic; suffix: " "; }`
+#[derive(Debug, Clone)]
+pub struct CounterStyleRule {
+    pub name: String,
+    pub descriptors: Vec<Declaration>,
+    pub span: Span,
+}
+
+impl ToString for CounterStyleRule {
+    fn to_string(&self) -> String {
+        format!(
+            "@counter-style {} {}",
+            self.name,
+            block_to_string(&self.descriptors, &[])
+        )
+    }
+}
+
+/// A registered custom property, e.g. `@property --gap { syntax: "<length>"; inherits: false; initial-value: 0px; }`.
+///
+/// `syntax`, `inherits` and `initial_value` are `None` when the descriptor is missing or invalid,
+/// in which case the rule is reported and has no effect.
+#[derive(Debug, Clone)]
+pub struct PropertyRule {
+    /// e.g. `--gap`
+    pub name: String,
+    pub descriptors: Vec<Declaration>,
+    pub syntax: Option<PropertySyntax>,
+    pub inherits: Option<bool>,
+    pub initial_value: Option<Value>,
+    pub span: Span,
+}
+
+impl ToString for PropertyRule {
+    fn to_string(&self) -> String {
+        format!(
+            "@property {} {}",
+            self.name,
+            block_to_string(&self.descriptors, &[])
+        )
+    }
+}
+
+impl PropertyRule {
+    /// Whether the rule registers the property: `syntax` and `inherits` are required,
+    /// and `initial-value` may only be omitted for the universal syntax `*`
+    pub fn is_valid(&self) -> bool {
+        self.syntax.is_some()
+            && self.inherits.is_some()
+            && (self.initial_value.is_some() || self.syntax == Some(PropertySyntax::Universal))
+    }
+}
+
+/// The `syntax` descriptor of `@property`, e.g. `"<length> | auto"`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertySyntax {
+    /// `*`, which accepts any value
+    Universal,
+    /// Alternatives separated by `|`
+    Components(Vec<SyntaxComponent>),
+}
+
+impl ToString for PropertySyntax {
+    fn to_string(&self) -> String {
+        match self {
+            PropertySyntax::Universal => "*".to_string(),
+            PropertySyntax::Components(components) => components
+                .iter()
+                .map(|component| component.to_string())
+                .collect::<Vec<String>>()
+                .join(" | "),
+        }
+    }
+}
+
+/// e.g. `<length>+` or `auto`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxComponent {
+    pub name: SyntaxComponentName,
+    pub multiplier: Option<SyntaxMultiplier>,
+}
+
+impl ToString for SyntaxComponent {
+    fn to_string(&self) -> String {
+        let name = match &self.name {
+            SyntaxComponentName::DataType(data_type) => format!("<{}>", data_type),
+            SyntaxComponentName::Ident(ident) => ident.clone(),
+        };
+        match self.multiplier {
+            Some(SyntaxMultiplier::SpaceSeparated) => name + "+",
+            Some(SyntaxMultiplier::CommaSeparated) => name + "#",
+            None => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxComponentName {
+    /// e.g. `length` for `<length>`
+    DataType(String),
+    /// a keyword which is matched literally, e.g. `auto`
+    Ident(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxMultiplier {
+    /// `+`
+    SpaceSeparated,
+    /// `#`
+    CommaSeparated,
+}
+
+/// e.g. `@layer reset, base, components;`, which declares the order of the layers
+#[derive(Debug, Clone)]
+pub struct LayerStatementRule {
+    pub names: Vec<LayerName>,
+    pub span: Span,
+}
+
+impl ToString for LayerStatementRule {
+    fn to_string(&self) -> String {
+        let names: Vec<String> = self.names.iter().map(|name| name.to_string()).collect();
+        format!("@layer {};", names.join(", "))
+    }
+}
+
+/// e.g. `@layer components { .card { padding: 0; } }`
+#[derive(Debug, Clone)]
+pub struct LayerBlockRule {
+    /// `None` for an anonymous layer
+    pub name: Option<LayerName>,
+    pub rules: Vec<Rule>,
+    pub span: Span,
+}
+
+impl ToString for LayerBlockRule {
+    fn to_string(&self) -> String {
+        let block = block_to_string(&[], &self.rules);
+        match &self.name {
+            Some(name) => format!("@layer {} {}", name.to_string(), block),
+            None => format!("@layer {}", block),
+        }
+    }
+}
+
+/// e.g. `components.card`, a layer nested in another one
+#[derive(Debug, Clone)]
+pub struct LayerName {
+    pub names: Vec<String>,
+    pub span: Span,
+}
+
+impl ToString for LayerName {
+    fn to_string(&self) -> String {
+        self.names.join(".")
+    }
+}
+
+/// e.g. `@import url("theme.css") layer(base) supports(display: grid) screen;`
+#[derive(Debug, Clone)]
+pub struct ImportRule {
+    pub url: String,
+    /// The span of the URL or string, e.g. `url("theme.css")`
+    pub url_span: Span,
+    pub layer: Option<ImportLayer>,
+    pub supports: Option<SupportsCondition>,
+    /// Empty when the import is unconditional
+    pub media: MediaQueryList,
+    pub span: Span,
+}
+
+impl ToString for ImportRule {
+    fn to_string(&self) -> String {
+        let mut text = format!("@import {}", string_to_string(&self.url));
+        match &self.layer {
+            Some(ImportLayer::Anonymous(_)) => text.push_str(" layer"),
+            Some(ImportLayer::Named(name)) => {
+                text.push_str(&format!(" layer({})", name.to_string()))
+            }
+            None => {}
+        }
+        match &self.supports {
+            Some(SupportsCondition::Declaration(declaration)) => text.push_str(&format!(
+                " supports({}: {})",
+                declaration.property, declaration.value
+            )),
+            Some(condition) => text.push_str(&format!(" supports({})", condition.to_string())),
+            None => {}
+        }
+        let media = self.media.to_string();
+        if !media.is_empty() {
+            text.push(' ');
+            text.push_str(&media);
+        }
+        text + ";"
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportLayer {
+    /// `layer`
+    Anonymous(Span),
+    /// e.g. `layer(base)`
+    Named(LayerName),
+}
+
+/// e.g. `@charset "UTF-8";`, which only has an effect when the stylesheet is decoded
+#[derive(Debug, Clone)]
+pub struct CharsetRule {
+    pub encoding: String,
+    pub span: Span,
+}
+
+impl ToString for CharsetRule {
+    fn to_string(&self) -> String {
+        format!("@charset {};", string_to_string(&self.encoding))
+    }
+}
+
+/// e.g. `@namespace svg url(http://www.w3.org/2000/svg);`
+#[derive(Debug, Clone)]
+pub struct NamespaceRule {
+    /// `None` for the default namespace
+    pub prefix: Option<String>,
+    pub url: String,
+    pub url_span: Span,
+    pub span: Span,
+}
+
+impl ToString for NamespaceRule {
+    fn to_string(&self) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("@namespace {} {};", prefix, string_to_string(&self.url)),
+            None => format!("@namespace {};", string_to_string(&self.url)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContainerRule {
+    pub condition: ContainerCondition,
     pub rules: Vec<Rule>,
+    pub span: Span,
+}
+
+impl ToString for ContainerRule {
+    fn to_string(&self) -> String {
+        format!(
+            "@container {} {}",
+            self.condition.to_string(),
+            block_to_string(&[], &self.rules)
+        )
+    }
 }
 
+/// e.g. `card (min-width: 400px)`, which queries the nearest ancestor container named `card`.
+/// At least one of `name` and `query` is present.
 #[derive(Debug, Clone)]
-pub enum Rule {
-    StyleRule(StyleRule),
-    // MediaQuery, TODO
+pub struct ContainerCondition {
+    pub name: Option<String>,
+    pub query: Option<ContainerQuery>,
+    pub span: Span,
+}
+
+impl ToString for ContainerCondition {
+    fn to_string(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(name) = &self.name {
+            parts.push(name.clone());
+        }
+        if let Some(query) = &self.query {
+            parts.push(query.to_string());
+        }
+        parts.join(" ")
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct StyleRule {
-    pub selector_text: String,
-    pub selectors: Vec<Selector>,
-    pub declarations: Vec<Declaration>,
+pub enum ContainerQuery {
+    /// A size feature, e.g. `(min-width: 400px)` or `(inline-size > 30em)`, in the same syntax as media features
+    Size(MediaFeature),
+    /// e.g. `style(--theme: dark)`
+    Style(StyleQuery, Span),
+    Not(Box<ContainerQuery>),
+    And(Vec<ContainerQuery>),
+    Or(Vec<ContainerQuery>),
+    /// `<general-enclosed>`, e.g. `scroll-state(stuck: top)`, kept as written. It never matches.
+    GeneralEnclosed(String, Span),
+}
+
+impl ContainerQuery {
+    /// The query as a `<query-in-parens>`, wrapping `not`, `and` and `or` in parentheses
+    fn to_string_in_parens(&self) -> String {
+        match self {
+            ContainerQuery::Not(_) | ContainerQuery::And(_) | ContainerQuery::Or(_) => {
+                format!("({})", self.to_string())
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl ToString for ContainerQuery {
+    fn to_string(&self) -> String {
+        match self {
+            ContainerQuery::Size(feature) => feature.to_string(),
+            ContainerQuery::Style(query, _) => format!("style({})", query.to_string()),
+            ContainerQuery::Not(query) => format!("not {}", query.to_string_in_parens()),
+            ContainerQuery::And(queries) => queries
+                .iter()
+                .map(|query| query.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" and "),
+            ContainerQuery::Or(queries) => queries
+                .iter()
+                .map(|query| query.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" or "),
+            ContainerQuery::GeneralEnclosed(text, _) => text.clone(),
+        }
+    }
+}
+
+/// The contents of `style()` in a container query
+#[derive(Debug, Clone)]
+pub enum StyleQuery {
+    Feature(StyleFeature),
+    Not(Box<StyleQuery>),
+    And(Vec<StyleQuery>),
+    Or(Vec<StyleQuery>),
+    GeneralEnclosed(String, Span),
+}
+
+impl StyleQuery {
+    /// The query as a `<style-in-parens>`, wrapping everything but `<general-enclosed>` in parentheses
+    fn to_string_in_parens(&self) -> String {
+        match self {
+            StyleQuery::GeneralEnclosed(..) => self.to_string(),
+            _ => format!("({})", self.to_string()),
+        }
+    }
+}
+
+impl ToString for StyleQuery {
+    fn to_string(&self) -> String {
+        match self {
+            StyleQuery::Feature(feature) => match &feature.value {
+                Some(value) => format!("{}: {}", feature.property, value),
+                None => feature.property.clone(),
+            },
+            StyleQuery::Not(query) => format!("not {}", query.to_string_in_parens()),
+            StyleQuery::And(queries) => queries
+                .iter()
+                .map(|query| query.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" and "),
+            StyleQuery::Or(queries) => queries
+                .iter()
+                .map(|query| query.to_string_in_parens())
+                .collect::<Vec<String>>()
+                .join(" or "),
+            StyleQuery::GeneralEnclosed(text, _) => text.clone(),
+        }
+    }
+}
+
+/// e.g. `--theme: dark`, or `--theme` which tests that the property has a value other than its initial value
+#[derive(Debug, Clone)]
+pub struct StyleFeature {
+    pub property: String,
+    /// The value as written
+    pub value: Option<String>,
+    pub span: Span,
+}
+
+/// An at-rule the parser does not know, e.g. `@tailwind base;` or `@scope (.card) { img { border: 0; } }`,
+/// kept as written so that it can be handled by other tools:
+///
+/// ```
+/// use emox_parser::bnf::Rule;
+/// use emox_parser::lexer::Lexer;
+/// use emox_parser::parser::Parser;
+/// use emox_parser::token::TokenType;
+///
+/// let mut lexer = Lexer::new("@tailwind base;".to_string());
+/// lexer.build();
+/// let (stylesheet, _) = Parser::new(lexer.tokens()).parse();
+/// let Rule::Unknown(unknown_at_rule) = &stylesheet.rules[0] else {
+///     panic!("expected unknown at-rule");
+/// };
+/// let layer = unknown_at_rule
+///     .prelude_tokens
+///     .iter()
+///     .find(|token| token.token_type == TokenType::Ident);
+/// assert_eq!(layer.map(|token| token.value.as_str()), Some("base"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnknownAtRule {
+    /// The name without `@`, e.g. `tailwind`
+    pub name: String,
+    /// The tokens between the at-keyword and the block or `;`, including whitespace
+    pub prelude_tokens: Vec<Token>,
+    /// The tokens inside `{}`, or `None` for a statement at-rule ending with `;`
+    pub block: Option<Vec<Token>>,
+    pub span: Span,
+}
+
+impl UnknownAtRule {
+    /// The prelude as written, without surrounding whitespace, e.g. `(.card)`
+    pub fn prelude(&self) -> String {
+        concat_lexemes(&self.prelude_tokens).trim().to_string()
+    }
 }
 
-// #[derive(Debug, Clone)]
-// pub struct MediaRule {
-//     pub condition: MediaCondition,
-//     pub rules: Vec<Rule>,
-// }
+impl ToString for UnknownAtRule {
+    fn to_string(&self) -> String {
+        let prelude = concat_lexemes(&self.prelude_tokens);
+        match &self.block {
+            Some(block) => format!("@{}{}{{{}}}", self.name, prelude, concat_lexemes(block)),
+            None => format!("@{}{};", self.name, prelude),
+        }
+    }
+}
 
-// pub struct MediaCondition {
-//     pub ident: String,
-//     pub feature: Option<MediaFeature>,
-// }
+fn concat_lexemes(tokens: &[Token]) -> String {
+    tokens.iter().map(|token| token.lexeme.as_str()).collect()
+}
 
+/// A [complex selector](https://www.w3.org/TR/selectors-4/#complex), e.g. `ul > li.active a`
 #[derive(Debug, Clone)]
 pub struct Selector {
-    pub simple_selectors: Vec<SimpleSelector>,
+    pub compounds: Vec<CompoundSelector>,
+    pub span: Span,
 }
 
 impl ToString for Selector {
     fn to_string(&self) -> String {
-        self.simple_selectors
-            .iter()
-            .map(|simple_selector| simple_selector.to_string())
-            .collect::<Vec<String>>()
-            .join(" ")
+        let mut text = String::new();
+        for (index, compound) in self.compounds.iter().enumerate() {
+            match compound.combinator {
+                Some(Combinator::Descendant) | None if index > 0 => text.push(' '),
+                Some(Combinator::Descendant) | None => {}
+                Some(combinator) if index > 0 => {
+                    text.push_str(&format!(" {} ", combinator.to_string()))
+                }
+                Some(combinator) => text.push_str(&format!("{} ", combinator.to_string())),
+            }
+            text.push_str(&compound.to_string());
+        }
+        text
+    }
+}
+
+/// A sequence of simple selectors which are not separated by a combinator, e.g. `a.btn#main:hover::before`
+#[derive(Debug, Clone)]
+pub struct CompoundSelector {
+    /// The combinator joining the compound to the previous one, e.g. `>` in `ul > li`.
+    /// The first compound only has one in a relative selector, e.g. `> .title` nested in a style rule.
+    pub combinator: Option<Combinator>,
+    /// The type or universal selector, which can only come first, e.g. `a` in `a.btn`
+    pub type_selector: Option<TypeSelector>,
+    /// The selectors following the type selector, e.g. `.btn`, `#main`, `:hover` and `::before`
+    pub subclass_selectors: Vec<SimpleSelector>,
+    pub span: Span,
+}
+
+impl ToString for CompoundSelector {
+    fn to_string(&self) -> String {
+        let mut text = match &self.type_selector {
+            Some(type_selector) => type_selector.to_string(),
+            None => String::new(),
+        };
+        for subclass_selector in &self.subclass_selectors {
+            text.push_str(&subclass_selector.to_string());
+        }
+        text
+    }
+}
+
+/// [Combinators](https://www.w3.org/TR/selectors-4/#combinators)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    /// whitespace, e.g. `div span`
+    Descendant,
+    /// `>`
+    Child,
+    /// `+`
+    NextSibling,
+    /// `~`
+    SubsequentSibling,
+    /// `||`, e.g. `col.selected || td`
+    Column,
+}
+
+impl ToString for Combinator {
+    fn to_string(&self) -> String {
+        match self {
+            Combinator::Descendant => " ",
+            Combinator::Child => ">",
+            Combinator::NextSibling => "+",
+            Combinator::SubsequentSibling => "~",
+            Combinator::Column => "||",
+        }
+        .to_string()
     }
 }
 
+/// A simple selector which can follow the type selector in a compound selector
 #[derive(Debug, Clone)]
 pub enum SimpleSelector {
-    Type(TypeSelector),
     Id(IdSelector),
     Class(ClassSelector),
+    Attribute(AttributeSelector),
     PseudoClass(PseudoClassSelector),
     PseudoElement(PseudoElementSelector),
+    Nesting(NestingSelector),
+}
+
+impl SimpleSelector {
+    pub fn span(&self) -> Span {
+        match self {
+            SimpleSelector::Id(id_selector) => id_selector.span,
+            SimpleSelector::Class(class_selector) => class_selector.span,
+            SimpleSelector::Attribute(attribute_selector) => attribute_selector.span,
+            SimpleSelector::PseudoClass(pseudo_class_selector) => pseudo_class_selector.span,
+            SimpleSelector::PseudoElement(pseudo_element_selector) => pseudo_element_selector.span,
+            SimpleSelector::Nesting(nesting_selector) => nesting_selector.span,
+        }
+    }
 }
 
 impl ToString for SimpleSelector {
     fn to_string(&self) -> String {
         match self {
-            SimpleSelector::Type(type_selector) => type_selector.element.clone(),
-            SimpleSelector::Id(id_selector) => id_selector.id.to_string(),
-            SimpleSelector::Class(class_selector) => class_selector.class_name.to_string(),
-            SimpleSelector::PseudoClass(pseudo_class_selector) => {
-                pseudo_class_selector.ident.to_string()
-            }
+            SimpleSelector::Id(id_selector) => id_selector.raw.clone(),
+            SimpleSelector::Class(class_selector) => class_selector.raw.clone(),
+            SimpleSelector::Attribute(attribute_selector) => attribute_selector.to_string(),
+            SimpleSelector::PseudoClass(pseudo_class_selector) => pseudo_class_selector.to_string(),
             SimpleSelector::PseudoElement(pseudo_element_selector) => {
-                pseudo_element_selector.ident.to_string()
+                pseudo_element_selector.to_string()
             }
+            SimpleSelector::Nesting(_) => "&".to_string(),
         }
     }
 }
 
+/// A [type selector](https://www.w3.org/TR/selectors-4/#type-selectors) such as `div` or `svg|circle`,
+/// or the [universal selector](https://www.w3.org/TR/selectors-4/#the-universal-selector) `*`
 #[derive(Debug, Clone)]
 pub struct TypeSelector {
+    /// The namespace prefix, e.g. `svg` in `svg|circle`.
+    /// `*` matches any namespace, and an empty prefix (`|circle`) only elements without one.
+    pub namespace: Option<String>,
+    /// The element name with escapes resolved, or `*` for the universal selector
     pub element: String,
+    /// The selector as written, e.g. `svg|\63 ircle`
+    pub raw: String,
+    pub span: Span,
+}
+
+impl TypeSelector {
+    /// Whether this is the universal selector, e.g. `*` or `svg|*`
+    pub fn is_universal(&self) -> bool {
+        self.element == "*"
+    }
+}
+
+impl ToString for TypeSelector {
+    fn to_string(&self) -> String {
+        self.raw.clone()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct IdSelector {
+    /// The id with escapes resolved and without `#`, e.g. `a:b` for `#a\:b`
     pub id: String,
+    /// The selector as written, e.g. `#a\:b`
+    pub raw: String,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct ClassSelector {
+    /// The class name with escapes resolved and without `.`, e.g. `sm:p-4` for `.sm\:p-4`
     pub class_name: String,
+    /// The selector as written, e.g. `.sm\:p-4`
+    pub raw: String,
+    pub span: Span,
+}
+
+/// An [attribute selector](https://www.w3.org/TR/selectors-4/#attribute-selectors), e.g. `[data-state="open" i]`
+#[derive(Debug, Clone)]
+pub struct AttributeSelector {
+    /// The namespace prefix, e.g. `xlink` in `[xlink|href]`.
+    /// `*` matches any namespace, and an empty prefix (`[|href]`) only attributes without one.
+    pub namespace: Option<String>,
+    pub name: String,
+    /// `None` when only the presence of the attribute is checked, e.g. `[disabled]`
+    pub matcher: Option<AttributeMatcher>,
+    pub span: Span,
+}
+
+impl ToString for AttributeSelector {
+    fn to_string(&self) -> String {
+        let mut text = "[".to_string();
+        if let Some(namespace) = &self.namespace {
+            text.push_str(namespace);
+            text.push('|');
+        }
+        text.push_str(&self.name);
+        if let Some(matcher) = &self.matcher {
+            text.push_str(&matcher.operator.to_string());
+            if matcher.quoted {
+                text.push_str(&format!(
+                    "\"{}\"",
+                    matcher.value.replace('\\', "\\\\").replace('"', "\\\"")
+                ));
+            } else {
+                text.push_str(&matcher.value);
+            }
+            match matcher.modifier {
+                Some(AttributeModifier::CaseInsensitive) => text.push_str(" i"),
+                Some(AttributeModifier::CaseSensitive) => text.push_str(" s"),
+                None => {}
+            }
+        }
+        text.push(']');
+        text
+    }
+}
+
+/// The comparison of an attribute selector, e.g. `^="https:"` in `a[href^="https:"]`
+#[derive(Debug, Clone)]
+pub struct AttributeMatcher {
+    pub operator: AttributeOperator,
+    /// The value with escapes resolved, which is either an identifier or a string
+    pub value: String,
+    /// Whether the value was written as a string
+    pub quoted: bool,
+    pub modifier: Option<AttributeModifier>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// `=`, exactly the value
+    Equals,
+    /// `~=`, the value is one of a whitespace separated list of words
+    Includes,
+    /// `|=`, exactly the value or the value followed by `-`, e.g. `[lang|=en]` for `en-US`
+    DashMatch,
+    /// `^=`, begins with the value
+    Prefix,
+    /// `$=`, ends with the value
+    Suffix,
+    /// `*=`, contains the value
+    Substring,
+}
+
+impl ToString for AttributeOperator {
+    fn to_string(&self) -> String {
+        match self {
+            AttributeOperator::Equals => "=",
+            AttributeOperator::Includes => "~=",
+            AttributeOperator::DashMatch => "|=",
+            AttributeOperator::Prefix => "^=",
+            AttributeOperator::Suffix => "$=",
+            AttributeOperator::Substring => "*=",
+        }
+        .to_string()
+    }
+}
+
+/// The flag which overrides how the value of an attribute selector is compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeModifier {
+    /// `i`, ASCII case-insensitively
+    CaseInsensitive,
+    /// `s`, case-sensitively even if the document language compares the attribute case-insensitively
+    CaseSensitive,
 }
 
 #[derive(Debug, Clone)]
 pub struct PseudoClassSelector {
+    /// The name with the leading colon, e.g. `:hover`, or `:not` for `:not(.a)`
     pub ident: String,
+    /// The argument of a functional pseudo-class, e.g. `.a` in `:not(.a)`
+    pub argument: Option<PseudoClassArgument>,
+    pub span: Span,
+}
+
+impl PseudoClassSelector {
+    /// The selectors in the argument, e.g. `.a, .b` in `:is(.a, .b)` or `.item` in `:nth-child(odd of .item)`
+    pub fn selectors(&self) -> &[Selector] {
+        match &self.argument {
+            Some(PseudoClassArgument::Selectors(selectors))
+            | Some(PseudoClassArgument::RelativeSelectors(selectors)) => selectors,
+            Some(PseudoClassArgument::Nth(NthArgument {
+                selectors: Some(selectors),
+                ..
+            })) => selectors,
+            _ => &[],
+        }
+    }
+}
+
+impl ToString for PseudoClassSelector {
+    fn to_string(&self) -> String {
+        match &self.argument {
+            Some(argument) => format!("{}({})", self.ident, argument.to_string()),
+            None => self.ident.clone(),
+        }
+    }
+}
+
+/// The argument of a [functional pseudo-class](https://www.w3.org/TR/selectors-4/#logical-combination),
+/// or of a functional pseudo-element such as `::slotted()`
+#[derive(Debug, Clone)]
+pub enum PseudoClassArgument {
+    /// A selector list, for `:is()`, `:where()` and `:not()`
+    Selectors(Vec<Selector>),
+    /// A relative selector list, for `:has()`, e.g. `> img` in `:has(> img)`
+    RelativeSelectors(Vec<Selector>),
+    /// For `:nth-child()` and the like, e.g. `2n+1 of .item`
+    Nth(NthArgument),
+    /// The argument of any other functional pseudo-class as written, e.g. `en` in `:lang(en)`
+    Raw(String),
+}
+
+impl ToString for PseudoClassArgument {
+    fn to_string(&self) -> String {
+        match self {
+            PseudoClassArgument::Selectors(selectors)
+            | PseudoClassArgument::RelativeSelectors(selectors) => selectors
+                .iter()
+                .map(|selector| selector.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            PseudoClassArgument::Nth(nth) => nth.to_string(),
+            PseudoClassArgument::Raw(text) => text.clone(),
+        }
+    }
+}
+
+/// An [An+B](https://www.w3.org/TR/css-syntax-3/#anb-microsyntax) expression with an optional selector list,
+/// which matches the elements whose index is `a * n + b` for some `n >= 0`, e.g. `2n+1` for odd elements
+#[derive(Debug, Clone)]
+pub struct NthArgument {
+    pub a: i32,
+    pub b: i32,
+    /// The selectors the counted siblings must match, e.g. `.item` in `:nth-child(2n of .item)`
+    pub selectors: Option<Vec<Selector>>,
+}
+
+impl ToString for NthArgument {
+    /// Serialize in the canonical form, e.g. `2n+1` for `odd`
+    fn to_string(&self) -> String {
+        let mut text = match self.a {
+            0 => String::new(),
+            1 => "n".to_string(),
+            -1 => "-n".to_string(),
+            a => format!("{}n", a),
+        };
+        if self.a == 0 {
+            text.push_str(&self.b.to_string());
+        } else if self.b > 0 {
+            text.push_str(&format!("+{}", self.b));
+        } else if self.b < 0 {
+            text.push_str(&self.b.to_string());
+        }
+        if let Some(selectors) = &self.selectors {
+            let selectors: Vec<String> = selectors
+                .iter()
+                .map(|selector| selector.to_string())
+                .collect();
+            text.push_str(&format!(" of {}", selectors.join(", ")));
+        }
+        text
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct PseudoElementSelector {
+    /// The name as written with the leading colons, e.g. `::before`,
+    /// or `:before` for the pseudo-elements which can also be written with a single colon
     pub ident: String,
+    /// The argument of a functional pseudo-element, e.g. `.a` in `::slotted(.a)` or `label` in `::part(label)`
+    pub argument: Option<PseudoClassArgument>,
+    pub span: Span,
+}
+
+impl PseudoElementSelector {
+    /// The selectors in the argument, e.g. `.a` in `::slotted(.a)`
+    pub fn selectors(&self) -> &[Selector] {
+        match &self.argument {
+            Some(PseudoClassArgument::Selectors(selectors)) => selectors,
+            _ => &[],
+        }
+    }
+}
+
+impl ToString for PseudoElementSelector {
+    fn to_string(&self) -> String {
+        match &self.argument {
+            Some(argument) => format!("{}({})", self.ident, argument.to_string()),
+            None => self.ident.clone(),
+        }
+    }
+}
+
+/// `&`, which stands for the elements matched by the parent style rule
+#[derive(Debug, Clone)]
+pub struct NestingSelector {
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct Declaration {
     pub property: String,
     pub value: Value,
+    /// Whether the declaration ends with `!important`, which makes it win over normal declarations
+    pub important: bool,
     pub css_text: String,
+    pub span: Span,
+}
+
+impl ToString for Declaration {
+    fn to_string(&self) -> String {
+        format!(
+            "{}: {}{};",
+            self.property,
+            self.value.to_string(),
+            if self.important { " !important" } else { "" }
+        )
+    }
 }
 
+/// The value of a declaration, e.g. `12px/1.5 sans-serif` in `font: 12px/1.5 sans-serif`
 #[derive(Debug, Clone)]
 pub struct Value {
-    pub value: LiteralValue,
+    /// The component values with the separators between them, without leading or trailing whitespace
+    pub components: Vec<ComponentValue>,
+    pub span: Span,
+}
+
+impl Value {
+    /// The literal if the value consists of just one, e.g. `true` for `inherits: true`
+    pub fn literal(&self) -> Option<&LiteralValue> {
+        match self.components.as_slice() {
+            [ComponentValue::Literal(literal, _)] => Some(literal),
+            _ => None,
+        }
+    }
 }
+
 impl ToString for Value {
     fn to_string(&self) -> String {
-        match &self.value {
-            LiteralValue::Ident(ident) => ident.clone(),
-            LiteralValue::Number(number) => number.clone(),
-            LiteralValue::Dimension(dimension) => dimension.clone(),
-            LiteralValue::Percentage(percentage) => percentage.clone(),
+        self.components
+            .iter()
+            .map(|component| component.to_string())
+            .collect()
+    }
+}
+
+/// A [component value](https://www.w3.org/TR/css-syntax-3/#component-value) in a declaration value,
+/// or a separator between them
+#[derive(Debug, Clone)]
+pub enum ComponentValue {
+    Literal(LiteralValue, Span),
+    /// e.g. `#fff`
+    Hash(String, Span),
+    /// The URL of an unquoted `url()`, e.g. `a.png` for `url(a.png)`
+    Url(String, Span),
+    /// e.g. `var(--gap)` or `rgb(0 0 0 / 50%)`
+    Function(FunctionValue),
+    /// A math function, e.g. `calc(100% - 2 * var(--gap))`
+    Math(MathFunction),
+    /// Any other component value as written, e.g. `+` or `[full-start]`
+    Raw(String, Span),
+    /// Whitespace between component values, e.g. in `0 auto`
+    Whitespace(Span),
+    /// e.g. in `opacity 1s, transform 2s`
+    Comma(Span),
+    /// e.g. in `12px/1.5`
+    Slash(Span),
+}
+
+impl ComponentValue {
+    pub fn span(&self) -> Span {
+        match self {
+            ComponentValue::Literal(_, span)
+            | ComponentValue::Hash(_, span)
+            | ComponentValue::Url(_, span)
+            | ComponentValue::Raw(_, span)
+            | ComponentValue::Whitespace(span)
+            | ComponentValue::Comma(span)
+            | ComponentValue::Slash(span) => *span,
+            ComponentValue::Function(function) => function.span,
+            ComponentValue::Math(math_function) => math_function.span,
+        }
+    }
+}
+
+impl ToString for ComponentValue {
+    fn to_string(&self) -> String {
+        match self {
+            ComponentValue::Literal(literal, _) => literal.to_string(),
+            ComponentValue::Hash(hash, _) => hash.clone(),
+            ComponentValue::Url(url, _) => format!("url({})", url),
+            ComponentValue::Function(function) => function.to_string(),
+            ComponentValue::Math(math_function) => math_function.to_string(),
+            ComponentValue::Raw(text, _) => text.clone(),
+            ComponentValue::Whitespace(_) => " ".to_string(),
+            ComponentValue::Comma(_) => ",".to_string(),
+            ComponentValue::Slash(_) => "/".to_string(),
+        }
+    }
+}
+
+/// A function other than a math function, with its arguments parsed as component values
+#[derive(Debug, Clone)]
+pub struct FunctionValue {
+    /// e.g. `var`
+    pub name: String,
+    /// The component values between the parentheses, without leading or trailing whitespace
+    pub arguments: Vec<ComponentValue>,
+    pub span: Span,
+}
+
+impl ToString for FunctionValue {
+    fn to_string(&self) -> String {
+        let arguments: String = self
+            .arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect();
+        format!("{}({})", self.name, arguments)
+    }
+}
+
+/// A [math function](https://www.w3.org/TR/css-values-4/#math), e.g. `clamp(1rem, 2.5vw, 2rem)`
+#[derive(Debug, Clone)]
+pub struct MathFunction {
+    /// e.g. `calc`, `min` or `atan2`
+    pub name: String,
+    /// The comma-separated arguments, e.g. `nearest` and `var(--x)` for `round(nearest, var(--x))`
+    pub arguments: Vec<MathExpression>,
+    pub span: Span,
+}
+
+impl ToString for MathFunction {
+    fn to_string(&self) -> String {
+        let arguments: Vec<String> = self
+            .arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect();
+        format!("{}({})", self.name, arguments.join(", "))
+    }
+}
+
+/// A [calculation](https://www.w3.org/TR/css-values-4/#calc-syntax) in a math function, e.g. `100% - 2 * 8px`.
+/// `*` and `/` bind tighter than `+` and `-`, and operators of the same precedence are left associative.
+#[derive(Debug, Clone)]
+pub enum MathExpression {
+    /// A number, dimension, percentage or keyword, e.g. `8px`, `pi` or `nearest`
+    Literal(LiteralValue, Span),
+    /// A function which is substituted before calculating, e.g. `var(--gap)`
+    Function(FunctionValue),
+    /// A nested math function, e.g. `min(10px, 5vw)` in `calc(min(10px, 5vw) * 2)`
+    Math(MathFunction),
+    /// e.g. `(1px + 2px)` in `calc((1px + 2px) * 3)`
+    Parenthesized(Box<MathExpression>, Span),
+    Binary(MathOperator, Box<MathExpression>, Box<MathExpression>),
+}
+
+impl MathExpression {
+    pub fn span(&self) -> Span {
+        match self {
+            MathExpression::Literal(_, span) | MathExpression::Parenthesized(_, span) => *span,
+            MathExpression::Function(function) => function.span,
+            MathExpression::Math(math_function) => math_function.span,
+            MathExpression::Binary(_, left, right) => left.span().to(right.span()),
+        }
+    }
+}
+
+impl ToString for MathExpression {
+    fn to_string(&self) -> String {
+        match self {
+            MathExpression::Literal(literal, _) => literal.to_string(),
+            MathExpression::Function(function) => function.to_string(),
+            MathExpression::Math(math_function) => math_function.to_string(),
+            MathExpression::Parenthesized(expression, _) => format!("({})", expression.to_string()),
+            MathExpression::Binary(operator, left, right) => format!(
+                "{} {} {}",
+                left.to_string(),
+                operator.to_string(),
+                right.to_string()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl ToString for MathOperator {
+    fn to_string(&self) -> String {
+        match self {
+            MathOperator::Add => "+",
+            MathOperator::Subtract => "-",
+            MathOperator::Multiply => "*",
+            MathOperator::Divide => "/",
         }
+        .to_string()
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum LiteralValue {
     Ident(String),
-    Number(String),     // TODO: Use f64
-    Dimension(String),  // TODO: Use f64 + unit
-    Percentage(String), // TODO: Use f64 + unit
+    Number(Number),
+    Dimension(Dimension),
+    /// A percentage such as `50%`, where the number is `50`
+    Percentage(Number),
+    /// The contents of a string, without quotes and with escapes resolved
+    String(String),
+}
+
+impl ToString for LiteralValue {
+    fn to_string(&self) -> String {
+        match self {
+            LiteralValue::Ident(ident) => ident.clone(),
+            LiteralValue::Number(number) => number.to_string(),
+            LiteralValue::Dimension(dimension) => dimension.to_string(),
+            LiteralValue::Percentage(percentage) => format!("{}%", percentage.to_string()),
+            LiteralValue::String(string) => {
+                format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+        }
+    }
+}
+
+/// A [number](https://www.w3.org/TR/css-values-4/#numbers), e.g. `-0.5` or `1e3`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Number {
+    pub value: f64,
+    /// Whether the number is an `<integer>`, i.e. it is written without a fraction or an exponent
+    pub is_integer: bool,
+    /// The number as written, e.g. `+.50` for a value of `0.5`
+    pub raw: String,
+}
+
+impl ToString for Number {
+    fn to_string(&self) -> String {
+        self.raw.clone()
+    }
+}
+
+/// A number followed by a unit, e.g. `-0.5em`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dimension {
+    pub number: Number,
+    pub unit: Unit,
+    /// The unit as written, e.g. `PX` for [`Unit::Px`]
+    pub raw_unit: String,
+}
+
+impl ToString for Dimension {
+    fn to_string(&self) -> String {
+        format!("{}{}", self.number.to_string(), self.raw_unit)
+    }
+}
+
+/// A [unit](https://www.w3.org/TR/css-values-4/#dimensions) of a dimension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    // absolute lengths
+    Px,
+    Cm,
+    Mm,
+    Q,
+    In,
+    Pt,
+    Pc,
+    // font-relative lengths
+    Em,
+    Rem,
+    Ex,
+    Rex,
+    Cap,
+    Rcap,
+    Ch,
+    Rch,
+    Ic,
+    Ric,
+    Lh,
+    Rlh,
+    // viewport-percentage lengths
+    Vw,
+    Vh,
+    Vi,
+    Vb,
+    Vmin,
+    Vmax,
+    Svw,
+    Svh,
+    Svi,
+    Svb,
+    Svmin,
+    Svmax,
+    Lvw,
+    Lvh,
+    Lvi,
+    Lvb,
+    Lvmin,
+    Lvmax,
+    Dvw,
+    Dvh,
+    Dvi,
+    Dvb,
+    Dvmin,
+    Dvmax,
+    // container query lengths
+    Cqw,
+    Cqh,
+    Cqi,
+    Cqb,
+    Cqmin,
+    Cqmax,
+    // angles
+    Deg,
+    Grad,
+    Rad,
+    Turn,
+    // durations
+    S,
+    Ms,
+    // frequencies
+    Hz,
+    Khz,
+    // resolutions
+    Dpi,
+    Dpcm,
+    Dppx,
+    X,
+    // flexible lengths
+    Fr,
+    /// A unit CSS does not define, e.g. `foo` in `1foo`, which is kept as written in [`Dimension::raw_unit`]
+    Unknown,
+}
+
+impl ToString for Unit {
+    fn to_string(&self) -> String {
+        match self {
+            Unit::Px => "px",
+            Unit::Cm => "cm",
+            Unit::Mm => "mm",
+            Unit::Q => "q",
+            Unit::In => "in",
+            Unit::Pt => "pt",
+            Unit::Pc => "pc",
+            Unit::Em => "em",
+            Unit::Rem => "rem",
+            Unit::Ex => "ex",
+            Unit::Rex => "rex",
+            Unit::Cap => "cap",
+            Unit::Rcap => "rcap",
+            Unit::Ch => "ch",
+            Unit::Rch => "rch",
+            Unit::Ic => "ic",
+            Unit::Ric => "ric",
+            Unit::Lh => "lh",
+            Unit::Rlh => "rlh",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+            Unit::Vi => "vi",
+            Unit::Vb => "vb",
+            Unit::Vmin => "vmin",
+            Unit::Vmax => "vmax",
+            Unit::Svw => "svw",
+            Unit::Svh => "svh",
+            Unit::Svi => "svi",
+            Unit::Svb => "svb",
+            Unit::Svmin => "svmin",
+            Unit::Svmax => "svmax",
+            Unit::Lvw => "lvw",
+            Unit::Lvh => "lvh",
+            Unit::Lvi => "lvi",
+            Unit::Lvb => "lvb",
+            Unit::Lvmin => "lvmin",
+            Unit::Lvmax => "lvmax",
+            Unit::Dvw => "dvw",
+            Unit::Dvh => "dvh",
+            Unit::Dvi => "dvi",
+            Unit::Dvb => "dvb",
+            Unit::Dvmin => "dvmin",
+            Unit::Dvmax => "dvmax",
+            Unit::Cqw => "cqw",
+            Unit::Cqh => "cqh",
+            Unit::Cqi => "cqi",
+            Unit::Cqb => "cqb",
+            Unit::Cqmin => "cqmin",
+            Unit::Cqmax => "cqmax",
+            Unit::Deg => "deg",
+            Unit::Grad => "grad",
+            Unit::Rad => "rad",
+            Unit::Turn => "turn",
+            Unit::S => "s",
+            Unit::Ms => "ms",
+            Unit::Hz => "hz",
+            Unit::Khz => "khz",
+            Unit::Dpi => "dpi",
+            Unit::Dpcm => "dpcm",
+            Unit::Dppx => "dppx",
+            Unit::X => "x",
+            Unit::Fr => "fr",
+            Unit::Unknown => "",
+        }
+        .to_string()
+    }
 }
@@ -0,0 +1,1334 @@
+use crate::bnf::{
+    ComponentValue, FunctionValue, LiteralValue, MathExpression, MathFunction, MathOperator, Value,
+};
+use crate::lexer::Lexer;
+use crate::number::UnitKind;
+use crate::parser::Parser;
+use crate::span::Span;
+
+/// A [color space](https://www.w3.org/TR/css-color-4/#color-conversion) colors are written in, converted between and mixed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    SrgbLinear,
+    DisplayP3,
+    A98Rgb,
+    ProphotoRgb,
+    Rec2020,
+    XyzD50,
+    XyzD65,
+    Hsl,
+    Hwb,
+    Lab,
+    Lch,
+    Oklab,
+    Oklch,
+}
+
+impl ColorSpace {
+    /// The color space named in `color()` or `color-mix()`, e.g. `display-p3`, where `xyz` is `xyz-d65`
+    pub fn from_name(name: &str) -> Option<ColorSpace> {
+        let space = match name.to_ascii_lowercase().as_str() {
+            "srgb" => ColorSpace::Srgb,
+            "srgb-linear" => ColorSpace::SrgbLinear,
+            "display-p3" => ColorSpace::DisplayP3,
+            "a98-rgb" => ColorSpace::A98Rgb,
+            "prophoto-rgb" => ColorSpace::ProphotoRgb,
+            "rec2020" => ColorSpace::Rec2020,
+            "xyz-d50" => ColorSpace::XyzD50,
+            "xyz" | "xyz-d65" => ColorSpace::XyzD65,
+            "hsl" => ColorSpace::Hsl,
+            "hwb" => ColorSpace::Hwb,
+            "lab" => ColorSpace::Lab,
+            "lch" => ColorSpace::Lch,
+            "oklab" => ColorSpace::Oklab,
+            "oklch" => ColorSpace::Oklch,
+            _ => return None,
+        };
+        Some(space)
+    }
+
+    /// The index of the hue among the components of a polar color space, e.g. `2` for `lch`
+    pub fn hue_index(&self) -> Option<usize> {
+        match self {
+            ColorSpace::Hsl | ColorSpace::Hwb => Some(0),
+            ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// How hues are [interpolated](https://www.w3.org/TR/css-color-4/#hue-interpolation) when mixing colors in a polar color space
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HueInterpolation {
+    #[default]
+    Shorter,
+    Longer,
+    Increasing,
+    Decreasing,
+}
+
+impl HueInterpolation {
+    fn from_name(name: &str) -> Option<HueInterpolation> {
+        let hue_interpolation = match name.to_ascii_lowercase().as_str() {
+            "shorter" => HueInterpolation::Shorter,
+            "longer" => HueInterpolation::Longer,
+            "increasing" => HueInterpolation::Increasing,
+            "decreasing" => HueInterpolation::Decreasing,
+            _ => return None,
+        };
+        Some(hue_interpolation)
+    }
+
+    /// Adjust two hues in degrees so that interpolating between them goes the right way around the hue wheel
+    fn fix_up(&self, from: f64, to: f64) -> (f64, f64) {
+        let (mut from, mut to) = (from.rem_euclid(360.0), to.rem_euclid(360.0));
+        let difference = to - from;
+        match self {
+            HueInterpolation::Shorter if difference > 180.0 => from += 360.0,
+            HueInterpolation::Shorter if difference < -180.0 => to += 360.0,
+            HueInterpolation::Longer if 0.0 < difference && difference < 180.0 => from += 360.0,
+            HueInterpolation::Longer if -180.0 < difference && difference <= 0.0 => to += 360.0,
+            HueInterpolation::Increasing if difference < 0.0 => to += 360.0,
+            HueInterpolation::Decreasing if difference > 0.0 => from += 360.0,
+            _ => {}
+        }
+        (from, to)
+    }
+}
+
+/// A color in the color space it is written in, e.g. `hsl(120 100% 50%)` stays in [`ColorSpace::Hsl`].
+///
+/// The components are in the order of the space's function and in the units of its numbers:
+/// RGB and XYZ spaces from 0 to 1, a hue in degrees, the other components of `hsl` and `hwb` and the lightness of `lab` and `lch` from 0 to 100,
+/// and the lightness of `oklab` and `oklch` from 0 to 1. A component written as `none` is 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub space: ColorSpace,
+    pub components: [f64; 3],
+    /// The opacity from 0 to 1
+    pub alpha: f64,
+}
+
+impl Color {
+    pub fn new(space: ColorSpace, components: [f64; 3], alpha: f64) -> Color {
+        Color {
+            space,
+            components,
+            alpha,
+        }
+    }
+
+    /// Parse a color such as `#fff`, `rebeccapurple` or `oklch(70% 0.1 200 / 50%)`.
+    /// `None` for anything else, including `currentcolor` and colors depending on custom properties.
+    pub fn parse(source: &str) -> Option<Color> {
+        let mut lexer = Lexer::new(source.trim().to_string());
+        lexer.build();
+        let value = Parser::new(lexer.tokens()).parse_value().ok()?;
+        match value.components.as_slice() {
+            [component] => Color::from_component_value(component),
+            _ => None,
+        }
+    }
+
+    /// The color a component value of a declaration value stands for, if any
+    pub fn from_component_value(component: &ComponentValue) -> Option<Color> {
+        match component {
+            ComponentValue::Literal(LiteralValue::Ident(name), _) => named_color(name),
+            ComponentValue::Hash(hash, _) => hex_color(hash.strip_prefix('#')?),
+            ComponentValue::Function(function) => color_function(function),
+            _ => None,
+        }
+    }
+
+    /// Convert the color into another color space.
+    /// Colors outside of the gamut of an RGB space are not mapped into it, e.g. converting `color(display-p3 1 0 0)` into sRGB gives a red greater than 1.
+    pub fn to_space(&self, space: ColorSpace) -> Color {
+        if space == self.space {
+            return *self;
+        }
+        let components = match (self.space, space) {
+            // hsl and hwb are other forms of sRGB, so they convert without going through XYZ
+            (
+                ColorSpace::Srgb | ColorSpace::Hsl | ColorSpace::Hwb,
+                ColorSpace::Srgb | ColorSpace::Hsl | ColorSpace::Hwb,
+            ) => from_srgb(space, to_srgb(self.space, self.components)),
+            _ => from_xyz(space, to_xyz(self.space, self.components)),
+        };
+        Color::new(space, components, self.alpha)
+    }
+
+    /// The red, green, blue and alpha in sRGB from 0 to 1, where a color outside of sRGB is clamped into it
+    pub fn to_srgb(&self) -> [f64; 4] {
+        let [red, green, blue] = self
+            .to_space(ColorSpace::Srgb)
+            .components
+            .map(|component| component.clamp(0.0, 1.0));
+        [red, green, blue, self.alpha]
+    }
+
+    /// The color in sRGB as a hex color, e.g. `#ff0000` or `#ff000080` when it is translucent
+    pub fn to_hex(&self) -> String {
+        let [red, green, blue, alpha] = self
+            .to_srgb()
+            .map(|component| (component * 255.0).round() as u8);
+        if alpha == 255 {
+            format!("#{:02x}{:02x}{:02x}", red, green, blue)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", red, green, blue, alpha)
+        }
+    }
+
+    /// Interpolate from this color to `other` by `progress` from 0 to 1 in `space`, as `color-mix()` does.
+    /// The components other than hue are premultiplied by alpha while interpolating.
+    pub fn mix(
+        &self,
+        other: &Color,
+        progress: f64,
+        space: ColorSpace,
+        hue_interpolation: HueInterpolation,
+    ) -> Color {
+        let from = self.to_space(space);
+        let to = other.to_space(space);
+        let alpha = from.alpha + (to.alpha - from.alpha) * progress;
+        let components = std::array::from_fn(|index| {
+            if space.hue_index() == Some(index) {
+                let (from, to) =
+                    hue_interpolation.fix_up(from.components[index], to.components[index]);
+                return (from + (to - from) * progress).rem_euclid(360.0);
+            }
+            let from = from.components[index] * from.alpha;
+            let to = to.components[index] * to.alpha;
+            let premultiplied = from + (to - from) * progress;
+            if alpha == 0.0 {
+                premultiplied
+            } else {
+                premultiplied / alpha
+            }
+        });
+        Color::new(space, components, alpha)
+    }
+}
+
+impl Value {
+    /// The colors in the value and where they are written, e.g. both colors in `linear-gradient(#fff, rgb(0 0 0 / 50%))`.
+    /// This includes colors in the arguments of other functions, such as the fallback of `var(--color, red)`.
+    pub fn colors(&self) -> Vec<(Color, Span)> {
+        let mut colors = Vec::new();
+        collect_colors(&self.components, &mut colors);
+        colors
+    }
+}
+
+fn collect_colors(components: &[ComponentValue], colors: &mut Vec<(Color, Span)>) {
+    for component in components {
+        if let Some(color) = Color::from_component_value(component) {
+            colors.push((color, component.span()));
+        } else if let ComponentValue::Function(function) = component {
+            collect_colors(&function.arguments, colors);
+        }
+    }
+}
+
+/// How a component of a color function is written
+#[derive(Debug, Clone, Copy)]
+struct Channel {
+    /// The keyword standing for the component of the origin color in relative color syntax, e.g. `r` in `rgb(from red r g b)`
+    keyword: &'static str,
+    /// Whether the component is a hue, which is a number of degrees or an angle
+    hue: bool,
+    /// The component for a number of 1, e.g. `1 / 255` for the red of `rgb()`
+    scale: f64,
+    /// The component for `100%`
+    percentage: f64,
+    /// The range the component is clamped to
+    range: (f64, f64),
+}
+
+impl Channel {
+    const fn new(keyword: &'static str, scale: f64, percentage: f64, range: (f64, f64)) -> Channel {
+        Channel {
+            keyword,
+            hue: false,
+            scale,
+            percentage,
+            range,
+        }
+    }
+
+    const fn hue(keyword: &'static str) -> Channel {
+        Channel {
+            keyword,
+            hue: true,
+            scale: 1.0,
+            percentage: f64::NAN,
+            range: (f64::NEG_INFINITY, f64::INFINITY),
+        }
+    }
+
+    /// Resolve a component written as a literal or a math function against the keywords of the origin color
+    fn resolve(&self, component: &ComponentValue, keywords: &[(&str, f64)]) -> Option<f64> {
+        let number = match component {
+            ComponentValue::Literal(literal, _) => self.number(literal, keywords)?,
+            ComponentValue::Math(math_function) => {
+                self.evaluate_math_function(math_function, keywords)?
+            }
+            _ => return None,
+        };
+        let (min, max) = self.range;
+        Some((number * self.scale).max(min).min(max))
+    }
+
+    /// The literal as a number before scaling, e.g. `255` for `100%` as the red of `rgb()`
+    fn number(&self, literal: &LiteralValue, keywords: &[(&str, f64)]) -> Option<f64> {
+        match literal {
+            LiteralValue::Number(number) => Some(number.value),
+            LiteralValue::Percentage(percentage) if !self.hue => {
+                Some(percentage.value / 100.0 * self.percentage / self.scale)
+            }
+            LiteralValue::Dimension(dimension)
+                if self.hue && dimension.unit.kind() == Some(UnitKind::Angle) =>
+            {
+                dimension.canonical_value()
+            }
+            LiteralValue::Ident(ident) if ident.eq_ignore_ascii_case("none") => Some(0.0),
+            LiteralValue::Ident(ident) => keywords
+                .iter()
+                .find(|(keyword, _)| ident.eq_ignore_ascii_case(keyword))
+                .map(|(_, value)| *value),
+            _ => None,
+        }
+    }
+
+    fn evaluate(&self, expression: &MathExpression, keywords: &[(&str, f64)]) -> Option<f64> {
+        match expression {
+            MathExpression::Literal(literal, _) => self.number(literal, keywords),
+            MathExpression::Parenthesized(expression, _) => self.evaluate(expression, keywords),
+            MathExpression::Binary(operator, left, right) => {
+                let left = self.evaluate(left, keywords)?;
+                let right = self.evaluate(right, keywords)?;
+                let result = match operator {
+                    MathOperator::Add => left + right,
+                    MathOperator::Subtract => left - right,
+                    MathOperator::Multiply => left * right,
+                    MathOperator::Divide => left / right,
+                };
+                Some(result)
+            }
+            MathExpression::Math(math_function) => {
+                self.evaluate_math_function(math_function, keywords)
+            }
+            // e.g. var(--x), which cannot be resolved here
+            MathExpression::Function(_) => None,
+        }
+    }
+
+    fn evaluate_math_function(
+        &self,
+        math_function: &MathFunction,
+        keywords: &[(&str, f64)],
+    ) -> Option<f64> {
+        let arguments = math_function
+            .arguments
+            .iter()
+            .map(|argument| self.evaluate(argument, keywords))
+            .collect::<Option<Vec<f64>>>()?;
+        match (
+            math_function.name.to_ascii_lowercase().as_str(),
+            arguments.as_slice(),
+        ) {
+            ("calc", [value]) => Some(*value),
+            ("min", [first, rest @ ..]) => {
+                Some(rest.iter().fold(*first, |min, value| min.min(*value)))
+            }
+            ("max", [first, rest @ ..]) => {
+                Some(rest.iter().fold(*first, |max, value| max.max(*value)))
+            }
+            // the minimum wins over the maximum when they overlap
+            ("clamp", [min, value, max]) => Some(value.min(*max).max(*min)),
+            ("abs", [value]) => Some(value.abs()),
+            _ => None,
+        }
+    }
+}
+
+const UNBOUNDED: (f64, f64) = (f64::NEG_INFINITY, f64::INFINITY);
+
+const ALPHA: Channel = Channel::new("alpha", 1.0, 1.0, (0.0, 1.0));
+
+/// The color space of a color function other than `color()` and how its components are written
+fn function_channels(name: &str) -> Option<(ColorSpace, [Channel; 3])> {
+    let channels = match name {
+        "rgb" | "rgba" => (
+            ColorSpace::Srgb,
+            [
+                Channel::new("r", 1.0 / 255.0, 1.0, (0.0, 1.0)),
+                Channel::new("g", 1.0 / 255.0, 1.0, (0.0, 1.0)),
+                Channel::new("b", 1.0 / 255.0, 1.0, (0.0, 1.0)),
+            ],
+        ),
+        "hsl" | "hsla" => (
+            ColorSpace::Hsl,
+            [
+                Channel::hue("h"),
+                Channel::new("s", 1.0, 100.0, (0.0, 100.0)),
+                Channel::new("l", 1.0, 100.0, (0.0, 100.0)),
+            ],
+        ),
+        "hwb" => (
+            ColorSpace::Hwb,
+            [
+                Channel::hue("h"),
+                Channel::new("w", 1.0, 100.0, (0.0, 100.0)),
+                Channel::new("b", 1.0, 100.0, (0.0, 100.0)),
+            ],
+        ),
+        "lab" => (
+            ColorSpace::Lab,
+            [
+                Channel::new("l", 1.0, 100.0, (0.0, 100.0)),
+                Channel::new("a", 1.0, 125.0, UNBOUNDED),
+                Channel::new("b", 1.0, 125.0, UNBOUNDED),
+            ],
+        ),
+        "lch" => (
+            ColorSpace::Lch,
+            [
+                Channel::new("l", 1.0, 100.0, (0.0, 100.0)),
+                Channel::new("c", 1.0, 150.0, (0.0, f64::INFINITY)),
+                Channel::hue("h"),
+            ],
+        ),
+        "oklab" => (
+            ColorSpace::Oklab,
+            [
+                Channel::new("l", 1.0, 1.0, (0.0, 1.0)),
+                Channel::new("a", 1.0, 0.4, UNBOUNDED),
+                Channel::new("b", 1.0, 0.4, UNBOUNDED),
+            ],
+        ),
+        "oklch" => (
+            ColorSpace::Oklch,
+            [
+                Channel::new("l", 1.0, 1.0, (0.0, 1.0)),
+                Channel::new("c", 1.0, 0.4, (0.0, f64::INFINITY)),
+                Channel::hue("h"),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(channels)
+}
+
+/// How the components of a predefined color space are written in `color()`, e.g. `color(display-p3 1 0.5 0)`
+fn predefined_channels(space: ColorSpace) -> Option<[Channel; 3]> {
+    let keywords = match space {
+        ColorSpace::Srgb
+        | ColorSpace::SrgbLinear
+        | ColorSpace::DisplayP3
+        | ColorSpace::A98Rgb
+        | ColorSpace::ProphotoRgb
+        | ColorSpace::Rec2020 => ["r", "g", "b"],
+        ColorSpace::XyzD50 | ColorSpace::XyzD65 => ["x", "y", "z"],
+        _ => return None,
+    };
+    Some(keywords.map(|keyword| Channel::new(keyword, 1.0, 1.0, UNBOUNDED)))
+}
+
+// <color-function> ::= <function-token> [ "from" <color> ]? <component> <component> <component> [ "/" <alpha> ]? ")"
+// <color()> ::= "color(" [ "from" <color> ]? <ident> <component> <component> <component> [ "/" <alpha> ]? ")"
+fn color_function(function: &FunctionValue) -> Option<Color> {
+    let name = function.name.to_ascii_lowercase();
+    let arguments: Vec<&ComponentValue> = function
+        .arguments
+        .iter()
+        .filter(|argument| !matches!(argument, ComponentValue::Whitespace(_)))
+        .collect();
+    if name == "color-mix" {
+        return color_mix(&arguments);
+    }
+    if arguments
+        .iter()
+        .any(|argument| matches!(argument, ComponentValue::Comma(_)))
+    {
+        return legacy_color_function(&name, &arguments);
+    }
+
+    let mut arguments = arguments.as_slice();
+    let origin = match arguments {
+        [from, origin, rest @ ..] if ident(from) == Some("from") => {
+            arguments = rest;
+            Some(Color::from_component_value(origin)?)
+        }
+        _ => None,
+    };
+    let (space, channels) = if name == "color" {
+        let [space, rest @ ..] = arguments else {
+            return None;
+        };
+        arguments = rest;
+        let space = ColorSpace::from_name(ident(space)?)?;
+        (space, predefined_channels(space)?)
+    } else {
+        function_channels(&name)?
+    };
+    let (components, alpha) = match arguments {
+        [first, second, third] => ([*first, *second, *third], None),
+        [first, second, third, ComponentValue::Slash(_), alpha] => {
+            ([*first, *second, *third], Some(*alpha))
+        }
+        _ => return None,
+    };
+
+    // the components of the origin color are available as keywords, e.g. `r` in `rgb(from red r g b)`
+    let mut keywords = Vec::new();
+    if let Some(origin) = origin {
+        let origin = origin.to_space(space);
+        for (channel, component) in channels.iter().zip(origin.components) {
+            keywords.push((channel.keyword, component / channel.scale));
+        }
+        keywords.push((ALPHA.keyword, origin.alpha));
+    }
+    let mut resolved = [0.0; 3];
+    for ((channel, component), resolved) in channels.iter().zip(components).zip(&mut resolved) {
+        *resolved = channel.resolve(component, &keywords)?;
+    }
+    let alpha = match alpha {
+        Some(alpha) => ALPHA.resolve(alpha, &keywords)?,
+        None => origin.map_or(1.0, |origin| origin.alpha),
+    };
+    Some(Color::new(space, resolved, alpha))
+}
+
+/// Parse the legacy syntax of `rgb()` and `hsl()` with commas, e.g. `rgba(255, 0, 0, 0.5)`
+fn legacy_color_function(name: &str, arguments: &[&ComponentValue]) -> Option<Color> {
+    if !matches!(name, "rgb" | "rgba" | "hsl" | "hsla") {
+        return None;
+    }
+    let (space, channels) = function_channels(name)?;
+    let separated_by_commas = arguments.len() % 2 == 1
+        && arguments
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|argument| matches!(argument, ComponentValue::Comma(_)));
+    if !separated_by_commas {
+        return None;
+    }
+    let values: Vec<&ComponentValue> = arguments.iter().step_by(2).copied().collect();
+    let (components, alpha) = match values.as_slice() {
+        [first, second, third] => ([*first, *second, *third], None),
+        [first, second, third, alpha] => ([*first, *second, *third], Some(*alpha)),
+        _ => return None,
+    };
+
+    // `none` is not allowed, and the components of `rgb()` are either all numbers or all percentages
+    let is_literal = |component: &ComponentValue, percentage: bool| match component {
+        ComponentValue::Literal(LiteralValue::Percentage(_), _) => percentage,
+        ComponentValue::Literal(LiteralValue::Number(_) | LiteralValue::Dimension(_), _) => {
+            !percentage
+        }
+        ComponentValue::Math(_) => true,
+        _ => false,
+    };
+    let valid = if space == ColorSpace::Srgb {
+        components
+            .iter()
+            .all(|component| is_literal(component, false))
+            || components
+                .iter()
+                .all(|component| is_literal(component, true))
+    } else {
+        is_literal(components[0], false)
+            && is_literal(components[1], true)
+            && is_literal(components[2], true)
+    };
+    if !valid {
+        return None;
+    }
+    let mut resolved = [0.0; 3];
+    for ((channel, component), resolved) in channels.iter().zip(components).zip(&mut resolved) {
+        *resolved = channel.resolve(component, &[])?;
+    }
+    let alpha = match alpha {
+        Some(alpha) => ALPHA.resolve(alpha, &[])?,
+        None => 1.0,
+    };
+    Some(Color::new(space, resolved, alpha))
+}
+
+// <color-mix()> ::= "color-mix(" "in" <ident> [ <ident> "hue" ]? "," <color> <percentage>? "," <color> <percentage>? ")"
+fn color_mix(arguments: &[&ComponentValue]) -> Option<Color> {
+    let groups: Vec<&[&ComponentValue]> = arguments
+        .split(|argument| matches!(argument, ComponentValue::Comma(_)))
+        .collect();
+    let [method, first, second] = groups.as_slice() else {
+        return None;
+    };
+    let (space, hue_interpolation) = match method {
+        [keyword, space] if ident(keyword) == Some("in") => (
+            ColorSpace::from_name(ident(space)?)?,
+            HueInterpolation::default(),
+        ),
+        [keyword, space, hue_interpolation, hue]
+            if ident(keyword) == Some("in") && ident(hue) == Some("hue") =>
+        {
+            let space = ColorSpace::from_name(ident(space)?)?;
+            space.hue_index()?;
+            (
+                space,
+                HueInterpolation::from_name(ident(hue_interpolation)?)?,
+            )
+        }
+        _ => return None,
+    };
+    let (first, first_percentage) = mix_argument(first)?;
+    let (second, second_percentage) = mix_argument(second)?;
+
+    // an omitted percentage makes up the rest of 100%, and percentages which don't add up to 100% are scaled to do so
+    let (first_percentage, second_percentage) = match (first_percentage, second_percentage) {
+        (None, None) => (50.0, 50.0),
+        (Some(first), None) => (first, 100.0 - first),
+        (None, Some(second)) => (100.0 - second, second),
+        (Some(first), Some(second)) => (first, second),
+    };
+    let total = first_percentage + second_percentage;
+    if total <= 0.0 {
+        return None;
+    }
+    let mut color = first.mix(&second, second_percentage / total, space, hue_interpolation);
+    // e.g. `color-mix(in srgb, red 30%, blue 30%)` is 60% opaque
+    if total < 100.0 {
+        color.alpha *= total / 100.0;
+    }
+    Some(color)
+}
+
+/// A color in `color-mix()` with its optional percentage, which is written either before or after it
+fn mix_argument(arguments: &[&ComponentValue]) -> Option<(Color, Option<f64>)> {
+    let percentage = |component: &ComponentValue| match component {
+        ComponentValue::Literal(LiteralValue::Percentage(percentage), _)
+            if (0.0..=100.0).contains(&percentage.value) =>
+        {
+            Some(percentage.value)
+        }
+        _ => None,
+    };
+    match arguments {
+        [color] => Some((Color::from_component_value(color)?, None)),
+        [color, percentage_value] | [percentage_value, color]
+            if percentage(percentage_value).is_some() =>
+        {
+            Some((
+                Color::from_component_value(color)?,
+                percentage(percentage_value),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn ident(component: &ComponentValue) -> Option<&str> {
+    match component {
+        ComponentValue::Literal(LiteralValue::Ident(ident), _) => Some(ident.as_str()),
+        _ => None,
+    }
+}
+
+/// Parse the digits of a hex color, e.g. `fff` or `ff000080`
+fn hex_color(digits: &str) -> Option<Color> {
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let parse = |digits: &str| u8::from_str_radix(digits, 16).ok().map(f64::from);
+    let channels: Vec<f64> = match digits.len() {
+        // each digit is repeated, e.g. `#fa0` is `#ffaa00`
+        3 | 4 => digits
+            .chars()
+            .map(|digit| parse(&digit.to_string().repeat(2)))
+            .collect::<Option<_>>()?,
+        6 | 8 => (0..digits.len())
+            .step_by(2)
+            .map(|index| parse(&digits[index..index + 2]))
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+    let alpha = channels.get(3).map_or(1.0, |alpha| alpha / 255.0);
+    Some(Color::new(
+        ColorSpace::Srgb,
+        [
+            channels[0] / 255.0,
+            channels[1] / 255.0,
+            channels[2] / 255.0,
+        ],
+        alpha,
+    ))
+}
+
+fn named_color(name: &str) -> Option<Color> {
+    if name.eq_ignore_ascii_case("transparent") {
+        return Some(Color::new(ColorSpace::Srgb, [0.0, 0.0, 0.0], 0.0));
+    }
+    let (_, rgb) = NAMED_COLORS
+        .iter()
+        .find(|(named_color, _)| named_color.eq_ignore_ascii_case(name))?;
+    let channel = |shift: u32| f64::from((rgb >> shift) & 0xff) / 255.0;
+    Some(Color::new(
+        ColorSpace::Srgb,
+        [channel(16), channel(8), channel(0)],
+        1.0,
+    ))
+}
+
+/// The [named colors](https://www.w3.org/TR/css-color-4/#named-colors) and their RGB values
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+// The conversions follow the [sample code](https://www.w3.org/TR/css-color-4/#color-conversion-code) of CSS Color 4,
+// going through XYZ with a D65 white point
+
+type Matrix = [[f64; 3]; 3];
+
+const SRGB_TO_XYZ: Matrix = [
+    [506752.0 / 1228815.0, 87881.0 / 245763.0, 12673.0 / 70218.0],
+    [87098.0 / 409605.0, 175762.0 / 245763.0, 12673.0 / 175545.0],
+    [7918.0 / 409605.0, 87881.0 / 737289.0, 1001167.0 / 1053270.0],
+];
+
+const DISPLAY_P3_TO_XYZ: Matrix = [
+    [
+        608311.0 / 1250200.0,
+        189793.0 / 714400.0,
+        198249.0 / 1000160.0,
+    ],
+    [
+        35783.0 / 156275.0,
+        247089.0 / 357200.0,
+        198249.0 / 2500400.0,
+    ],
+    [0.0, 32229.0 / 714400.0, 5220557.0 / 5000800.0],
+];
+
+const A98_RGB_TO_XYZ: Matrix = [
+    [
+        573536.0 / 994567.0,
+        263643.0 / 1420810.0,
+        187206.0 / 994567.0,
+    ],
+    [
+        591459.0 / 1989134.0,
+        6239551.0 / 9945670.0,
+        374412.0 / 4972835.0,
+    ],
+    [
+        53769.0 / 1989134.0,
+        351524.0 / 4972835.0,
+        4929758.0 / 4972835.0,
+    ],
+];
+
+const REC2020_TO_XYZ: Matrix = [
+    [
+        63426534.0 / 99577255.0,
+        20160776.0 / 139408157.0,
+        47086771.0 / 278816314.0,
+    ],
+    [
+        26158966.0 / 99577255.0,
+        472592308.0 / 697040785.0,
+        8267143.0 / 139408157.0,
+    ],
+    [0.0, 19567812.0 / 697040785.0, 295819943.0 / 278816314.0],
+];
+
+/// ProPhoto RGB has a D50 white point
+const PROPHOTO_RGB_TO_XYZ_D50: Matrix = [
+    [0.7977666449006423, 0.13518129740053308, 0.0313477341283922],
+    [0.2880748288194013, 0.711835234241873, 0.00008993693872564],
+    [0.0, 0.0, 0.8251046025104602],
+];
+
+/// The Bradford chromatic adaptation from D50 to D65
+const D50_TO_D65: Matrix = [
+    [0.955473421488075, -0.02309845494876471, 0.06325924320057072],
+    [
+        -0.0283697093338637,
+        1.0099953980813041,
+        0.021041441191917323,
+    ],
+    [
+        0.012314014864481998,
+        -0.020507649298898964,
+        1.330365926242124,
+    ],
+];
+
+const XYZ_TO_LMS: Matrix = [
+    [0.819022437996703, 0.3619062600528904, -0.1288737815209879],
+    [0.0329836539323885, 0.9292868615863434, 0.0361446663506424],
+    [0.0481771893596242, 0.2642395317527308, 0.6335478284694309],
+];
+
+const LMS_TO_OKLAB: Matrix = [
+    [0.210454268309314, 0.7936177747023054, -0.0040720430116193],
+    [1.9779985324311684, -2.42859224204858, 0.450593709617411],
+    [0.0259040424655478, 0.7827717124575296, -0.8086757549788774],
+];
+
+const D50_WHITE: [f64; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+fn multiply(matrix: &Matrix, vector: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * vector[0] + row[1] * vector[1] + row[2] * vector[2])
+}
+
+fn invert(matrix: &Matrix) -> Matrix {
+    let [[a, b, c], [d, e, f], [g, h, i]] = *matrix;
+    let determinant = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+    [
+        [e * i - f * h, c * h - b * i, b * f - c * e],
+        [f * g - d * i, a * i - c * g, c * d - a * f],
+        [d * h - e * g, b * g - a * h, a * e - b * d],
+    ]
+    .map(|row| row.map(|value| value / determinant))
+}
+
+fn to_xyz(space: ColorSpace, components: [f64; 3]) -> [f64; 3] {
+    match space {
+        ColorSpace::Srgb | ColorSpace::Hsl | ColorSpace::Hwb => {
+            multiply(&SRGB_TO_XYZ, to_srgb(space, components).map(srgb_to_linear))
+        }
+        ColorSpace::SrgbLinear => multiply(&SRGB_TO_XYZ, components),
+        ColorSpace::DisplayP3 => multiply(&DISPLAY_P3_TO_XYZ, components.map(srgb_to_linear)),
+        ColorSpace::A98Rgb => multiply(
+            &A98_RGB_TO_XYZ,
+            components.map(|component| gamma(component, 563.0 / 256.0)),
+        ),
+        ColorSpace::ProphotoRgb => multiply(
+            &D50_TO_D65,
+            multiply(
+                &PROPHOTO_RGB_TO_XYZ_D50,
+                components.map(prophoto_rgb_to_linear),
+            ),
+        ),
+        ColorSpace::Rec2020 => multiply(&REC2020_TO_XYZ, components.map(rec2020_to_linear)),
+        ColorSpace::XyzD50 => multiply(&D50_TO_D65, components),
+        ColorSpace::XyzD65 => components,
+        ColorSpace::Lab => multiply(&D50_TO_D65, lab_to_xyz_d50(components)),
+        ColorSpace::Lch => to_xyz(ColorSpace::Lab, polar_to_rectangular(components)),
+        ColorSpace::Oklab => {
+            let lms = multiply(&invert(&LMS_TO_OKLAB), components).map(|value| value.powi(3));
+            multiply(&invert(&XYZ_TO_LMS), lms)
+        }
+        ColorSpace::Oklch => to_xyz(ColorSpace::Oklab, polar_to_rectangular(components)),
+    }
+}
+
+fn from_xyz(space: ColorSpace, xyz: [f64; 3]) -> [f64; 3] {
+    match space {
+        ColorSpace::Srgb | ColorSpace::Hsl | ColorSpace::Hwb => from_srgb(
+            space,
+            multiply(&invert(&SRGB_TO_XYZ), xyz).map(linear_to_srgb),
+        ),
+        ColorSpace::SrgbLinear => multiply(&invert(&SRGB_TO_XYZ), xyz),
+        ColorSpace::DisplayP3 => multiply(&invert(&DISPLAY_P3_TO_XYZ), xyz).map(linear_to_srgb),
+        ColorSpace::A98Rgb => {
+            multiply(&invert(&A98_RGB_TO_XYZ), xyz).map(|component| gamma(component, 256.0 / 563.0))
+        }
+        ColorSpace::ProphotoRgb => multiply(
+            &invert(&PROPHOTO_RGB_TO_XYZ_D50),
+            multiply(&invert(&D50_TO_D65), xyz),
+        )
+        .map(linear_to_prophoto_rgb),
+        ColorSpace::Rec2020 => multiply(&invert(&REC2020_TO_XYZ), xyz).map(linear_to_rec2020),
+        ColorSpace::XyzD50 => multiply(&invert(&D50_TO_D65), xyz),
+        ColorSpace::XyzD65 => xyz,
+        ColorSpace::Lab => xyz_d50_to_lab(multiply(&invert(&D50_TO_D65), xyz)),
+        ColorSpace::Lch => rectangular_to_polar(from_xyz(ColorSpace::Lab, xyz)),
+        ColorSpace::Oklab => {
+            let lms = multiply(&XYZ_TO_LMS, xyz).map(f64::cbrt);
+            multiply(&LMS_TO_OKLAB, lms)
+        }
+        ColorSpace::Oklch => rectangular_to_polar(from_xyz(ColorSpace::Oklab, xyz)),
+    }
+}
+
+/// Convert from `srgb`, `hsl` or `hwb` into `srgb`
+fn to_srgb(space: ColorSpace, components: [f64; 3]) -> [f64; 3] {
+    match space {
+        ColorSpace::Hsl => hsl_to_srgb(components),
+        ColorSpace::Hwb => hwb_to_srgb(components),
+        _ => components,
+    }
+}
+
+/// Convert from `srgb` into `srgb`, `hsl` or `hwb`
+fn from_srgb(space: ColorSpace, rgb: [f64; 3]) -> [f64; 3] {
+    match space {
+        ColorSpace::Hsl => srgb_to_hsl(rgb),
+        ColorSpace::Hwb => srgb_to_hwb(rgb),
+        _ => rgb,
+    }
+}
+
+/// Raise the magnitude of a component to `exponent`, keeping its sign for components outside of the gamut
+fn gamma(component: f64, exponent: f64) -> f64 {
+    component.signum() * component.abs().powf(exponent)
+}
+
+fn srgb_to_linear(component: f64) -> f64 {
+    if component.abs() <= 0.04045 {
+        component / 12.92
+    } else {
+        gamma((component.abs() + 0.055) / 1.055, 2.4) * component.signum()
+    }
+}
+
+fn linear_to_srgb(component: f64) -> f64 {
+    if component.abs() > 0.0031308 {
+        component.signum() * (1.055 * component.abs().powf(1.0 / 2.4) - 0.055)
+    } else {
+        component * 12.92
+    }
+}
+
+fn prophoto_rgb_to_linear(component: f64) -> f64 {
+    if component.abs() <= 16.0 / 512.0 {
+        component / 16.0
+    } else {
+        gamma(component, 1.8)
+    }
+}
+
+fn linear_to_prophoto_rgb(component: f64) -> f64 {
+    if component.abs() >= 1.0 / 512.0 {
+        gamma(component, 1.0 / 1.8)
+    } else {
+        component * 16.0
+    }
+}
+
+const REC2020_ALPHA: f64 = 1.09929682680944;
+const REC2020_BETA: f64 = 0.018053968510807;
+
+fn rec2020_to_linear(component: f64) -> f64 {
+    if component.abs() < REC2020_BETA * 4.5 {
+        component / 4.5
+    } else {
+        component.signum()
+            * ((component.abs() + REC2020_ALPHA - 1.0) / REC2020_ALPHA).powf(1.0 / 0.45)
+    }
+}
+
+fn linear_to_rec2020(component: f64) -> f64 {
+    if component.abs() > REC2020_BETA {
+        component.signum() * (REC2020_ALPHA * component.abs().powf(0.45) - (REC2020_ALPHA - 1.0))
+    } else {
+        component * 4.5
+    }
+}
+
+fn hsl_to_srgb([hue, saturation, lightness]: [f64; 3]) -> [f64; 3] {
+    let hue = hue.rem_euclid(360.0);
+    let (saturation, lightness) = (saturation / 100.0, lightness / 100.0);
+    let channel = |n: f64| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [channel(0.0), channel(8.0), channel(4.0)]
+}
+
+fn srgb_to_hsl([red, green, blue]: [f64; 3]) -> [f64; 3] {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (min + max) / 2.0;
+    let difference = max - min;
+    // the hue of a gray is powerless, and taken as 0
+    let (mut hue, mut saturation) = (0.0, 0.0);
+    if difference != 0.0 {
+        if lightness != 0.0 && lightness != 1.0 {
+            saturation = (max - lightness) / lightness.min(1.0 - lightness);
+        }
+        hue = if max == red {
+            (green - blue) / difference + if green < blue { 6.0 } else { 0.0 }
+        } else if max == green {
+            (blue - red) / difference + 2.0
+        } else {
+            (red - green) / difference + 4.0
+        } * 60.0;
+    }
+    if saturation < 0.0 {
+        hue += 180.0;
+        saturation = saturation.abs();
+    }
+    [hue.rem_euclid(360.0), saturation * 100.0, lightness * 100.0]
+}
+
+fn hwb_to_srgb([hue, whiteness, blackness]: [f64; 3]) -> [f64; 3] {
+    let (whiteness, blackness) = (whiteness / 100.0, blackness / 100.0);
+    if whiteness + blackness >= 1.0 {
+        let gray = whiteness / (whiteness + blackness);
+        return [gray; 3];
+    }
+    hsl_to_srgb([hue, 100.0, 50.0])
+        .map(|channel| channel * (1.0 - whiteness - blackness) + whiteness)
+}
+
+fn srgb_to_hwb(rgb: [f64; 3]) -> [f64; 3] {
+    let [hue, _, _] = srgb_to_hsl(rgb);
+    let whiteness = rgb[0].min(rgb[1]).min(rgb[2]);
+    let blackness = 1.0 - rgb[0].max(rgb[1]).max(rgb[2]);
+    [hue, whiteness * 100.0, blackness * 100.0]
+}
+
+const LAB_KAPPA: f64 = 24389.0 / 27.0;
+const LAB_EPSILON: f64 = 216.0 / 24389.0;
+
+fn xyz_d50_to_lab(xyz: [f64; 3]) -> [f64; 3] {
+    let [x, y, z] = std::array::from_fn(|index| xyz[index] / D50_WHITE[index]).map(|value: f64| {
+        if value > LAB_EPSILON {
+            value.cbrt()
+        } else {
+            (LAB_KAPPA * value + 16.0) / 116.0
+        }
+    });
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+fn lab_to_xyz_d50([lightness, a, b]: [f64; 3]) -> [f64; 3] {
+    let y = (lightness + 16.0) / 116.0;
+    let x = a / 500.0 + y;
+    let z = y - b / 200.0;
+    let cube_or_linear = |value: f64| {
+        if value.powi(3) > LAB_EPSILON {
+            value.powi(3)
+        } else {
+            (116.0 * value - 16.0) / LAB_KAPPA
+        }
+    };
+    let y = if lightness > LAB_KAPPA * LAB_EPSILON {
+        y.powi(3)
+    } else {
+        lightness / LAB_KAPPA
+    };
+    let xyz = [cube_or_linear(x), y, cube_or_linear(z)];
+    std::array::from_fn(|index| xyz[index] * D50_WHITE[index])
+}
+
+/// Convert the chroma and hue of `lch` or `oklch` into the a and b of `lab` or `oklab`
+fn polar_to_rectangular([lightness, chroma, hue]: [f64; 3]) -> [f64; 3] {
+    let hue = hue.to_radians();
+    [lightness, chroma * hue.cos(), chroma * hue.sin()]
+}
+
+fn rectangular_to_polar([lightness, a, b]: [f64; 3]) -> [f64; 3] {
+    [
+        lightness,
+        a.hypot(b),
+        b.atan2(a).to_degrees().rem_euclid(360.0),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bnf::{Declaration, Rule};
+    use crate::test_helper::parse;
+
+    #[test]
+    fn test_parse_colors() {
+        // (color, hex in sRGB)
+        let cases = [
+            ("red", "#ff0000"),
+            ("RebeccaPurple", "#663399"),
+            ("transparent", "#00000000"),
+            ("#ABC", "#aabbcc"),
+            ("#ff000080", "#ff000080"),
+            ("#f008", "#ff000088"),
+            ("rgb(255, 0, 0)", "#ff0000"),
+            ("rgba(100%, 50%, 0%, 0.5)", "#ff800080"),
+            ("rgb(255 0 0 / 50%)", "#ff000080"),
+            ("rgb(300 -10 none)", "#ff0000"),
+            ("rgb(calc(255 / 2) 0 0)", "#800000"),
+            ("hsl(120, 100%, 50%)", "#00ff00"),
+            ("hsla(120deg 100 25 / 0.5)", "#00800080"),
+            ("hsl(0.5turn 100% 50%)", "#00ffff"),
+            ("hwb(240 0% 0%)", "#0000ff"),
+            ("hwb(0 60% 60%)", "#808080"),
+            ("lab(54.29% 80.8 69.89)", "#ff0000"),
+            ("lch(54.29 106.84 40.85)", "#ff0000"),
+            ("oklab(62.8% 0.2249 0.1258)", "#ff0000"),
+            ("oklch(0.628 0.2577 29.23 / 0.5)", "#ff000080"),
+            ("color(srgb 1 0.5 0)", "#ff8000"),
+            ("color(display-p3 1 1 1)", "#ffffff"),
+            ("color(xyz 0.9505 1 1.089)", "#ffffff"),
+            ("color(rec2020 0 0 0)", "#000000"),
+            ("color-mix(in srgb, red, blue)", "#800080"),
+            ("color-mix(in srgb, red 25%, blue)", "#4000bf"),
+            ("color-mix(in srgb, blue, red 25%)", "#4000bf"),
+            ("color-mix(in srgb, red 40%, blue 40%)", "#800080cc"),
+            ("color-mix(in hsl, red, blue)", "#ff00ff"),
+            ("color-mix(in hsl longer hue, red, blue)", "#00ff00"),
+            ("color-mix(in srgb, red, transparent)", "#ff000080"),
+            ("rgb(from red r g b / 50%)", "#ff000080"),
+            ("rgb(from #0000ff b g r)", "#ff0000"),
+            ("hsl(from red calc(h + 120) s l)", "#00ff00"),
+            ("color(from red srgb calc(r / 2) g b)", "#800000"),
+            ("oklch(from color-mix(in srgb, red, red) l c h)", "#ff0000"),
+        ];
+        for (source, hex) in cases {
+            let color = Color::parse(source).unwrap_or_else(|| panic!("{}", source));
+            assert_eq!(color.to_hex(), hex, "{}", source);
+        }
+
+        let invalid = [
+            "currentcolor",
+            "notacolor",
+            "#ff",
+            "#ggg",
+            "rgb(100%, 0, 0)",
+            "rgb(255, 0 0)",
+            "rgb(none, 0, 0)",
+            "hsl(10%, 50%, 50%)",
+            "hsl(120, 50, 50)",
+            "rgb(1 2)",
+            "rgb(var(--r) 0 0)",
+            "color(hsl 1 1 1)",
+            "color(foo 1 1 1)",
+            "color-mix(in srgb, red)",
+            "color-mix(in srgb longer hue, red, blue)",
+            "color-mix(in srgb, red 0%, blue 0%)",
+            "rgb(from currentcolor r g b)",
+        ];
+        for source in invalid {
+            assert_eq!(Color::parse(source), None, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_convert_colors() {
+        let assert_components = |color: Color, expected: [f64; 3], tolerance: f64| {
+            for (component, expected) in color.components.iter().zip(expected) {
+                assert!(
+                    (component - expected).abs() < tolerance,
+                    "{:?} is not {:?}",
+                    color,
+                    expected
+                );
+            }
+        };
+        let red = Color::parse("red").unwrap();
+        assert_components(red.to_space(ColorSpace::Lab), [54.29, 80.8, 69.89], 0.01);
+        assert_components(red.to_space(ColorSpace::Lch), [54.29, 106.84, 40.85], 0.01);
+        assert_components(
+            red.to_space(ColorSpace::Oklch),
+            [0.628, 0.2577, 29.234],
+            0.001,
+        );
+        assert_components(red.to_space(ColorSpace::Hsl), [0.0, 100.0, 50.0], 1e-9);
+        assert_components(red.to_space(ColorSpace::Hwb), [0.0, 0.0, 0.0], 1e-9);
+
+        let display_p3_red = Color::parse("color(display-p3 1 0 0)").unwrap();
+        assert_components(
+            display_p3_red.to_space(ColorSpace::Srgb),
+            [1.0931, -0.2267, -0.1501],
+            0.001,
+        );
+        assert_eq!(display_p3_red.to_hex(), "#ff0000");
+
+        let color = Color::parse("rgb(10% 50% 80% / 0.3)").unwrap();
+        let spaces = [
+            ColorSpace::SrgbLinear,
+            ColorSpace::DisplayP3,
+            ColorSpace::A98Rgb,
+            ColorSpace::ProphotoRgb,
+            ColorSpace::Rec2020,
+            ColorSpace::XyzD50,
+            ColorSpace::XyzD65,
+            ColorSpace::Hsl,
+            ColorSpace::Hwb,
+            ColorSpace::Lab,
+            ColorSpace::Lch,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+        ];
+        for space in spaces {
+            let converted = color.to_space(space);
+            assert_eq!(converted.space, space);
+            assert_eq!(converted.alpha, 0.3);
+            assert_components(converted.to_space(ColorSpace::Srgb), [0.1, 0.5, 0.8], 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_value_colors() {
+        let (stylesheet, _) = parse(
+            ".a { background: linear-gradient(#fff, rgb(0 0 0 / 50%)) 0 0 / auto, var(--bg, red); }",
+        );
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let Declaration { value, .. } = &style_rule.declarations[0];
+        let colors: Vec<(String, Span)> = value
+            .colors()
+            .into_iter()
+            .map(|(color, span)| (color.to_hex(), span))
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                ("#ffffff".to_string(), Span::new(33, 37)),
+                ("#00000080".to_string(), Span::new(39, 55)),
+                ("#ff0000".to_string(), Span::new(79, 82)),
+            ]
+        );
+    }
+}
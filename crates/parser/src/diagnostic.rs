@@ -0,0 +1,90 @@
+use crate::span::Span;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A stable identifier for each kind of diagnostic, serialized as e.g. `"expected-colon"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    ExpectedSelector,
+    InvalidSelector,
+    ExpectedCurlyLeft,
+    ExpectedCurlyRight,
+    ExpectedProperty,
+    ExpectedColon,
+    ExpectedValue,
+    ExpectedSemicolon,
+    UnsupportedAtRule,
+    InvalidMediaQuery,
+    InvalidSupportsCondition,
+    InvalidContainerQuery,
+    ExpectedKeyframesName,
+    InvalidKeyframeSelector,
+    InvalidPageSelector,
+    ExpectedCounterStyleName,
+    ExpectedCustomPropertyName,
+    InvalidPropertyRule,
+    InvalidLayerName,
+    ExpectedUrl,
+    ExpectedCharsetEncoding,
+    MisplacedAtRule,
+    IgnoredRule,
+    IgnoredDeclaration,
+    NestingTooDeep,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub span: Span,
+    pub severity: Severity,
+}
+
+/// The error returned by the `Parser::parse_*` methods, reported as an error [`Diagnostic`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(code: DiagnosticCode, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic {
+            code: error.code,
+            message: error.message,
+            span: error.span,
+            severity: Severity::Error,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+/// The encodings a stylesheet can be [decoded](decode) from without an external encoding library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl Encoding {
+    /// Look up an [encoding label](https://encoding.spec.whatwg.org/#names-and-labels), e.g. `"latin1"`
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8"
+            | "x-unicode20utf8" => Some(Encoding::Utf8),
+            "utf-16le" | "utf-16" | "ucs-2" | "unicode" | "csunicode" | "iso-10646-ucs-2"
+            | "unicodefeff" => Some(Encoding::Utf16Le),
+            "utf-16be" | "unicodefffe" => Some(Encoding::Utf16Be),
+            "windows-1252" | "latin1" | "iso-8859-1" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "cp1252" | "x-cp1252" | "cp819" | "ibm819"
+            | "iso-ir-100" | "csisolatin1" | "us-ascii" | "ascii" | "ansi_x3.4-1968" => {
+                Some(Encoding::Windows1252)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// [Determine the fallback encoding](https://www.w3.org/TR/css-syntax-3/#determine-the-fallback-encoding)
+/// of a stylesheet from its byte order mark, or else its `@charset` rule, defaulting to UTF-8
+pub fn detect_encoding(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Encoding::Utf8;
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Encoding::Utf16Be;
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Encoding::Utf16Le;
+    }
+
+    // the rule must be written exactly as `@charset "<label>";` within the first 1024 bytes
+    let prefix = b"@charset \"";
+    let head = &bytes[..bytes.len().min(1024)];
+    if let Some(rest) = head.strip_prefix(&prefix[..]) {
+        let label = rest
+            .windows(2)
+            .position(|window| window == b"\";")
+            .and_then(|end| std::str::from_utf8(&rest[..end]).ok())
+            .and_then(Encoding::for_label);
+        match label {
+            // a stylesheet which is readable as ASCII cannot actually be UTF-16
+            Some(Encoding::Utf16Le) | Some(Encoding::Utf16Be) => return Encoding::Utf8,
+            Some(encoding) => return encoding,
+            None => {}
+        }
+    }
+    Encoding::Utf8
+}
+
+/// Decode a stylesheet in the [detected encoding](detect_encoding), dropping the byte order mark.
+/// Invalid byte sequences are replaced with U+FFFD.
+pub fn decode(bytes: &[u8]) -> String {
+    match detect_encoding(bytes) {
+        Encoding::Utf8 => {
+            let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+        Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+        Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+        Encoding::Windows1252 => bytes
+            .iter()
+            .map(|&byte| match byte {
+                0x80..=0x9F => WINDOWS_1252[(byte - 0x80) as usize],
+                _ => byte as char,
+            })
+            .collect(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes[2..].chunks(2).map(|chunk| match chunk {
+        [a, b] => from_bytes([*a, *b]),
+        // a trailing odd byte
+        _ => 0xFFFD,
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// The characters of the bytes 0x80 to 0x9F in windows-1252, which differ from ISO-8859-1
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encoding() {
+        let cases: [(&[u8], Encoding); 8] = [
+            (b"a { color: red; }", Encoding::Utf8),
+            (b"\xEF\xBB\xBF@charset \"latin1\";", Encoding::Utf8),
+            (b"\xFE\xFF\x00a", Encoding::Utf16Be),
+            (b"\xFF\xFEa\x00", Encoding::Utf16Le),
+            (b"@charset \"ISO-8859-1\"; a {}", Encoding::Windows1252),
+            (b"@charset \"utf-16\";", Encoding::Utf8),
+            // only the exact form is honoured
+            (b"@charset 'latin1';", Encoding::Utf8),
+            (b"@charset \"unknown\";", Encoding::Utf8),
+        ];
+        for (bytes, encoding) in cases {
+            assert_eq!(
+                detect_encoding(bytes),
+                encoding,
+                "{}",
+                String::from_utf8_lossy(bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xEF\xBB\xBFa{}"), "a{}");
+        assert_eq!(
+            decode(b"@charset \"windows-1252\"; .caf\xE9::after { content: \"\x80\"; }"),
+            "@charset \"windows-1252\"; .café::after { content: \"€\"; }"
+        );
+        assert_eq!(decode(b"\xFE\xFF\x00a\x00{\x00}"), "a{}");
+        assert_eq!(decode(b"\xFF\xFEa\x00{\x00}\x00\x00"), "a{}\u{FFFD}");
+        assert_eq!(decode(b"a\xFF{}"), "a\u{FFFD}{}");
+    }
+}
@@ -1,62 +1,362 @@
 use crate::{
-    bnf::{Rule, StyleRule, StyleSheet},
+    bnf::{Declaration, Rule, Selector, StyleSheet},
+    diagnostic::{Diagnostic, DiagnosticCode, Severity},
     helper::ToString,
+    selector::Specificity,
+    span::{LineIndex, Location, Span},
 };
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HIR {
     pub rules: Vec<HIRRule>,
+    /// The fully qualified names of the cascade layers, from the lowest to the highest priority
+    pub layers: Vec<String>,
+    pub keyframes: Vec<HIRKeyframes>,
+    /// Custom properties registered with a valid `@property` rule
+    pub properties: Vec<HIRProperty>,
+    /// The stylesheets imported with `@import`, in source order
+    pub imports: Vec<HIRImport>,
+    pub diagnostics: Vec<HIRDiagnostic>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HIRRule {
+    /// The selector as written, e.g. `&:hover` for a nested rule
     pub selector: String,
+    /// The selector with `&` resolved against the enclosing style rules, e.g. `.card:hover`
+    pub resolved_selector: String,
+    /// The class names in the resolved selectors without the leading `.`, e.g. `["card", "active"]`
+    pub class_names: Vec<String>,
+    /// The specificity of each of the resolved selectors
+    pub specificity: Vec<Specificity>,
+    /// The index of the style rule this rule is nested in
+    pub parent: Option<usize>,
     pub declarations: Vec<HIRDeclaration>,
+    /// The media query lists of the `@media` rules this rule is nested in, outermost first,
+    /// e.g. `["screen", "(max-width: 600px)"]`. All of them must match for the declarations to apply.
+    pub media: Vec<String>,
+    /// The conditions of the `@supports` rules this rule is nested in, outermost first,
+    /// e.g. `["(display: grid)"]`. All of them must hold for the declarations to apply.
+    pub supports: Vec<String>,
+    /// The conditions of the `@container` rules this rule is nested in, outermost first,
+    /// e.g. `["card (min-width: 400px)"]`. All of them must match for the declarations to apply.
+    pub container: Vec<String>,
+    /// The fully qualified name of the cascade layer this rule is in, e.g. `components.card`.
+    /// `None` for unlayered rules, which win over every layer.
+    pub layer: Option<String>,
+    pub span: Span,
+    pub location: Location,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HIRDeclaration {
     pub property: String,
+    /// The value without `!important`, e.g. `0 auto`
     pub value: String,
+    pub important: bool,
+    /// The colors in the value, e.g. for swatches next to them
+    pub colors: Vec<HIRColor>,
+    pub span: Span,
+    pub location: Location,
+}
+
+/// A color in sRGB, with each channel from 0 to 1. Colors outside of sRGB are clamped into it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HIRColor {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub alpha: f64,
+    pub span: Span,
+    pub location: Location,
+}
+
+/// A `@keyframes` rule. Its name is an identifier scoped the same way as class names are in CSS Modules.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HIRKeyframes {
+    pub name: String,
+    pub keyframes: Vec<HIRKeyframe>,
+    pub span: Span,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HIRKeyframe {
+    /// e.g. `from, 50%`
+    pub selector: String,
+    pub declarations: Vec<HIRDeclaration>,
+    pub span: Span,
+    pub location: Location,
+}
+
+/// e.g. `@property --gap { syntax: "<length>"; inherits: false; initial-value: 0px; }`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HIRProperty {
+    pub name: String,
+    /// The declared type, e.g. `<length> | auto`
+    pub syntax: String,
+    pub inherits: bool,
+    /// Only omitted for the universal syntax `*`
+    pub initial_value: Option<String>,
+    pub span: Span,
+    pub location: Location,
+}
+
+/// e.g. `@import url("theme.css") layer(base) supports(display: grid) screen;`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HIRImport {
+    pub url: String,
+    /// The span of the URL or string, e.g. `url("theme.css")`
+    pub url_span: Span,
+    /// The fully qualified name of the cascade layer the stylesheet is imported into
+    pub layer: Option<String>,
+    pub supports: Option<String>,
+    /// `None` when the import is unconditional
+    pub media: Option<String>,
+    pub span: Span,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HIRDiagnostic {
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub severity: Severity,
+    pub span: Span,
+    pub location: Location,
+}
+
+/// The conditions the rules being generated are nested under
+#[derive(Debug, Clone, Default)]
+struct HIRContext {
+    media: Vec<String>,
+    supports: Vec<String>,
+    container: Vec<String>,
+    layer: Vec<String>,
+    parent: Option<usize>,
+    /// The resolved selectors of the enclosing style rule
+    parent_selectors: Option<Vec<Selector>>,
 }
 
 pub struct HIRGenerator {
     ast: StyleSheet,
+    diagnostics: Vec<Diagnostic>,
+    line_index: LineIndex,
 }
 
 impl HIRGenerator {
-    pub fn new(ast: StyleSheet) -> Self {
-        Self { ast }
+    pub fn new(ast: StyleSheet, diagnostics: Vec<Diagnostic>, line_index: LineIndex) -> Self {
+        Self {
+            ast,
+            diagnostics,
+            line_index,
+        }
     }
 
     pub fn generate(&self) -> HIR {
-        let mut rules = vec![];
+        let diagnostics = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| HIRDiagnostic {
+                code: diagnostic.code,
+                message: diagnostic.message.clone(),
+                severity: diagnostic.severity,
+                span: diagnostic.span,
+                location: self.line_index.location(diagnostic.span),
+            })
+            .collect();
+        let mut hir = HIR {
+            rules: vec![],
+            layers: self.ast.layer_order(),
+            keyframes: vec![],
+            properties: vec![],
+            imports: vec![],
+            diagnostics,
+        };
+        self.generate_rules(&self.ast.rules, &HIRContext::default(), &mut hir);
+        hir
+    }
+
+    fn generate_rules(&self, rules: &[Rule], context: &HIRContext, hir: &mut HIR) {
         // O(n^2), FIXME: optimize
-        for rule in &self.ast.rules {
-            // modify this when we have more types of rules e.g. AtRule
-            let Rule::StyleRule(style_rule) = rule;
-            let selector = style_rule.selector_text.clone();
-            let mut declarations = vec![];
-            for declaration in &style_rule.declarations {
-                let property = declaration.property.clone();
-                let value = declaration.value.clone().to_string();
-                declarations.push(HIRDeclaration { property, value });
+        for rule in rules {
+            match rule {
+                Rule::StyleRule(style_rule) => {
+                    let selector = style_rule.selector_text.clone();
+                    let resolved_selectors: Vec<Selector> = style_rule
+                        .selectors
+                        .iter()
+                        .map(|selector| selector.resolve(context.parent_selectors.as_deref()))
+                        .collect();
+                    let mut class_names: Vec<String> = Vec::new();
+                    for class_name in resolved_selectors.iter().flat_map(Selector::class_names) {
+                        if !class_names.contains(&class_name) {
+                            class_names.push(class_name);
+                        }
+                    }
+                    let declarations = self.generate_declarations(&style_rule.declarations);
+                    let index = hir.rules.len();
+                    hir.rules.push(HIRRule {
+                        selector: selector.clone(),
+                        resolved_selector: resolved_selectors
+                            .iter()
+                            .map(|selector| selector.to_string())
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                        class_names,
+                        specificity: resolved_selectors
+                            .iter()
+                            .map(Selector::specificity)
+                            .collect(),
+                        parent: context.parent,
+                        declarations,
+                        media: context.media.clone(),
+                        supports: context.supports.clone(),
+                        container: context.container.clone(),
+                        layer: if context.layer.is_empty() {
+                            None
+                        } else {
+                            Some(context.layer.join("."))
+                        },
+                        span: style_rule.span,
+                        location: self.line_index.location(style_rule.span),
+                    });
+                    let mut context = context.clone();
+                    context.parent = Some(index);
+                    context.parent_selectors = Some(resolved_selectors);
+                    self.generate_rules(&style_rule.rules, &context, hir);
+                }
+                Rule::Media(media_rule) => {
+                    let mut context = context.clone();
+                    context.media.push(media_rule.query_list.to_string());
+                    self.generate_rules(&media_rule.rules, &context, hir);
+                }
+                Rule::Supports(supports_rule) => {
+                    let mut context = context.clone();
+                    context.supports.push(supports_rule.condition.to_string());
+                    self.generate_rules(&supports_rule.rules, &context, hir);
+                }
+                Rule::Container(container_rule) => {
+                    let mut context = context.clone();
+                    context.container.push(container_rule.condition.to_string());
+                    self.generate_rules(&container_rule.rules, &context, hir);
+                }
+                Rule::LayerBlock(layer_block_rule) => {
+                    let mut context = context.clone();
+                    context.layer.extend(layer_block_rule.layer_name());
+                    self.generate_rules(&layer_block_rule.rules, &context, hir);
+                }
+                Rule::Keyframes(keyframes_rule) => {
+                    let keyframes = keyframes_rule
+                        .keyframes
+                        .iter()
+                        .map(|keyframe| HIRKeyframe {
+                            selector: keyframe
+                                .selectors
+                                .iter()
+                                .map(|selector| selector.to_string())
+                                .collect::<Vec<String>>()
+                                .join(", "),
+                            declarations: self.generate_declarations(&keyframe.declarations),
+                            span: keyframe.span,
+                            location: self.line_index.location(keyframe.span),
+                        })
+                        .collect();
+                    hir.keyframes.push(HIRKeyframes {
+                        name: keyframes_rule.name.clone(),
+                        keyframes,
+                        span: keyframes_rule.span,
+                        location: self.line_index.location(keyframes_rule.span),
+                    });
+                }
+                Rule::Property(property_rule) => {
+                    if let (true, Some(syntax), Some(inherits)) = (
+                        property_rule.is_valid(),
+                        &property_rule.syntax,
+                        property_rule.inherits,
+                    ) {
+                        hir.properties.push(HIRProperty {
+                            name: property_rule.name.clone(),
+                            syntax: syntax.to_string(),
+                            inherits,
+                            initial_value: property_rule
+                                .initial_value
+                                .as_ref()
+                                .map(|value| value.to_string()),
+                            span: property_rule.span,
+                            location: self.line_index.location(property_rule.span),
+                        });
+                    }
+                }
+                Rule::Import(import_rule) => {
+                    hir.imports.push(HIRImport {
+                        url: import_rule.url.clone(),
+                        url_span: import_rule.url_span,
+                        layer: import_rule.layer.as_ref().map(|layer| {
+                            [context.layer.clone(), layer.layer_name()]
+                                .concat()
+                                .join(".")
+                        }),
+                        supports: import_rule
+                            .supports
+                            .as_ref()
+                            .map(|condition| condition.to_string()),
+                        media: if import_rule.media.queries.is_empty() {
+                            None
+                        } else {
+                            Some(import_rule.media.to_string())
+                        },
+                        span: import_rule.span,
+                        location: self.line_index.location(import_rule.span),
+                    });
+                }
+                // descriptors don't apply to elements, and layer order is computed up front
+                Rule::FontFace(_)
+                | Rule::Page(_)
+                | Rule::CounterStyle(_)
+                | Rule::LayerStatement(_)
+                | Rule::Charset(_)
+                | Rule::Namespace(_)
+                | Rule::Unknown(_) => {}
             }
-            rules.push(HIRRule {
-                selector: selector.clone(),
-                declarations: declarations.clone(),
-            });
         }
-        HIR { rules }
+    }
+
+    fn generate_declarations(&self, declarations: &[Declaration]) -> Vec<HIRDeclaration> {
+        declarations
+            .iter()
+            .map(|declaration| HIRDeclaration {
+                property: declaration.property.clone(),
+                value: declaration.value.to_string(),
+                important: declaration.important,
+                colors: declaration
+                    .value
+                    .colors()
+                    .into_iter()
+                    .map(|(color, span)| {
+                        let [red, green, blue, alpha] = color.to_srgb();
+                        HIRColor {
+                            red,
+                            green,
+                            blue,
+                            alpha,
+                            span,
+                            location: self.line_index.location(span),
+                        }
+                    })
+                    .collect(),
+                span: declaration.span,
+                location: self.line_index.location(declaration.span),
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::Lexer;
-    use crate::parser::Parser;
+    use crate::test_helper::generate_hir;
 
     #[test]
     fn test_generate() {
@@ -67,19 +367,312 @@ mod tests {
                 align-items: center;
                 font-size: 16px;
                 width: 100%;
+                font: 12px/1.5 sans-serif;
+                transition: opacity 1s, transform 2s;
+                color: red !important;
             }
         "#;
-        let mut lexer = Lexer::new(source_text.to_string());
-        lexer.build();
-        let tokens = lexer.tokens();
-        let mut parser = Parser::new(tokens);
-        let ast = parser.parse();
-        let generator = HIRGenerator::new(ast);
-        let hir = generator.generate();
+        let hir = generate_hir(source_text);
         assert_eq!(hir.rules.len(), 1);
 
         let rule = &hir.rules[0];
+        assert_eq!(rule.location.start.line, 2);
+        assert_eq!(rule.location.start.column, 13);
+        assert_eq!(rule.location.end.line, 11);
         let declarations = &rule.declarations;
-        assert_eq!(declarations.len(), 5);
+        assert_eq!(declarations.len(), 8);
+        assert_eq!(declarations[5].value, "12px/1.5 sans-serif");
+        assert_eq!(declarations[6].value, "opacity 1s, transform 2s");
+        assert!(!declarations[6].important);
+        assert_eq!(declarations[7].value, "red");
+        assert!(declarations[7].important);
+        assert!(declarations[6].colors.is_empty());
+        let color = &declarations[7].colors[0];
+        assert_eq!(
+            (color.red, color.green, color.blue, color.alpha),
+            (1.0, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(&source_text[color.span.start..color.span.end], "red");
+        assert_eq!(color.location.start.line, 10);
+        assert_eq!(
+            &source_text[declarations[3].span.start..declarations[3].span.end],
+            "font-size: 16px;"
+        );
+        assert_eq!(declarations[3].location.start.line, 6);
+        assert!(hir.diagnostics.is_empty());
+        assert!(rule.media.is_empty());
+    }
+
+    #[test]
+    fn test_generate_media_context() {
+        let source_text = r#"
+            .a { color: red; }
+            @media screen and (max-width: 600px) {
+                .a { color: blue; }
+                @media (prefers-color-scheme: dark) {
+                    .a { color: white; }
+                }
+            }
+        "#;
+        let hir = generate_hir(source_text);
+
+        assert!(hir.diagnostics.is_empty());
+        let media: Vec<Vec<String>> = hir.rules.iter().map(|rule| rule.media.clone()).collect();
+        assert_eq!(
+            media,
+            vec![
+                vec![],
+                vec!["screen and (max-width: 600px)".to_string()],
+                vec![
+                    "screen and (max-width: 600px)".to_string(),
+                    "(prefers-color-scheme: dark)".to_string()
+                ],
+            ]
+        );
+        assert_eq!(hir.rules[2].declarations[0].value, "white");
+    }
+
+    #[test]
+    fn test_generate_supports_context() {
+        let source_text = r#"
+            @supports (display: grid) {
+                @media print {
+                    @supports not selector(nav a) {
+                        .a { display: grid; }
+                    }
+                }
+            }
+        "#;
+        let hir = generate_hir(source_text);
+
+        assert!(hir.diagnostics.is_empty());
+        let rule = &hir.rules[0];
+        assert_eq!(rule.media, vec!["print".to_string()]);
+        assert_eq!(
+            rule.supports,
+            vec![
+                "(display: grid)".to_string(),
+                "not selector(nav a)".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_container_context() {
+        let source_text = r#"
+            .card { padding: 0; }
+            @container card (min-width: 400px) {
+                @media print {
+                    .card { padding: 1rem; }
+                }
+            }
+        "#;
+        let hir = generate_hir(source_text);
+
+        assert!(hir.diagnostics.is_empty());
+        assert!(hir.rules[0].container.is_empty());
+        assert_eq!(
+            hir.rules[1].container,
+            vec!["card (min-width: 400px)".to_string()]
+        );
+        assert_eq!(hir.rules[1].media, vec!["print".to_string()]);
+    }
+
+    #[test]
+    fn test_generate_layers() {
+        let source_text = r#"
+            @layer reset, components;
+            .a { color: red; }
+            @layer components {
+                @media print {
+                    @layer card {
+                        .a { color: blue; }
+                    }
+                }
+            }
+        "#;
+        let hir = generate_hir(source_text);
+
+        assert!(hir.diagnostics.is_empty());
+        assert_eq!(hir.layers, vec!["reset", "components.card", "components"]);
+        assert_eq!(hir.rules[0].layer, None);
+        assert_eq!(hir.rules[1].layer, Some("components.card".to_string()));
+        assert_eq!(hir.rules[1].media, vec!["print".to_string()]);
+    }
+
+    #[test]
+    fn test_generate_nested_rules() {
+        let source_text = r#"
+            .card {
+                color: red;
+                .title { font-weight: bold; }
+                @media print {
+                    padding: 0;
+                    .x & { color: black; }
+                }
+            }
+        "#;
+        let hir = generate_hir(source_text);
+
+        assert!(hir.diagnostics.is_empty());
+        let rules: Vec<(&str, &str, Option<usize>)> = hir
+            .rules
+            .iter()
+            .map(|rule| {
+                (
+                    rule.selector.as_str(),
+                    rule.resolved_selector.as_str(),
+                    rule.parent,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                (".card", ".card", None),
+                (".title", ".card .title", Some(0)),
+                // the declarations directly in `@media`
+                ("&", ".card", Some(0)),
+                (".x &", ".x .card", Some(0)),
+            ]
+        );
+        assert_eq!(hir.rules[0].declarations[0].property, "color");
+        assert_eq!(hir.rules[2].declarations[0].property, "padding");
+        assert_eq!(hir.rules[2].media, vec!["print".to_string()]);
+        assert_eq!(hir.rules[3].media, vec!["print".to_string()]);
+    }
+
+    #[test]
+    fn test_generate_class_names() {
+        let source_text = ".card, .panel { &:not(.hidden) > .title { color: red; } }";
+        let hir = generate_hir(source_text);
+
+        assert_eq!(hir.rules[0].class_names, vec!["card", "panel"]);
+        let rule = &hir.rules[1];
+        assert_eq!(
+            rule.resolved_selector,
+            ":is(.card, .panel):not(.hidden) > .title"
+        );
+        assert_eq!(rule.class_names, vec!["card", "panel", "hidden", "title"]);
+        assert_eq!(
+            rule.specificity,
+            vec![Specificity {
+                ids: 0,
+                classes: 3,
+                types: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_generate_imports() {
+        let source_text = r#"
+            @charset "utf-8";
+            @import "reset.css" layer;
+            @import url(theme.css) layer(base) supports(display: grid) screen and (color);
+            .a { color: red; }
+            @import "late.css";
+        "#;
+        let hir = generate_hir(source_text);
+
+        let imports: Vec<(&str, &str, Option<&str>)> = hir
+            .imports
+            .iter()
+            .map(|import| {
+                (
+                    import.url.as_str(),
+                    &source_text[import.url_span.start..import.url_span.end],
+                    import.layer.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            imports,
+            vec![
+                ("reset.css", "\"reset.css\"", Some("<anonymous@63>")),
+                ("theme.css", "url(theme.css)", Some("base")),
+            ]
+        );
+        assert_eq!(hir.imports[0].media, None);
+        assert_eq!(hir.imports[1].supports.as_deref(), Some("(display: grid)"));
+        assert_eq!(hir.imports[1].media.as_deref(), Some("screen and (color)"));
+        assert_eq!(hir.imports[1].location.start.line, 4);
+        // the misplaced import is reported and ignored
+        assert_eq!(hir.diagnostics[0].code, DiagnosticCode::MisplacedAtRule);
+    }
+
+    #[test]
+    fn test_generate_keyframes() {
+        let source_text = r#"
+            .spinner { color: red; }
+            @media (prefers-reduced-motion: no-preference) {
+                @keyframes spin {
+                    from, 50% { opacity: 0; }
+                    to { opacity: 1; }
+                }
+            }
+        "#;
+        let hir = generate_hir(source_text);
+
+        assert!(hir.diagnostics.is_empty());
+        assert_eq!(hir.rules.len(), 1);
+        let keyframes = &hir.keyframes[0];
+        assert_eq!(keyframes.name, "spin");
+        assert_eq!(keyframes.location.start.line, 4);
+        assert_eq!(keyframes.keyframes[0].selector, "from, 50%");
+        assert_eq!(keyframes.keyframes[1].declarations[0].value, "1");
+    }
+
+    #[test]
+    fn test_generate_properties() {
+        let source_text = r#"
+            @property --gap { syntax: "<length> | auto"; inherits: false; initial-value: 0px; }
+            @property --any { syntax: "*"; inherits: true; }
+            @property --invalid { syntax: "<length>"; inherits: false; }
+        "#;
+        let hir = generate_hir(source_text);
+
+        let properties: Vec<(&str, &str, bool, Option<&str>)> = hir
+            .properties
+            .iter()
+            .map(|property| {
+                (
+                    property.name.as_str(),
+                    property.syntax.as_str(),
+                    property.inherits,
+                    property.initial_value.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            properties,
+            vec![
+                ("--gap", "<length> | auto", false, Some("0px")),
+                ("--any", "*", true, None),
+            ]
+        );
+        assert_eq!(hir.diagnostics.len(), 1);
+        assert_eq!(hir.diagnostics[0].code, DiagnosticCode::InvalidPropertyRule);
+        assert_eq!(hir.diagnostics[0].location.start.line, 4);
+    }
+
+    #[test]
+    fn test_generate_partial_result() {
+        let source_text = ".a { color: ; }\n.b { color: red; }";
+        let hir = generate_hir(source_text);
+
+        assert_eq!(hir.rules.len(), 2);
+        assert!(hir.rules[0].declarations.is_empty());
+        assert_eq!(hir.rules[1].selector, ".b");
+        assert_eq!(hir.rules[1].declarations.len(), 1);
+
+        let diagnostic = &hir.diagnostics[0];
+        assert_eq!(diagnostic.code, DiagnosticCode::ExpectedValue);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.location.start.line, 1);
+        assert_eq!(diagnostic.location.start.column, 13);
+        let ignored = &hir.diagnostics[1];
+        assert_eq!(ignored.code, DiagnosticCode::IgnoredDeclaration);
+        assert_eq!(ignored.severity, Severity::Warning);
     }
 }
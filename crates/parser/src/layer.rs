@@ -0,0 +1,156 @@
+use crate::bnf::{ImportLayer, LayerBlockRule, LayerName, Rule, StyleSheet};
+use crate::span::Span;
+
+impl StyleSheet {
+    /// The [cascade layers](https://www.w3.org/TR/css-cascade-5/#layer-ordering) of the stylesheet
+    /// by their fully qualified names, e.g. `components.card`, from the lowest to the highest priority.
+    ///
+    /// Layers are ordered by where they are first declared, and a layer comes after the layers nested in it.
+    /// Styles which are not in any layer have a higher priority than every layer.
+    /// Layers declared inside `@media`, `@supports`, `@container` and style rules are included whether or not the condition holds.
+    pub fn layer_order(&self) -> Vec<String> {
+        let mut root = LayerNode::default();
+        collect_layers(&self.rules, &[], &mut root);
+        let mut order = Vec::new();
+        root.flatten(&mut Vec::new(), &mut order);
+        order
+    }
+}
+
+impl LayerBlockRule {
+    /// The name of the block's layer relative to the enclosing layer.
+    /// An anonymous layer is given a unique name by its position, e.g. `<anonymous@120>`.
+    pub fn layer_name(&self) -> Vec<String> {
+        match &self.name {
+            Some(name) => name.names.clone(),
+            None => vec![anonymous_layer_name(self.span)],
+        }
+    }
+}
+
+impl ImportLayer {
+    /// The name of the layer the imported stylesheet is put in, the same as [`LayerBlockRule::layer_name`]
+    pub fn layer_name(&self) -> Vec<String> {
+        match self {
+            ImportLayer::Named(name) => name.names.clone(),
+            ImportLayer::Anonymous(span) => vec![anonymous_layer_name(*span)],
+        }
+    }
+}
+
+fn anonymous_layer_name(span: Span) -> String {
+    format!("<anonymous@{}>", span.start)
+}
+
+/// A layer and the layers nested in it, in the order they were declared
+#[derive(Default)]
+struct LayerNode {
+    children: Vec<(String, LayerNode)>,
+}
+
+impl LayerNode {
+    /// Declare the layer with the given path relative to this one, along with its ancestors
+    fn declare(&mut self, path: &[String]) {
+        let Some((name, rest)) = path.split_first() else {
+            return;
+        };
+        let index = match self.children.iter().position(|(child, _)| child == name) {
+            Some(index) => index,
+            None => {
+                self.children.push((name.clone(), LayerNode::default()));
+                self.children.len() - 1
+            }
+        };
+        self.children[index].1.declare(rest);
+    }
+
+    /// Push the qualified names of the nested layers in priority order, each after its own nested layers
+    fn flatten(&self, path: &mut Vec<String>, order: &mut Vec<String>) {
+        for (name, child) in &self.children {
+            path.push(name.clone());
+            child.flatten(path, order);
+            order.push(path.join("."));
+            path.pop();
+        }
+    }
+}
+
+fn collect_layers(rules: &[Rule], parent: &[String], root: &mut LayerNode) {
+    let qualify = |name: Vec<String>| [parent.to_vec(), name].concat();
+    for rule in rules {
+        match rule {
+            Rule::LayerStatement(layer_statement_rule) => {
+                for LayerName { names, .. } in &layer_statement_rule.names {
+                    root.declare(&qualify(names.clone()));
+                }
+            }
+            Rule::LayerBlock(layer_block_rule) => {
+                let path = qualify(layer_block_rule.layer_name());
+                root.declare(&path);
+                collect_layers(&layer_block_rule.rules, &path, root);
+            }
+            Rule::Import(import_rule) => {
+                if let Some(layer) = &import_rule.layer {
+                    root.declare(&qualify(layer.layer_name()));
+                }
+            }
+            Rule::Media(media_rule) => collect_layers(&media_rule.rules, parent, root),
+            Rule::Supports(supports_rule) => collect_layers(&supports_rule.rules, parent, root),
+            Rule::Container(container_rule) => collect_layers(&container_rule.rules, parent, root),
+            Rule::StyleRule(style_rule) => collect_layers(&style_rule.rules, parent, root),
+            Rule::Keyframes(_)
+            | Rule::FontFace(_)
+            | Rule::Page(_)
+            | Rule::CounterStyle(_)
+            | Rule::Property(_)
+            | Rule::Charset(_)
+            | Rule::Namespace(_)
+            | Rule::Unknown(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_helper::parse_valid;
+
+    fn layer_order(source: &str) -> Vec<String> {
+        parse_valid(source).layer_order()
+    }
+
+    #[test]
+    fn test_layer_order() {
+        let source = r#"
+            @import url("reset.css") layer(reset);
+            @layer base, components;
+            @layer components.card { .card { padding: 0; } }
+            @layer utilities { @layer spacing; }
+            @media print { @layer print {} }
+            @layer base { @layer typography {} }
+            @layer components { @layer button; }
+        "#;
+        assert_eq!(
+            layer_order(source),
+            vec![
+                "reset",
+                "base.typography",
+                "base",
+                "components.card",
+                "components.button",
+                "components",
+                "utilities.spacing",
+                "utilities",
+                "print",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_anonymous_layers() {
+        let source = "@layer { .a {} } @layer a { @layer { .b {} } }";
+        assert_eq!(
+            layer_order(source),
+            vec!["<anonymous@0>", "a.<anonymous@28>", "a"]
+        );
+    }
+}
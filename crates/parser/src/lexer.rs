@@ -1,119 +1,519 @@
+use crate::span::{LineIndex, Span};
 use crate::token::{Token, TokenType};
 
-use lazy_static::lazy_static;
-use regex::Regex;
-
-lazy_static! {
-    static ref CLASS_SELECTOR: Regex = Regex::new(r"^\.[a-zA-Z_][a-zA-Z0-9_-]*").unwrap();
-    static ref ID_SELECTOR: Regex = Regex::new(r"^#[a-zA-Z_][a-zA-Z0-9_-]*").unwrap();
-    static ref IDENT: Regex = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_-]*").unwrap();
-    static ref ATKEYWORD: Regex = Regex::new(r"^@[a-zA-Z_][a-zA-Z0-9_-]*").unwrap();
-    static ref STRING: Regex = Regex::new(r#"^"[^"]*""#).unwrap();
-    static ref NUMBER: Regex = Regex::new(r"^[0-9]+").unwrap();
-    static ref PERCENTAGE: Regex = Regex::new(r"^[0-9]+%").unwrap();
-    static ref DIMENSION: Regex = Regex::new(r"^[0-9]+[a-zA-Z_][a-zA-Z0-9_-]*").unwrap();
-    static ref COLON: Regex = Regex::new(r"^:").unwrap();
-    static ref SEMICOLON: Regex = Regex::new(r"^;").unwrap();
-    static ref CURLY_LEFT: Regex = Regex::new(r"^\{").unwrap();
-    static ref CURLY_RIGHT: Regex = Regex::new(r"^\}").unwrap();
-    static ref PAREN_LEFT: Regex = Regex::new(r"^\(").unwrap();
-    static ref PAREN_RIGHT: Regex = Regex::new(r"^\)").unwrap();
-    static ref BRACKET_LEFT: Regex = Regex::new(r"^\[").unwrap();
-    static ref BRACKET_RIGHT: Regex = Regex::new(r"^\]").unwrap();
-    static ref S: Regex = Regex::new(r"^[ \t\r\n\f]+").unwrap();
-    static ref COMMENT: Regex = Regex::new(r"^/\*[^*]*\*+([^/*][^*]*\*+)*/").unwrap();
-    static ref COMMA: Regex = Regex::new(r"^,").unwrap();
-    static ref PLUS: Regex = Regex::new(r"^\+").unwrap();
-    static ref GREATER: Regex = Regex::new(r"^>").unwrap();
-    static ref TILDE: Regex = Regex::new(r"^~").unwrap();
-}
-
+/// A tokenizer following the [CSS Syntax Module Level 3](https://www.w3.org/TR/css-syntax-3/#tokenization) state machine.
+///
+/// The input is not preprocessed up front; instead every check for a newline treats `\r\n`, `\r` and `\f`
+/// as a single newline, and `U+0000` is replaced with `U+FFFD` where it ends up in a token value,
+/// so that `current` always points into the original source.
 pub struct Lexer {
     source: String,
     tokens: Vec<Token>,
     current: usize,
-    line: usize,
+    line_index: LineIndex,
 }
 
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+const MAXIMUM_ALLOWED_CODE_POINT: u32 = 0x10FFFF;
+
 impl Lexer {
     pub fn new(source: String) -> Lexer {
         Self {
+            line_index: LineIndex::new(&source),
             source,
             tokens: Vec::new(),
             current: 0,
-            line: 1,
         }
     }
 
     pub fn build(&mut self) {
-        // mappings of css token types to regex patterns
-        let patterns: Vec<(TokenType, Regex)> = vec![
-            (TokenType::ClassSelector, CLASS_SELECTOR.clone()),
-            (TokenType::IdSelector, ID_SELECTOR.clone()),
-            (TokenType::Ident, IDENT.clone()),
-            (TokenType::Atkeyword, ATKEYWORD.clone()),
-            (TokenType::String, STRING.clone()),
-            // Percentage & Dimension must come before Number to avoid matching the number part of the dimension
-            // Maybe we should use PEG parser to skip lexing, but for now, this is fine
-            (TokenType::Percentage, PERCENTAGE.clone()),
-            (TokenType::Dimension, DIMENSION.clone()),
-            (TokenType::Number, NUMBER.clone()),
-            (TokenType::Colon, COLON.clone()),
-            (TokenType::Semicolon, SEMICOLON.clone()),
-            (TokenType::CurlyLeft, CURLY_LEFT.clone()),
-            (TokenType::CurlyRight, CURLY_RIGHT.clone()),
-            (TokenType::ParenLeft, PAREN_LEFT.clone()),
-            (TokenType::ParenRight, PAREN_RIGHT.clone()),
-            (TokenType::BracketLeft, BRACKET_LEFT.clone()),
-            (TokenType::BracketRight, BRACKET_RIGHT.clone()),
-            (TokenType::S, S.clone()),
-            (TokenType::Comment, COMMENT.clone()),
-            (TokenType::Comma, COMMA.clone()),
-            (TokenType::Plus, PLUS.clone()),
-            (TokenType::Greater, GREATER.clone()),
-            (TokenType::Tilde, TILDE.clone()),
-        ];
-
-        // iterate over the source string to store tokens when matched with a regex pattern
-        while self.current < self.source.len() {
-            let remaining = &self.source[self.current..];
-            let mut matched = false;
-
-            for (token_type, regex) in &patterns {
-                if let Some(mat) = regex.find(remaining) {
-                    let lexeme = &remaining[mat.start()..mat.end()];
-
-                    // increment line count if token type is whitespace & contains newline
-                    // we do not store whitespace tokens
-                    if token_type == &TokenType::S {
-                        self.line += lexeme.matches('\n').count();
+        loop {
+            let token = self.consume_token();
+            let is_eof = token.token_type == TokenType::Eof;
+            self.tokens.push(token);
+            if is_eof {
+                break;
+            }
+        }
+    }
+
+    pub fn tokens(&self) -> Vec<Token> {
+        self.tokens.clone()
+    }
+
+    /// The line/column lookup table of the source, for resolving the spans of tokens and AST nodes
+    pub fn line_index(&self) -> LineIndex {
+        self.line_index.clone()
+    }
+
+    /// [4.3.1. Consume a token](https://www.w3.org/TR/css-syntax-3/#consume-token)
+    fn consume_token(&mut self) -> Token {
+        self.consume_comments();
+        let start = self.current;
+        let line = self.line_index.line(start);
+
+        let c = match self.consume() {
+            Some(c) => c,
+            None => {
+                return Token::new(
+                    TokenType::Eof,
+                    "".to_string(),
+                    "".to_string(),
+                    Span::new(start, start),
+                    line,
+                )
+            }
+        };
+
+        let (token_type, value) = match c {
+            c if is_whitespace(c) => {
+                while self.peek(0).is_some_and(is_whitespace) {
+                    self.consume();
+                }
+                (TokenType::S, None)
+            }
+            '"' | '\'' => self.consume_string(c),
+            '#' => {
+                if self.peek(0).is_some_and(is_name_code_point)
+                    || is_valid_escape(self.peek(0), self.peek(1))
+                {
+                    (TokenType::Hash, Some(self.consume_name()))
+                } else {
+                    (TokenType::Delim, None)
+                }
+            }
+            '(' => (TokenType::ParenLeft, None),
+            ')' => (TokenType::ParenRight, None),
+            '+' => {
+                if starts_number(Some(c), self.peek(0), self.peek(1)) {
+                    self.reconsume(c);
+                    self.consume_numeric()
+                } else {
+                    (TokenType::Plus, None)
+                }
+            }
+            ',' => (TokenType::Comma, None),
+            '-' => {
+                if starts_number(Some(c), self.peek(0), self.peek(1)) {
+                    self.reconsume(c);
+                    self.consume_numeric()
+                } else if self.peek(0) == Some('-') && self.peek(1) == Some('>') {
+                    self.consume();
+                    self.consume();
+                    (TokenType::Cdc, None)
+                } else if starts_identifier(Some(c), self.peek(0), self.peek(1)) {
+                    self.reconsume(c);
+                    self.consume_ident_like()
+                } else {
+                    (TokenType::Delim, None)
+                }
+            }
+            '.' => {
+                if starts_number(Some(c), self.peek(0), self.peek(1)) {
+                    self.reconsume(c);
+                    self.consume_numeric()
+                } else {
+                    (TokenType::Delim, None)
+                }
+            }
+            ':' => (TokenType::Colon, None),
+            ';' => (TokenType::Semicolon, None),
+            '<' => {
+                if self.peek(0) == Some('!')
+                    && self.peek(1) == Some('-')
+                    && self.peek(2) == Some('-')
+                {
+                    self.consume();
+                    self.consume();
+                    self.consume();
+                    (TokenType::Cdo, None)
+                } else {
+                    (TokenType::Delim, None)
+                }
+            }
+            '@' => {
+                if starts_identifier(self.peek(0), self.peek(1), self.peek(2)) {
+                    (TokenType::Atkeyword, Some(self.consume_name()))
+                } else {
+                    (TokenType::Delim, None)
+                }
+            }
+            '[' => (TokenType::BracketLeft, None),
+            '\\' => {
+                if is_valid_escape(Some(c), self.peek(0)) {
+                    self.reconsume(c);
+                    self.consume_ident_like()
+                } else {
+                    // parse error
+                    (TokenType::Delim, None)
+                }
+            }
+            ']' => (TokenType::BracketRight, None),
+            '{' => (TokenType::CurlyLeft, None),
+            '}' => (TokenType::CurlyRight, None),
+            c if c.is_ascii_digit() => {
+                self.reconsume(c);
+                self.consume_numeric()
+            }
+            c if is_ident_start_code_point(c) => {
+                self.reconsume(c);
+                self.consume_ident_like()
+            }
+            '>' => (TokenType::Greater, None),
+            '~' if self.peek(0) == Some('=') => {
+                self.consume();
+                (TokenType::Includes, None)
+            }
+            '~' => (TokenType::Tilde, None),
+            '|' if self.peek(0) == Some('=') => {
+                self.consume();
+                (TokenType::DashMatch, None)
+            }
+            _ => (TokenType::Delim, None),
+        };
+
+        let lexeme = self.source[start..self.current].to_string();
+        let value = value.unwrap_or_else(|| lexeme.clone());
+        Token::new(
+            token_type,
+            lexeme,
+            value,
+            Span::new(start, self.current),
+            line,
+        )
+    }
+
+    /// [4.3.2. Consume comments](https://www.w3.org/TR/css-syntax-3/#consume-comment)
+    fn consume_comments(&mut self) {
+        while self.peek(0) == Some('/') && self.peek(1) == Some('*') {
+            self.consume();
+            self.consume();
+            loop {
+                match self.consume() {
+                    Some('*') if self.peek(0) == Some('/') => {
+                        self.consume();
                         break;
                     }
+                    Some(_) => {}
+                    // parse error: unterminated comment
+                    None => return,
+                }
+            }
+        }
+    }
+
+    /// [4.3.3. Consume a numeric token](https://www.w3.org/TR/css-syntax-3/#consume-numeric-token)
+    fn consume_numeric(&mut self) -> (TokenType, Option<String>) {
+        self.consume_number();
+        if starts_identifier(self.peek(0), self.peek(1), self.peek(2)) {
+            (TokenType::Dimension, Some(self.consume_name()))
+        } else if self.peek(0) == Some('%') {
+            self.consume();
+            (TokenType::Percentage, None)
+        } else {
+            (TokenType::Number, None)
+        }
+    }
 
-                    self.tokens.push(Token::new(
-                        token_type.clone(),
-                        lexeme.to_string(),
-                        self.line,
-                    ));
+    /// [4.3.4. Consume an ident-like token](https://www.w3.org/TR/css-syntax-3/#consume-ident-like-token)
+    fn consume_ident_like(&mut self) -> (TokenType, Option<String>) {
+        let name = self.consume_name();
+        if name.eq_ignore_ascii_case("url") && self.peek(0) == Some('(') {
+            self.consume();
+            while self.peek(0).is_some_and(is_whitespace) && self.peek(1).is_some_and(is_whitespace)
+            {
+                self.consume();
+            }
+            let next = match self.peek(0) {
+                Some(c) if is_whitespace(c) => self.peek(1),
+                next => next,
+            };
+            if next == Some('"') || next == Some('\'') {
+                (TokenType::Function, Some(name))
+            } else {
+                self.consume_url()
+            }
+        } else if self.peek(0) == Some('(') {
+            self.consume();
+            (TokenType::Function, Some(name))
+        } else {
+            (TokenType::Ident, Some(name))
+        }
+    }
 
-                    self.current += lexeme.len();
-                    matched = true;
+    /// [4.3.5. Consume a string token](https://www.w3.org/TR/css-syntax-3/#consume-string-token)
+    fn consume_string(&mut self, ending: char) -> (TokenType, Option<String>) {
+        let mut value = String::new();
+        loop {
+            match self.peek(0) {
+                Some(c) if c == ending => {
+                    self.consume();
                     break;
                 }
+                // parse error: unterminated string
+                None => break,
+                Some(c) if is_newline(c) => {
+                    // parse error: the newline is left for the following whitespace token
+                    return (TokenType::BadString, None);
+                }
+                Some('\\') => {
+                    self.consume();
+                    match self.peek(0) {
+                        None => {}
+                        Some(c) if is_newline(c) => self.consume_newline(),
+                        Some(_) => value.push(self.consume_escape()),
+                    }
+                }
+                Some(c) => {
+                    self.consume();
+                    value.push(replace_null(c));
+                }
             }
+        }
+        (TokenType::String, Some(value))
+    }
 
-            if !matched {
-                // skip to the next character if no regex pattern is matched
-                self.current += 1;
+    /// [4.3.6. Consume a url token](https://www.w3.org/TR/css-syntax-3/#consume-url-token)
+    fn consume_url(&mut self) -> (TokenType, Option<String>) {
+        let mut value = String::new();
+        while self.peek(0).is_some_and(is_whitespace) {
+            self.consume();
+        }
+        loop {
+            match self.consume() {
+                Some(')') => break,
+                // parse error: unterminated url
+                None => break,
+                Some(c) if is_whitespace(c) => {
+                    while self.peek(0).is_some_and(is_whitespace) {
+                        self.consume();
+                    }
+                    match self.peek(0) {
+                        Some(')') => {
+                            self.consume();
+                            break;
+                        }
+                        None => break,
+                        Some(_) => {
+                            self.consume_bad_url_remnants();
+                            return (TokenType::BadUri, None);
+                        }
+                    }
+                }
+                Some(c) if c == '"' || c == '\'' || c == '(' || is_non_printable(c) => {
+                    // parse error
+                    self.consume_bad_url_remnants();
+                    return (TokenType::BadUri, None);
+                }
+                Some('\\') => {
+                    if is_valid_escape(Some('\\'), self.peek(0)) {
+                        value.push(self.consume_escape());
+                    } else {
+                        // parse error
+                        self.consume_bad_url_remnants();
+                        return (TokenType::BadUri, None);
+                    }
+                }
+                Some(c) => value.push(replace_null(c)),
             }
         }
+        (TokenType::Uri, Some(value))
+    }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), self.line));
+    /// [4.3.7. Consume an escaped code point](https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point)
+    ///
+    /// Assumes the `\` has already been consumed.
+    fn consume_escape(&mut self) -> char {
+        match self.consume() {
+            Some(c) if c.is_ascii_hexdigit() => {
+                let mut hex = c.to_string();
+                while hex.len() < 6 && self.peek(0).is_some_and(|c| c.is_ascii_hexdigit()) {
+                    hex.push(self.consume().unwrap());
+                }
+                if self.peek(0).is_some_and(is_whitespace) {
+                    self.consume_whitespace_code_point();
+                }
+                let code_point = u32::from_str_radix(&hex, 16).unwrap_or(0);
+                if code_point == 0 || code_point > MAXIMUM_ALLOWED_CODE_POINT {
+                    REPLACEMENT_CHARACTER
+                } else {
+                    // surrogates are rejected by char::from_u32
+                    char::from_u32(code_point).unwrap_or(REPLACEMENT_CHARACTER)
+                }
+            }
+            // parse error: escape at EOF
+            None => REPLACEMENT_CHARACTER,
+            Some(c) => replace_null(c),
+        }
     }
 
-    pub fn tokens(&self) -> Vec<Token> {
-        self.tokens.clone()
+    /// [4.3.11. Consume a name](https://www.w3.org/TR/css-syntax-3/#consume-name)
+    fn consume_name(&mut self) -> String {
+        let mut result = String::new();
+        loop {
+            match self.peek(0) {
+                Some(c) if is_name_code_point(c) => {
+                    self.consume();
+                    result.push(c);
+                }
+                Some('\\') if is_valid_escape(Some('\\'), self.peek(1)) => {
+                    self.consume();
+                    result.push(self.consume_escape());
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// [4.3.12. Consume a number](https://www.w3.org/TR/css-syntax-3/#consume-number)
+    ///
+    /// The number's representation is exactly the consumed source text, so it is not returned separately.
+    fn consume_number(&mut self) {
+        if matches!(self.peek(0), Some('+') | Some('-')) {
+            self.consume();
+        }
+        self.consume_digits();
+        if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.consume();
+            self.consume_digits();
+        }
+        if matches!(self.peek(0), Some('e') | Some('E')) {
+            let has_exponent = match self.peek(1) {
+                Some('+') | Some('-') => self.peek(2).is_some_and(|c| c.is_ascii_digit()),
+                Some(c) => c.is_ascii_digit(),
+                None => false,
+            };
+            if has_exponent {
+                self.consume();
+                if matches!(self.peek(0), Some('+') | Some('-')) {
+                    self.consume();
+                }
+                self.consume_digits();
+            }
+        }
+    }
+
+    fn consume_digits(&mut self) {
+        while self.peek(0).is_some_and(|c| c.is_ascii_digit()) {
+            self.consume();
+        }
+    }
+
+    /// [4.3.14. Consume the remnants of a bad url](https://www.w3.org/TR/css-syntax-3/#consume-remnants-of-bad-url)
+    fn consume_bad_url_remnants(&mut self) {
+        loop {
+            match self.peek(0) {
+                Some(')') => {
+                    self.consume();
+                    return;
+                }
+                None => return,
+                Some('\\') if is_valid_escape(Some('\\'), self.peek(1)) => {
+                    self.consume();
+                    self.consume_escape();
+                }
+                Some(_) => {
+                    self.consume();
+                }
+            }
+        }
+    }
+
+    /// Consume a newline, treating `\r\n` as a single code point
+    fn consume_newline(&mut self) {
+        if self.consume() == Some('\r') && self.peek(0) == Some('\n') {
+            self.consume();
+        }
+    }
+
+    /// Consume a single whitespace code point, treating `\r\n` as one
+    fn consume_whitespace_code_point(&mut self) {
+        if self.peek(0).is_some_and(is_newline) {
+            self.consume_newline();
+        } else {
+            self.consume();
+        }
+    }
+
+    fn consume(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.current += c.len_utf8();
+        Some(c)
+    }
+
+    /// Step back over a code point which was just consumed
+    fn reconsume(&mut self, c: char) {
+        self.current -= c.len_utf8();
+    }
+
+    /// Look at the `n`th code point after the current position without consuming it
+    fn peek(&self, n: usize) -> Option<char> {
+        self.source[self.current..].chars().nth(n)
+    }
+}
+
+fn is_newline(c: char) -> bool {
+    c == '\n' || c == '\r' || c == '\x0C'
+}
+
+fn is_whitespace(c: char) -> bool {
+    is_newline(c) || c == '\t' || c == ' '
+}
+
+fn is_ident_start_code_point(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_name_code_point(c: char) -> bool {
+    is_ident_start_code_point(c) || c.is_ascii_digit() || c == '-'
+}
+
+fn is_non_printable(c: char) -> bool {
+    matches!(c, '\x00'..='\x08' | '\x0B' | '\x0E'..='\x1F' | '\x7F')
+}
+
+fn replace_null(c: char) -> char {
+    if c == '\0' {
+        REPLACEMENT_CHARACTER
+    } else {
+        c
+    }
+}
+
+/// [4.3.8. Check if two code points are a valid escape](https://www.w3.org/TR/css-syntax-3/#starts-with-a-valid-escape)
+fn is_valid_escape(first: Option<char>, second: Option<char>) -> bool {
+    first == Some('\\') && !second.is_some_and(is_newline)
+}
+
+/// [4.3.9. Check if three code points would start an ident sequence](https://www.w3.org/TR/css-syntax-3/#would-start-an-identifier)
+pub(crate) fn starts_identifier(
+    first: Option<char>,
+    second: Option<char>,
+    third: Option<char>,
+) -> bool {
+    match first {
+        Some('-') => {
+            second.is_some_and(|c| is_ident_start_code_point(c) || c == '-')
+                || is_valid_escape(second, third)
+        }
+        Some('\\') => is_valid_escape(first, second),
+        Some(c) => is_ident_start_code_point(c),
+        None => false,
+    }
+}
+
+/// [4.3.10. Check if three code points would start a number](https://www.w3.org/TR/css-syntax-3/#starts-with-a-number)
+fn starts_number(first: Option<char>, second: Option<char>, third: Option<char>) -> bool {
+    match first {
+        Some('+') | Some('-') => match second {
+            Some(c) if c.is_ascii_digit() => true,
+            Some('.') => third.is_some_and(|c| c.is_ascii_digit()),
+            _ => false,
+        },
+        Some('.') => second.is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => c.is_ascii_digit(),
+        None => false,
     }
 }
 
@@ -121,6 +521,12 @@ impl Lexer {
 mod tests {
     use super::*;
 
+    fn token_types(source: &str) -> Vec<TokenType> {
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.build();
+        lexer.tokens().into_iter().map(|t| t.token_type).collect()
+    }
+
     #[test]
     fn test_lexer() {
         // Arrange
@@ -141,13 +547,15 @@ mod tests {
         // Assert: the last token should be Eof
         assert_eq!(last_token, TokenType::Eof);
 
-        // Act: get the first class selector token
-        let class_selector = tokens
-            .iter()
-            .find(|t| t.token_type == TokenType::ClassSelector)
+        // Act: get the token following the first `.` delimiter
+        let class_name = tokens
+            .windows(2)
+            .find(|pair| pair[0].token_type == TokenType::Delim && pair[0].value == ".")
+            .map(|pair| pair[1].clone())
             .unwrap();
-        // Assert: the first class selector token should be .container
-        assert_eq!(class_selector.lexeme, ".container");
+        // Assert: the class selector is a `.` delimiter followed by the ident container
+        assert_eq!(class_name.token_type, TokenType::Ident);
+        assert_eq!(class_name.lexeme, "container");
 
         // Act: get all ident tokens
         let idents: Vec<Token> = tokens
@@ -160,9 +568,19 @@ mod tests {
                 }
             })
             .collect();
-        // Assert: there should be 7 ident tokens
+        // Assert: there should be 8 ident tokens
         // 16px is considered as Dimension token
-        assert_eq!(idents.len(), 7);
+        assert_eq!(idents.len(), 8);
+
+        // Assert: the dimension token is on line 6 and its value is the unit
+        let dimension = tokens
+            .iter()
+            .find(|t| t.token_type == TokenType::Dimension)
+            .unwrap();
+        assert_eq!(dimension.lexeme, "16px");
+        assert_eq!(dimension.value, "px");
+        assert_eq!(dimension.line, 6);
+        assert_eq!(&source[dimension.span.start..dimension.span.end], "16px");
     }
 
     #[test]
@@ -187,4 +605,104 @@ mod tests {
         let tokens = lexer.tokens();
         println!("{:#?}", tokens);
     }
+
+    #[test]
+    fn test_numbers() {
+        let mut lexer = Lexer::new("-0.5em +.5 1e3 10% 1.e3 -->".to_string());
+        lexer.build();
+        let tokens: Vec<(TokenType, String)> = lexer
+            .tokens()
+            .into_iter()
+            .filter(|t| t.token_type != TokenType::S)
+            .map(|t| (t.token_type, t.lexeme))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenType::Dimension, "-0.5em".to_string()),
+                (TokenType::Number, "+.5".to_string()),
+                (TokenType::Number, "1e3".to_string()),
+                (TokenType::Percentage, "10%".to_string()),
+                // `1.` is not a number, so this is 1, ".", e3
+                (TokenType::Number, "1".to_string()),
+                (TokenType::Delim, ".".to_string()),
+                (TokenType::Ident, "e3".to_string()),
+                (TokenType::Cdc, "-->".to_string()),
+                (TokenType::Eof, "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_functions_and_urls() {
+        let mut lexer =
+            Lexer::new(r#"url( "a.png" ) url( b.png ) url(c d) rgb(0 0 0) <!--"#.to_string());
+        lexer.build();
+        let tokens: Vec<Token> = lexer
+            .tokens()
+            .into_iter()
+            .filter(|t| t.token_type != TokenType::S)
+            .collect();
+
+        assert_eq!(tokens[0].token_type, TokenType::Function);
+        assert_eq!(tokens[0].value, "url");
+        assert_eq!(tokens[1].token_type, TokenType::String);
+        assert_eq!(tokens[1].value, "a.png");
+        assert_eq!(tokens[2].token_type, TokenType::ParenRight);
+        assert_eq!(tokens[3].token_type, TokenType::Uri);
+        assert_eq!(tokens[3].value, "b.png");
+        assert_eq!(tokens[4].token_type, TokenType::BadUri);
+        assert_eq!(tokens[4].lexeme, "url(c d)");
+        assert_eq!(tokens[5].token_type, TokenType::Function);
+        assert_eq!(tokens[5].value, "rgb");
+        assert_eq!(tokens[9].token_type, TokenType::ParenRight);
+        assert_eq!(tokens[10].token_type, TokenType::Cdo);
+    }
+
+    #[test]
+    fn test_strings_hashes_and_escapes() {
+        let mut lexer =
+            Lexer::new("\"a\\\"b\" 'unterminated\n#fff #-x \\31 0 @media/**/~=|=".to_string());
+        lexer.build();
+        let tokens: Vec<Token> = lexer
+            .tokens()
+            .into_iter()
+            .filter(|t| t.token_type != TokenType::S)
+            .collect();
+
+        assert_eq!(tokens[0].token_type, TokenType::String);
+        assert_eq!(tokens[0].value, "a\"b");
+        assert_eq!(tokens[1].token_type, TokenType::BadString);
+        assert_eq!(tokens[2].token_type, TokenType::Hash);
+        assert_eq!(tokens[2].value, "fff");
+        assert_eq!(tokens[2].line, 2);
+        assert_eq!(tokens[3].token_type, TokenType::Hash);
+        assert_eq!(tokens[3].value, "-x");
+        assert_eq!(tokens[4].token_type, TokenType::Ident);
+        assert_eq!(tokens[4].value, "10");
+        assert_eq!(tokens[5].token_type, TokenType::Atkeyword);
+        assert_eq!(tokens[5].value, "media");
+        assert_eq!(tokens[6].token_type, TokenType::Includes);
+        assert_eq!(tokens[7].token_type, TokenType::DashMatch);
+    }
+
+    #[test]
+    fn test_delims_are_not_skipped() {
+        assert_eq!(
+            token_types("a>b+c~d*e!"),
+            vec![
+                TokenType::Ident,
+                TokenType::Greater,
+                TokenType::Ident,
+                TokenType::Plus,
+                TokenType::Ident,
+                TokenType::Tilde,
+                TokenType::Ident,
+                TokenType::Delim,
+                TokenType::Ident,
+                TokenType::Delim,
+                TokenType::Eof,
+            ]
+        );
+    }
 }
@@ -1,14 +1,20 @@
-extern crate lazy_static;
-extern crate regex;
 extern crate serde;
 
-mod bnf;
+pub mod bnf;
+pub mod color;
+pub mod diagnostic;
+pub mod encoding;
 mod helper;
 pub mod hir;
+pub mod layer;
 pub mod lexer;
+pub mod media;
+pub mod nesting;
+pub mod number;
 pub mod parser;
-mod token;
-
-use hir::HIRGenerator;
-use lexer::Lexer;
-use parser::Parser;
+pub mod property;
+pub mod selector;
+pub mod span;
+#[cfg(test)]
+mod test_helper;
+pub mod token;
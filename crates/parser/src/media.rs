@@ -0,0 +1,440 @@
+use crate::bnf::{
+    LiteralValue, MediaComparison, MediaCondition, MediaFeature, MediaFeatureKind,
+    MediaFeatureValue, MediaQuery, MediaQueryList, MediaQueryModifier, Unit,
+};
+use crate::number::UnitKind;
+use std::cmp::Ordering;
+
+/// The environment media queries are evaluated against, e.g. a device preset picked by the user.
+///
+/// Lengths are in CSS pixels and `resolution` is in `dppx`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaEnvironment {
+    /// `screen` or `print`
+    pub media_type: String,
+    pub width: f64,
+    pub height: f64,
+    pub resolution: f64,
+    pub prefers_color_scheme: ColorScheme,
+    pub hover: Hover,
+    pub pointer: Pointer,
+    /// The initial font size, which `em` and `rem` resolve against in media queries
+    pub font_size: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hover {
+    None,
+    Hover,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pointer {
+    None,
+    Coarse,
+    Fine,
+}
+
+impl MediaEnvironment {
+    /// A phone held in portrait orientation
+    pub fn mobile() -> Self {
+        Self {
+            media_type: "screen".to_string(),
+            width: 390.0,
+            height: 844.0,
+            resolution: 3.0,
+            prefers_color_scheme: ColorScheme::Light,
+            hover: Hover::None,
+            pointer: Pointer::Coarse,
+            font_size: 16.0,
+        }
+    }
+
+    /// A laptop or desktop display with a mouse
+    pub fn desktop() -> Self {
+        Self {
+            media_type: "screen".to_string(),
+            width: 1440.0,
+            height: 900.0,
+            resolution: 1.0,
+            prefers_color_scheme: ColorScheme::Light,
+            hover: Hover::Hover,
+            pointer: Pointer::Fine,
+            font_size: 16.0,
+        }
+    }
+}
+
+impl Default for MediaEnvironment {
+    fn default() -> Self {
+        Self::desktop()
+    }
+}
+
+impl MediaQueryList {
+    /// Whether the list matches the environment, i.e. any of its queries does.
+    /// An empty list always matches.
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|query| query.evaluate(environment))
+    }
+}
+
+impl MediaQuery {
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        let type_matches = match &self.media_type {
+            Some(media_type) => {
+                media_type.eq_ignore_ascii_case("all")
+                    || media_type.eq_ignore_ascii_case(&environment.media_type)
+            }
+            None => true,
+        };
+        // an unknown condition stays unknown through `not` and only fails the query at the end
+        let condition_matches = match &self.condition {
+            Some(condition) => condition.evaluate(environment),
+            None => Some(true),
+        };
+        let matches = if type_matches {
+            condition_matches
+        } else {
+            Some(false)
+        };
+        let matches = if self.modifier == Some(MediaQueryModifier::Not) {
+            matches.map(|matches| !matches)
+        } else {
+            matches
+        };
+        matches.unwrap_or(false)
+    }
+}
+
+impl MediaCondition {
+    /// Evaluate the condition with the spec's three-valued logic, where `None` is unknown,
+    /// e.g. for `<general-enclosed>` or a feature this evaluator does not know.
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> Option<bool> {
+        match self {
+            MediaCondition::Feature(feature) => feature.evaluate(environment),
+            MediaCondition::Not(condition) => condition.evaluate(environment).map(|result| !result),
+            MediaCondition::And(conditions) => {
+                let results = conditions
+                    .iter()
+                    .map(|condition| condition.evaluate(environment));
+                let mut result = Some(true);
+                for condition_result in results {
+                    match condition_result {
+                        Some(false) => return Some(false),
+                        None => result = None,
+                        Some(true) => {}
+                    }
+                }
+                result
+            }
+            MediaCondition::Or(conditions) => {
+                let results = conditions
+                    .iter()
+                    .map(|condition| condition.evaluate(environment));
+                let mut result = Some(false);
+                for condition_result in results {
+                    match condition_result {
+                        Some(true) => return Some(true),
+                        None => result = None,
+                        Some(false) => {}
+                    }
+                }
+                result
+            }
+            MediaCondition::GeneralEnclosed(..) => None,
+        }
+    }
+}
+
+/// The value of a media feature in an environment
+enum FeatureValue {
+    /// A length in px, a resolution in dppx, a ratio or an integer
+    Number(f64),
+    /// A discrete keyword, e.g. `portrait` for `orientation`
+    Keyword(&'static str),
+}
+
+/// The kinds of values a [`FeatureValue::Number`] may be compared with
+#[derive(Clone, Copy, PartialEq)]
+enum FeatureType {
+    Length,
+    Resolution,
+    Ratio,
+    Integer,
+    /// A plain number, e.g. the dppx of `-webkit-device-pixel-ratio`
+    Number,
+}
+
+impl MediaFeature {
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> Option<bool> {
+        let name = self.kind.name().to_ascii_lowercase();
+        match &self.kind {
+            MediaFeatureKind::Boolean { .. } => {
+                let (value, _) = feature_value(&name, environment)?;
+                Some(match value {
+                    FeatureValue::Number(number) => number != 0.0,
+                    FeatureValue::Keyword(keyword) => keyword != "none",
+                })
+            }
+            MediaFeatureKind::Plain { value, .. } => {
+                // `min-` and `max-` prefixes are shorthands for ranges, also after a vendor prefix
+                let (vendor, name) = match name.strip_prefix("-webkit-") {
+                    Some(name) => ("-webkit-", name),
+                    None => ("", name.as_str()),
+                };
+                let (name, comparison) = if let Some(name) = name.strip_prefix("min-") {
+                    (name, MediaComparison::Ge)
+                } else if let Some(name) = name.strip_prefix("max-") {
+                    (name, MediaComparison::Le)
+                } else {
+                    (name, MediaComparison::Eq)
+                };
+                let name = format!("{}{}", vendor, name);
+                let (feature, feature_type) = feature_value(&name, environment)?;
+                match feature {
+                    FeatureValue::Number(feature) => {
+                        let value = resolve_value(value, feature_type, environment)?;
+                        Some(compare(feature, comparison, value))
+                    }
+                    FeatureValue::Keyword(keyword) => match value {
+                        MediaFeatureValue::Literal(LiteralValue::Ident(ident))
+                            if comparison == MediaComparison::Eq =>
+                        {
+                            Some(ident.eq_ignore_ascii_case(keyword))
+                        }
+                        _ => None,
+                    },
+                }
+            }
+            MediaFeatureKind::Range { start, end, .. } => {
+                // only numeric features can be used in a range
+                let (FeatureValue::Number(feature), feature_type) =
+                    feature_value(&name, environment)?
+                else {
+                    return None;
+                };
+                let mut result = true;
+                if let Some((value, comparison)) = start {
+                    let value = resolve_value(value, feature_type, environment)?;
+                    result &= compare(value, *comparison, feature);
+                }
+                if let Some((comparison, value)) = end {
+                    let value = resolve_value(value, feature_type, environment)?;
+                    result &= compare(feature, *comparison, value);
+                }
+                Some(result)
+            }
+        }
+    }
+}
+
+/// Look up a [media feature](https://www.w3.org/TR/mediaqueries-4/#mq-features) in the environment.
+/// Returns `None` for features this evaluator does not know.
+fn feature_value(
+    name: &str,
+    environment: &MediaEnvironment,
+) -> Option<(FeatureValue, FeatureType)> {
+    let value = match name {
+        "width" | "device-width" => (FeatureValue::Number(environment.width), FeatureType::Length),
+        "height" | "device-height" => (
+            FeatureValue::Number(environment.height),
+            FeatureType::Length,
+        ),
+        "aspect-ratio" | "device-aspect-ratio" => (
+            FeatureValue::Number(environment.width / environment.height),
+            FeatureType::Ratio,
+        ),
+        "resolution" => (
+            FeatureValue::Number(environment.resolution),
+            FeatureType::Resolution,
+        ),
+        "-webkit-device-pixel-ratio" => (
+            FeatureValue::Number(environment.resolution),
+            FeatureType::Number,
+        ),
+        "orientation" => {
+            let orientation = if environment.height >= environment.width {
+                "portrait"
+            } else {
+                "landscape"
+            };
+            (FeatureValue::Keyword(orientation), FeatureType::Integer)
+        }
+        "prefers-color-scheme" => {
+            let scheme = match environment.prefers_color_scheme {
+                ColorScheme::Light => "light",
+                ColorScheme::Dark => "dark",
+            };
+            (FeatureValue::Keyword(scheme), FeatureType::Integer)
+        }
+        "hover" | "any-hover" => {
+            let hover = match environment.hover {
+                Hover::None => "none",
+                Hover::Hover => "hover",
+            };
+            (FeatureValue::Keyword(hover), FeatureType::Integer)
+        }
+        "pointer" | "any-pointer" => {
+            let pointer = match environment.pointer {
+                Pointer::None => "none",
+                Pointer::Coarse => "coarse",
+                Pointer::Fine => "fine",
+            };
+            (FeatureValue::Keyword(pointer), FeatureType::Integer)
+        }
+        // screens are assumed to have 8 bits per color channel and no color palette
+        "color" => (FeatureValue::Number(8.0), FeatureType::Integer),
+        "color-index" | "monochrome" | "grid" => (FeatureValue::Number(0.0), FeatureType::Integer),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Resolve a value in a media query to the unit the feature is compared in, e.g. `40em` to `640` px.
+/// Returns `None` when the value is not valid for the feature.
+fn resolve_value(
+    value: &MediaFeatureValue,
+    feature_type: FeatureType,
+    environment: &MediaEnvironment,
+) -> Option<f64> {
+    let literal = match value {
+        MediaFeatureValue::Ratio(numerator, denominator) if feature_type == FeatureType::Ratio => {
+            return Some(numerator.value / denominator.value);
+        }
+        MediaFeatureValue::Ratio(..) => return None,
+        MediaFeatureValue::Literal(literal) => literal,
+    };
+
+    match (literal, feature_type) {
+        // a unitless zero is a valid length
+        (LiteralValue::Number(number), FeatureType::Length) if number.value == 0.0 => Some(0.0),
+        // a single number is a ratio with a denominator of 1
+        (
+            LiteralValue::Number(number),
+            FeatureType::Ratio | FeatureType::Integer | FeatureType::Number,
+        ) => Some(number.value),
+        (LiteralValue::Dimension(dimension), FeatureType::Length) => {
+            let px = match dimension.unit {
+                Unit::Em | Unit::Rem => environment.font_size,
+                Unit::Vw => environment.width / 100.0,
+                Unit::Vh => environment.height / 100.0,
+                Unit::Vmin => environment.width.min(environment.height) / 100.0,
+                Unit::Vmax => environment.width.max(environment.height) / 100.0,
+                Unit::Px | Unit::Cm | Unit::Mm | Unit::Q | Unit::In | Unit::Pt | Unit::Pc => {
+                    dimension.unit.canonical_factor()?
+                }
+                _ => return None,
+            };
+            Some(dimension.number.value * px)
+        }
+        (LiteralValue::Dimension(dimension), FeatureType::Resolution)
+            if dimension.unit.kind() == Some(UnitKind::Resolution) =>
+        {
+            dimension.canonical_value()
+        }
+        _ => None,
+    }
+}
+
+fn compare(left: f64, comparison: MediaComparison, right: f64) -> bool {
+    let ordering = match left.partial_cmp(&right) {
+        Some(ordering) => ordering,
+        None => return false,
+    };
+    match comparison {
+        MediaComparison::Lt => ordering == Ordering::Less,
+        MediaComparison::Le => ordering != Ordering::Greater,
+        MediaComparison::Gt => ordering == Ordering::Greater,
+        MediaComparison::Ge => ordering != Ordering::Less,
+        MediaComparison::Eq => ordering == Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bnf::Rule;
+    use crate::test_helper::parse;
+
+    fn query_list(prelude: &str) -> MediaQueryList {
+        let (stylesheet, _) = parse(&format!("@media {} {{}}", prelude));
+        match &stylesheet.rules[0] {
+            Rule::Media(media_rule) => media_rule.query_list.clone(),
+            _ => panic!("expected media rule"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_presets() {
+        // (query list, matches mobile, matches desktop)
+        let cases = [
+            ("", true, true),
+            ("all", true, true),
+            ("screen", true, true),
+            ("print", false, false),
+            ("not print", true, true),
+            ("only screen and (max-width: 600px)", true, false),
+            ("(min-width: 768px)", false, true),
+            ("(min-width: 48em)", false, true),
+            ("(width < 600px)", true, false),
+            ("(600px <= width < 1200px)", false, false),
+            ("(1200px < width <= 1440px)", false, true),
+            ("(orientation: portrait)", true, false),
+            ("(hover: hover) and (pointer: fine)", false, true),
+            ("(hover: none), (min-resolution: 2dppx)", true, false),
+            ("(min-resolution: 192dpi)", true, false),
+            ("(prefers-color-scheme: dark)", false, false),
+            ("(min-aspect-ratio: 16/10)", false, true),
+            ("(aspect-ratio < 1)", true, false),
+            ("(hover)", false, true),
+            ("(color)", true, true),
+            ("(monochrome)", false, false),
+            ("not (hover)", true, false),
+            ("(max-width: 600px) or (orientation: landscape)", true, true),
+            ("(-webkit-min-device-pixel-ratio: 2)", true, false),
+            ("(-webkit-max-device-pixel-ratio: 1.5)", false, true),
+            ("(-webkit-device-pixel-ratio: 3)", true, false),
+        ];
+        let mobile = MediaEnvironment::mobile();
+        let desktop = MediaEnvironment::desktop();
+        for (prelude, matches_mobile, matches_desktop) in cases {
+            let query_list = query_list(prelude);
+            assert_eq!(query_list.evaluate(&mobile), matches_mobile, "{}", prelude);
+            assert_eq!(
+                query_list.evaluate(&desktop),
+                matches_desktop,
+                "{}",
+                prelude
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_unknown() {
+        let environment = MediaEnvironment {
+            prefers_color_scheme: ColorScheme::Dark,
+            ..MediaEnvironment::desktop()
+        };
+        assert!(query_list("(prefers-color-scheme: dark)").evaluate(&environment));
+
+        // unknown features and <general-enclosed> are unknown, which `not` keeps unknown
+        let query_list = query_list("not (unknown-feature: 1)");
+        let Some(condition) = &query_list.queries[0].condition else {
+            panic!("expected condition");
+        };
+        assert_eq!(condition.evaluate(&environment), None);
+        assert!(!query_list.evaluate(&environment));
+        assert!(!self::query_list("not screen and (foo)").evaluate(&environment));
+        assert!(!self::query_list("not screen and (foo)").evaluate(&MediaEnvironment::mobile()));
+        assert!(self::query_list("not print and (foo)").evaluate(&environment));
+        assert!(!self::query_list("(width: 10deg)").evaluate(&environment));
+        assert!(self::query_list("(hover) or foo(bar)").evaluate(&environment));
+    }
+}
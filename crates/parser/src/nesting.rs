@@ -0,0 +1,439 @@
+use crate::bnf::{
+    Combinator, CompoundSelector, ContainerRule, Declaration, LayerBlockRule, MediaRule,
+    NthArgument, PseudoClassArgument, PseudoClassSelector, Rule, Selector, SimpleSelector,
+    StyleRule, StyleSheet, SupportsRule,
+};
+use crate::helper::ToString;
+use crate::span::Span;
+
+impl Selector {
+    /// The selector with the [nesting selector](https://www.w3.org/TR/css-nesting-1/#nest-selector) resolved
+    /// against `parent`, the resolved selectors of the parent style rule, e.g. `.card:hover` for `&:hover` in `.card`.
+    ///
+    /// A selector without `&`, or starting with a combinator, is relative to the parent, e.g. `.card > .title` for `> .title`.
+    /// The parent is wrapped in `:is()` when substituting it as is would change what is matched,
+    /// e.g. `.x &` in `.a .b` resolves to `.x :is(.a .b)`. Outside of a style rule, `&` stands for `:scope`.
+    pub fn resolve(&self, parent: Option<&[Selector]>) -> Selector {
+        let Some(parent) = parent else {
+            let scope = |span| {
+                SimpleSelector::PseudoClass(PseudoClassSelector {
+                    ident: ":scope".to_string(),
+                    argument: None,
+                    span,
+                })
+            };
+            return replace_nesting_selectors(self, &scope);
+        };
+
+        let wrapped = |span| {
+            SimpleSelector::PseudoClass(PseudoClassSelector {
+                ident: ":is".to_string(),
+                argument: Some(PseudoClassArgument::Selectors(parent.to_vec())),
+                span,
+            })
+        };
+        let single_parent = match parent {
+            [selector] => Some(selector),
+            _ => None,
+        };
+
+        let mut compounds: Vec<CompoundSelector> = Vec::new();
+        let mut has_nesting_selector = false;
+        for (index, compound) in self.compounds.iter().enumerate() {
+            let mut resolved = CompoundSelector {
+                subclass_selectors: vec![],
+                ..compound.clone()
+            };
+            for subclass_selector in &compound.subclass_selectors {
+                let SimpleSelector::Nesting(nesting_selector) = subclass_selector else {
+                    // e.g. `:not(&)`
+                    has_nesting_selector |= contains_nesting_selector(subclass_selector);
+                    resolved
+                        .subclass_selectors
+                        .push(replace_nesting_selector(subclass_selector, &wrapped));
+                    continue;
+                };
+                has_nesting_selector = true;
+                let is_empty =
+                    resolved.type_selector.is_none() && resolved.subclass_selectors.is_empty();
+                match single_parent {
+                    // a type selector must come first in a compound
+                    Some(selector)
+                        if selector.compounds.len() == 1
+                            && (is_empty || selector.compounds[0].type_selector.is_none()) =>
+                    {
+                        let parent_compound = &selector.compounds[0];
+                        resolved.type_selector = resolved
+                            .type_selector
+                            .or_else(|| parent_compound.type_selector.clone());
+                        resolved
+                            .subclass_selectors
+                            .extend(parent_compound.subclass_selectors.clone());
+                    }
+                    // a complex parent can start the selector, e.g. `&.c` in `.a .b` is `.a .b.c`
+                    Some(selector) if index == 0 && compound.combinator.is_none() && is_empty => {
+                        let (last, ancestors) = selector
+                            .compounds
+                            .split_last()
+                            .expect("a selector has at least one compound");
+                        compounds.extend(ancestors.iter().cloned());
+                        resolved.combinator = last.combinator;
+                        resolved.type_selector = last.type_selector.clone();
+                        resolved
+                            .subclass_selectors
+                            .extend(last.subclass_selectors.clone());
+                    }
+                    _ => resolved
+                        .subclass_selectors
+                        .push(wrapped(nesting_selector.span)),
+                }
+            }
+            compounds.push(resolved);
+        }
+
+        let is_relative = self.compounds[0].combinator.is_some();
+        if has_nesting_selector && !is_relative {
+            return Selector {
+                compounds,
+                span: self.span,
+            };
+        }
+        // the selector is relative to the parent, as if it started with `& `
+        compounds[0].combinator = compounds[0].combinator.or(Some(Combinator::Descendant));
+        let mut prefix = match single_parent {
+            Some(selector) => selector.compounds.clone(),
+            None => vec![CompoundSelector {
+                combinator: None,
+                type_selector: None,
+                subclass_selectors: vec![wrapped(self.span)],
+                span: self.span,
+            }],
+        };
+        prefix.extend(compounds);
+        Selector {
+            compounds: prefix,
+            span: self.span,
+        }
+    }
+}
+
+/// Whether the simple selector is `&` or has one in its arguments, e.g. `:not(&)`
+fn contains_nesting_selector(simple_selector: &SimpleSelector) -> bool {
+    match simple_selector {
+        SimpleSelector::Nesting(_) => true,
+        SimpleSelector::PseudoClass(pseudo_class_selector) => pseudo_class_selector
+            .selectors()
+            .iter()
+            .flat_map(|selector| &selector.compounds)
+            .flat_map(|compound| &compound.subclass_selectors)
+            .any(contains_nesting_selector),
+        _ => false,
+    }
+}
+
+/// Replace every `&` in the selector, including those in the arguments of pseudo-classes, with `replacement(span)`
+fn replace_nesting_selectors(
+    selector: &Selector,
+    replacement: &dyn Fn(Span) -> SimpleSelector,
+) -> Selector {
+    Selector {
+        compounds: selector
+            .compounds
+            .iter()
+            .map(|compound| CompoundSelector {
+                subclass_selectors: compound
+                    .subclass_selectors
+                    .iter()
+                    .map(|subclass_selector| {
+                        replace_nesting_selector(subclass_selector, replacement)
+                    })
+                    .collect(),
+                ..compound.clone()
+            })
+            .collect(),
+        span: selector.span,
+    }
+}
+
+fn replace_nesting_selector(
+    simple_selector: &SimpleSelector,
+    replacement: &dyn Fn(Span) -> SimpleSelector,
+) -> SimpleSelector {
+    let replace_all = |selectors: &[Selector]| -> Vec<Selector> {
+        selectors
+            .iter()
+            .map(|selector| replace_nesting_selectors(selector, replacement))
+            .collect()
+    };
+    match simple_selector {
+        SimpleSelector::Nesting(nesting_selector) => replacement(nesting_selector.span),
+        SimpleSelector::PseudoClass(pseudo_class_selector) => {
+            let argument = match &pseudo_class_selector.argument {
+                Some(PseudoClassArgument::Selectors(selectors)) => {
+                    Some(PseudoClassArgument::Selectors(replace_all(selectors)))
+                }
+                Some(PseudoClassArgument::RelativeSelectors(selectors)) => Some(
+                    PseudoClassArgument::RelativeSelectors(replace_all(selectors)),
+                ),
+                Some(PseudoClassArgument::Nth(nth)) => {
+                    Some(PseudoClassArgument::Nth(NthArgument {
+                        selectors: nth.selectors.as_deref().map(replace_all),
+                        ..nth.clone()
+                    }))
+                }
+                argument => argument.clone(),
+            };
+            SimpleSelector::PseudoClass(PseudoClassSelector {
+                argument,
+                ..pseudo_class_selector.clone()
+            })
+        }
+        _ => simple_selector.clone(),
+    }
+}
+
+impl StyleSheet {
+    /// Rewrite nested style rules into rules with resolved selectors which older browsers understand,
+    /// e.g. `.a { color: red; @media print { .b { color: blue; } } }` into
+    /// `.a { color: red; } @media print { .a .b { color: blue; } }`.
+    ///
+    /// Nested rules are placed after their parent, and the conditional rules they are in are hoisted along with them.
+    /// Unknown at-rules such as `@apply` stay in the style rule they are nested in, as they may depend on it.
+    /// A style rule left without declarations or unknown at-rules is removed.
+    pub fn flatten_nesting(&self) -> StyleSheet {
+        StyleSheet {
+            rules: flatten_rules(&self.rules, None),
+            span: self.span,
+        }
+    }
+}
+
+fn flatten_rules(rules: &[Rule], parent: Option<&[Selector]>) -> Vec<Rule> {
+    let mut flattened = Vec::new();
+    for rule in rules {
+        match rule {
+            Rule::StyleRule(style_rule) => {
+                let selectors: Vec<Selector> = style_rule
+                    .selectors
+                    .iter()
+                    .map(|selector| selector.resolve(parent))
+                    .collect();
+                let (unknown_rules, nested_rules): (Vec<Rule>, Vec<Rule>) = style_rule
+                    .rules
+                    .iter()
+                    .cloned()
+                    .partition(|rule| matches!(rule, Rule::Unknown(_)));
+                if !style_rule.declarations.is_empty()
+                    || !unknown_rules.is_empty()
+                    || nested_rules.is_empty()
+                {
+                    flattened.push(resolved_style_rule(
+                        &selectors,
+                        style_rule.declarations.clone(),
+                        unknown_rules,
+                        style_rule.span,
+                    ));
+                }
+                flattened.extend(flatten_rules(&nested_rules, Some(&selectors)));
+            }
+            // e.g. `@apply` in a conditional rule nested in a style rule
+            Rule::Unknown(unknown_at_rule) => match parent {
+                Some(parent) => flattened.push(resolved_style_rule(
+                    parent,
+                    vec![],
+                    vec![rule.clone()],
+                    unknown_at_rule.span,
+                )),
+                None => flattened.push(rule.clone()),
+            },
+            Rule::Media(media_rule) => flattened.push(Rule::Media(MediaRule {
+                rules: flatten_rules(&media_rule.rules, parent),
+                ..media_rule.clone()
+            })),
+            Rule::Supports(supports_rule) => flattened.push(Rule::Supports(SupportsRule {
+                rules: flatten_rules(&supports_rule.rules, parent),
+                ..supports_rule.clone()
+            })),
+            Rule::Container(container_rule) => flattened.push(Rule::Container(ContainerRule {
+                rules: flatten_rules(&container_rule.rules, parent),
+                ..container_rule.clone()
+            })),
+            Rule::LayerBlock(layer_block_rule) => {
+                flattened.push(Rule::LayerBlock(LayerBlockRule {
+                    rules: flatten_rules(&layer_block_rule.rules, parent),
+                    ..layer_block_rule.clone()
+                }))
+            }
+            _ => flattened.push(rule.clone()),
+        }
+    }
+    flattened
+}
+
+/// A style rule which is already resolved, and so only has unknown at-rules nested in it
+fn resolved_style_rule(
+    selectors: &[Selector],
+    declarations: Vec<Declaration>,
+    rules: Vec<Rule>,
+    span: Span,
+) -> Rule {
+    Rule::StyleRule(StyleRule {
+        selector_text: selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect::<Vec<String>>()
+            .join(", "),
+        selectors: selectors.to_vec(),
+        declarations,
+        rules,
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bnf::Rule;
+    use crate::helper::ToString;
+    use crate::test_helper::parse_valid;
+
+    #[test]
+    fn test_resolve_selector() {
+        // (parent selectors, nested selector, resolved)
+        let cases = [
+            (".card", ".title", ".card .title"),
+            (".card", "> .title", ".card > .title"),
+            (".card", ".x &", ".x .card"),
+            (".card", "&:hover", ".card:hover"),
+            (".card", "& + &", ".card + .card"),
+            (".card", "~ .x &", ".card ~ .x .card"),
+            ("div", ".x&", ".x:is(div)"),
+            ("div .b", "&.c", "div .b.c"),
+            ("div > .b", "& .c", "div > .b .c"),
+            ("div .b", ".x &", ".x :is(div .b)"),
+            ("div .b", ".x&", ".x:is(div .b)"),
+            (".a, #b", ".c", ":is(.a, #b) .c"),
+            (".a, #b", "&:hover", ":is(.a, #b):hover"),
+            (".a, #b", ".x &", ".x :is(.a, #b)"),
+            (".card", ":not(&)", ":not(:is(.card))"),
+            (".card", ".x:has(> &)", ".x:has(> :is(.card))"),
+            (".card", ":not(.x)", ".card :not(.x)"),
+        ];
+        for (parent, selector, resolved) in cases {
+            let stylesheet = parse_valid(&format!("{} {{ {} {{}} }}", parent, selector));
+            let Rule::StyleRule(parent_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            let Rule::StyleRule(style_rule) = &parent_rule.rules[0] else {
+                panic!("expected nested style rule");
+            };
+            assert_eq!(
+                style_rule.selectors[0]
+                    .resolve(Some(&parent_rule.selectors))
+                    .to_string(),
+                resolved,
+                "{}",
+                selector
+            );
+        }
+
+        let stylesheet = parse_valid("& .a, :is(&) .a {}");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(
+            style_rule.selectors[0].resolve(None).to_string(),
+            ":scope .a"
+        );
+        assert_eq!(
+            style_rule.selectors[1].resolve(None).to_string(),
+            ":is(:scope) .a"
+        );
+    }
+
+    #[test]
+    fn test_flatten_nesting() {
+        let source = r#"
+            .card {
+                color: red;
+                .title { font-weight: bold; }
+                @media print {
+                    padding: 0;
+                    @supports (display: grid) {
+                        .x & { display: grid; }
+                    }
+                }
+            }
+            .list { .item { margin: 0; } }
+            @layer base { .a { .b { color: blue; } } }
+        "#;
+        let stylesheet = parse_valid(source).flatten_nesting();
+
+        fn describe(rules: &[Rule]) -> Vec<String> {
+            rules
+                .iter()
+                .map(|rule| match rule {
+                    Rule::StyleRule(style_rule) => {
+                        assert!(style_rule.rules.is_empty());
+                        format!(
+                            "{} {{ {} }}",
+                            style_rule.selector_text,
+                            style_rule.declarations.len()
+                        )
+                    }
+                    Rule::Media(media_rule) => format!(
+                        "@media {} {{ {} }}",
+                        media_rule.query_list.to_string(),
+                        describe(&media_rule.rules).join(" ")
+                    ),
+                    Rule::Supports(supports_rule) => format!(
+                        "@supports {} {{ {} }}",
+                        supports_rule.condition.to_string(),
+                        describe(&supports_rule.rules).join(" ")
+                    ),
+                    Rule::LayerBlock(layer_block_rule) => format!(
+                        "@layer {} {{ {} }}",
+                        layer_block_rule.layer_name().join("."),
+                        describe(&layer_block_rule.rules).join(" ")
+                    ),
+                    _ => panic!("unexpected rule"),
+                })
+                .collect()
+        }
+        assert_eq!(
+            describe(&stylesheet.rules),
+            vec![
+                ".card { 1 }",
+                ".card .title { 1 }",
+                "@media print { .card { 1 } @supports (display: grid) { .x .card { 1 } } }",
+                ".list .item { 1 }",
+                "@layer base { .a .b { 1 } }",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_print_flattened() {
+        let source = r#"
+            .a {
+                @apply foo;
+                color: red;
+                &:hover { color: blue !important; }
+                @media (min-width: 600px) { @apply bar; .b { --x: { a: b }; } }
+            }
+            @tailwind base;
+        "#;
+        let printed = parse_valid(source).flatten_nesting().to_string();
+        assert_eq!(
+            printed,
+            [
+                ".a { color: red; @apply foo; }",
+                ".a:hover { color: blue !important; }",
+                "@media (min-width: 600px) { .a { @apply bar; } .a .b { --x: { a: b }; } }",
+                "@tailwind base;",
+            ]
+            .join("\n")
+        );
+        // the printed stylesheet parses into the same rules
+        assert_eq!(parse_valid(&printed).to_string(), printed);
+    }
+}
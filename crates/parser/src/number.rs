@@ -0,0 +1,269 @@
+use crate::bnf::{Dimension, LiteralValue, Number, Unit};
+use crate::helper::ToString;
+use crate::token::{Token, TokenType};
+
+/// The kind of quantity a [`Unit`] measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitKind {
+    Length,
+    Angle,
+    Time,
+    Frequency,
+    Resolution,
+    Flex,
+}
+
+const UNITS: [Unit; 62] = [
+    Unit::Px,
+    Unit::Cm,
+    Unit::Mm,
+    Unit::Q,
+    Unit::In,
+    Unit::Pt,
+    Unit::Pc,
+    Unit::Em,
+    Unit::Rem,
+    Unit::Ex,
+    Unit::Rex,
+    Unit::Cap,
+    Unit::Rcap,
+    Unit::Ch,
+    Unit::Rch,
+    Unit::Ic,
+    Unit::Ric,
+    Unit::Lh,
+    Unit::Rlh,
+    Unit::Vw,
+    Unit::Vh,
+    Unit::Vi,
+    Unit::Vb,
+    Unit::Vmin,
+    Unit::Vmax,
+    Unit::Svw,
+    Unit::Svh,
+    Unit::Svi,
+    Unit::Svb,
+    Unit::Svmin,
+    Unit::Svmax,
+    Unit::Lvw,
+    Unit::Lvh,
+    Unit::Lvi,
+    Unit::Lvb,
+    Unit::Lvmin,
+    Unit::Lvmax,
+    Unit::Dvw,
+    Unit::Dvh,
+    Unit::Dvi,
+    Unit::Dvb,
+    Unit::Dvmin,
+    Unit::Dvmax,
+    Unit::Cqw,
+    Unit::Cqh,
+    Unit::Cqi,
+    Unit::Cqb,
+    Unit::Cqmin,
+    Unit::Cqmax,
+    Unit::Deg,
+    Unit::Grad,
+    Unit::Rad,
+    Unit::Turn,
+    Unit::S,
+    Unit::Ms,
+    Unit::Hz,
+    Unit::Khz,
+    Unit::Dpi,
+    Unit::Dpcm,
+    Unit::Dppx,
+    Unit::X,
+    Unit::Fr,
+];
+
+impl Unit {
+    /// The unit named `name`, which is ASCII case-insensitive, e.g. [`Unit::Px`] for `PX`
+    pub fn from_name(name: &str) -> Unit {
+        UNITS
+            .iter()
+            .find(|unit| unit.to_string().eq_ignore_ascii_case(name))
+            .copied()
+            .unwrap_or(Unit::Unknown)
+    }
+
+    /// The kind of quantity the unit measures, or `None` for an unknown unit
+    pub fn kind(&self) -> Option<UnitKind> {
+        let kind = match self {
+            Unit::Deg | Unit::Grad | Unit::Rad | Unit::Turn => UnitKind::Angle,
+            Unit::S | Unit::Ms => UnitKind::Time,
+            Unit::Hz | Unit::Khz => UnitKind::Frequency,
+            Unit::Dpi | Unit::Dpcm | Unit::Dppx | Unit::X => UnitKind::Resolution,
+            Unit::Fr => UnitKind::Flex,
+            Unit::Unknown => return None,
+            _ => UnitKind::Length,
+        };
+        Some(kind)
+    }
+
+    /// The number of canonical units (`px`, `deg`, `s`, `hz` or `dppx`) in one of this unit,
+    /// e.g. `96` for `in`, or `None` when it depends on the context such as `em` and `vw`
+    pub fn canonical_factor(&self) -> Option<f64> {
+        let factor = match self {
+            Unit::Px | Unit::Deg | Unit::S | Unit::Hz | Unit::Dppx | Unit::X => 1.0,
+            Unit::Cm => 96.0 / 2.54,
+            Unit::Mm => 96.0 / 25.4,
+            Unit::Q => 96.0 / 101.6,
+            Unit::In => 96.0,
+            Unit::Pt => 96.0 / 72.0,
+            Unit::Pc => 16.0,
+            Unit::Grad => 0.9,
+            Unit::Rad => 180.0 / std::f64::consts::PI,
+            Unit::Turn => 360.0,
+            Unit::Ms => 0.001,
+            Unit::Khz => 1000.0,
+            Unit::Dpi => 1.0 / 96.0,
+            Unit::Dpcm => 2.54 / 96.0,
+            _ => return None,
+        };
+        Some(factor)
+    }
+}
+
+impl Number {
+    /// Parse a number as written, e.g. `-.5e2`
+    pub fn parse(raw: &str) -> Option<Number> {
+        let value: f64 = raw.parse().ok()?;
+        Some(Number {
+            value,
+            is_integer: !raw.contains(['.', 'e', 'E']),
+            raw: raw.to_string(),
+        })
+    }
+}
+
+impl Dimension {
+    /// The value in the canonical unit of its kind, e.g. `48` for `0.5in`, or `None` when it depends on the context
+    pub fn canonical_value(&self) -> Option<f64> {
+        Some(self.number.value * self.unit.canonical_factor()?)
+    }
+}
+
+/// Convert a number, percentage or dimension token into a literal value
+pub(crate) fn numeric_literal(token: &Token) -> Option<LiteralValue> {
+    match token.token_type {
+        TokenType::Number => Number::parse(&token.lexeme).map(LiteralValue::Number),
+        TokenType::Percentage => {
+            Number::parse(token.lexeme.strip_suffix('%')?).map(LiteralValue::Percentage)
+        }
+        TokenType::Dimension => {
+            let (number, raw_unit) = token.lexeme.split_at(number_length(&token.lexeme));
+            Some(LiteralValue::Dimension(Dimension {
+                number: Number::parse(number)?,
+                unit: Unit::from_name(&token.value),
+                raw_unit: raw_unit.to_string(),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// The length of the number at the start of a dimension such as `-1.5e2px`
+pub(crate) fn number_length(dimension: &str) -> usize {
+    let bytes = dimension.as_bytes();
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'+') | Some(b'-')) {
+        end += 1;
+    }
+    while end < bytes.len() && (bytes[end].is_ascii_digit() || bytes[end] == b'.') {
+        end += 1;
+    }
+    // an exponent is only part of the number when followed by digits, otherwise it starts the unit
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent_end = end + 1;
+        if matches!(bytes.get(exponent_end), Some(b'+') | Some(b'-')) {
+            exponent_end += 1;
+        }
+        if bytes.get(exponent_end).is_some_and(u8::is_ascii_digit) {
+            end = exponent_end;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn literal(source: &str) -> LiteralValue {
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.build();
+        numeric_literal(&lexer.tokens()[0]).unwrap()
+    }
+
+    #[test]
+    fn test_numeric_literal() {
+        // (source, value, is integer)
+        let numbers = [
+            ("0", 0.0, true),
+            ("-12", -12.0, true),
+            ("+.5", 0.5, false),
+            ("1e3", 1000.0, false),
+            ("-1.5E-2", -0.015, false),
+        ];
+        for (source, value, is_integer) in numbers {
+            let LiteralValue::Number(number) = literal(source) else {
+                panic!("expected number for {}", source);
+            };
+            assert_eq!(number.value, value, "{}", source);
+            assert_eq!(number.is_integer, is_integer, "{}", source);
+            assert_eq!(number.to_string(), source);
+        }
+
+        let LiteralValue::Percentage(percentage) = literal("50.0%") else {
+            panic!("expected percentage");
+        };
+        assert_eq!(percentage.value, 50.0);
+        assert!(!percentage.is_integer);
+
+        // (source, value, unit)
+        let dimensions = [
+            ("-0.5em", -0.5, Unit::Em),
+            ("1e3ms", 1000.0, Unit::Ms),
+            ("-1.5e2em", -150.0, Unit::Em),
+            ("2e", 2.0, Unit::Unknown),
+            ("2e-px", 2.0, Unit::Unknown),
+            ("10PX", 10.0, Unit::Px),
+            ("1fr", 1.0, Unit::Fr),
+            ("3svmin", 3.0, Unit::Svmin),
+        ];
+        for (source, value, unit) in dimensions {
+            let LiteralValue::Dimension(dimension) = literal(source) else {
+                panic!("expected dimension for {}", source);
+            };
+            assert_eq!(dimension.number.value, value, "{}", source);
+            assert_eq!(dimension.unit, unit, "{}", source);
+            assert_eq!(dimension.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn test_unit() {
+        // (unit, kind, value of 1 in the canonical unit)
+        let cases = [
+            ("in", Some(UnitKind::Length), Some(96.0)),
+            ("cqi", Some(UnitKind::Length), None),
+            ("turn", Some(UnitKind::Angle), Some(360.0)),
+            ("ms", Some(UnitKind::Time), Some(0.001)),
+            ("kHz", Some(UnitKind::Frequency), Some(1000.0)),
+            ("x", Some(UnitKind::Resolution), Some(1.0)),
+            ("fr", Some(UnitKind::Flex), None),
+            ("foo", None, None),
+        ];
+        for (name, kind, factor) in cases {
+            let unit = Unit::from_name(name);
+            assert_eq!(unit.kind(), kind, "{}", name);
+            assert_eq!(unit.canonical_factor(), factor, "{}", name);
+        }
+    }
+}
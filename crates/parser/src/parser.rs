@@ -1,10 +1,23 @@
 use crate::bnf::{
-    ClassSelector, Declaration, IdSelector, LiteralValue, PseudoClassSelector,
-    PseudoElementSelector, Rule, Selector, SimpleSelector, StyleRule, StyleSheet, TypeSelector,
-    Value,
+    AttributeMatcher, AttributeModifier, AttributeOperator, AttributeSelector, CharsetRule,
+    ClassSelector, Combinator, ComponentValue, CompoundSelector, ContainerCondition,
+    ContainerQuery, ContainerRule, CounterStyleRule, Declaration, FontFaceRule, FunctionValue,
+    IdSelector, ImportLayer, ImportRule, Keyframe, KeyframeSelector, KeyframeSelectorKind,
+    KeyframesRule, LayerBlockRule, LayerName, LayerStatementRule, LiteralValue, MathExpression,
+    MathFunction, MathOperator, MediaComparison, MediaCondition, MediaFeature, MediaFeatureKind,
+    MediaFeatureValue, MediaQuery, MediaQueryList, MediaQueryModifier, MediaRule, NamespaceRule,
+    NestingSelector, NthArgument, Number, PageMarginRule, PageRule, PageSelector, PropertyRule,
+    PropertySyntax, PseudoClassArgument, PseudoClassSelector, PseudoElementSelector, Rule,
+    Selector, SimpleSelector, StyleFeature, StyleQuery, StyleRule, StyleSheet, SupportsCondition,
+    SupportsDeclaration, SupportsRule, SyntaxComponent, SyntaxComponentName, SyntaxMultiplier,
+    TypeSelector, UnknownAtRule, Value,
 };
+use crate::diagnostic::{Diagnostic, DiagnosticCode, ParseError, Severity};
 use crate::helper::ToString;
-use crate::token::{self, Token, TokenType};
+use crate::lexer::starts_identifier;
+use crate::number::{number_length, numeric_literal};
+use crate::span::Span;
+use crate::token::{Token, TokenType};
 use std::rc::Rc;
 
 pub struct Parser {
@@ -16,21 +29,36 @@ pub struct Parser {
     ```
     `tokens` will be a vector of the following tokens:
 
-    ```rust
-    Token { token_type: ClassSelector, lexeme: ".class", line: 2 }
-    Token { token_type: CurlyLeft, lexeme: "{", line: 2 }
-    Token { token_type: Ident, lexeme: "color", line: 3 }
-    Token { token_type: Colon, lexeme: ":", line: 3 }
-    Token { token_type: Ident, lexeme: "red", line: 3 }
-    Token { token_type: Semicolon, lexeme: ";", line: 3 }
-    Token { token_type: CurlyRight, lexeme: "}", line: 4 }
-    Token { token_type: Eof, lexeme: "", line: 5 }
+    ```text
+    Token { token_type: Delim, lexeme: ".", value: ".", span: Span { start: 0, end: 1 }, line: 1 }
+    Token { token_type: Ident, lexeme: "class", value: "class", span: Span { start: 1, end: 6 }, line: 1 }
+    Token { token_type: S, lexeme: " ", value: " ", span: Span { start: 6, end: 7 }, line: 1 }
+    Token { token_type: CurlyLeft, lexeme: "{", value: "{", span: Span { start: 7, end: 8 }, line: 1 }
+    Token { token_type: S, lexeme: "\n    ", value: "\n    ", span: Span { start: 8, end: 13 }, line: 1 }
+    Token { token_type: Ident, lexeme: "color", value: "color", span: Span { start: 13, end: 18 }, line: 2 }
+    Token { token_type: Colon, lexeme: ":", value: ":", span: Span { start: 18, end: 19 }, line: 2 }
+    Token { token_type: S, lexeme: " ", value: " ", span: Span { start: 19, end: 20 }, line: 2 }
+    Token { token_type: Ident, lexeme: "red", value: "red", span: Span { start: 20, end: 23 }, line: 2 }
+    Token { token_type: Semicolon, lexeme: ";", value: ";", span: Span { start: 23, end: 24 }, line: 2 }
+    Token { token_type: S, lexeme: "\n", value: "\n", span: Span { start: 24, end: 25 }, line: 2 }
+    Token { token_type: CurlyRight, lexeme: "}", value: "}", span: Span { start: 25, end: 26 }, line: 3 }
+    Token { token_type: Eof, lexeme: "", value: "", span: Span { start: 26, end: 26 }, line: 3 }
     ```
     */
     tokens: Vec<Token>,
     current: usize,
+    /// Errors which were recovered from, along with the ranges skipped to recover
+    diagnostics: Vec<Diagnostic>,
+    /// The number of style rules enclosing the current position, which allow nested rules to mix with declarations
+    nesting_depth: usize,
+    /// The number of blocks, functions and parentheses enclosing the current position, limited to [`MAX_DEPTH`]
+    depth: usize,
 }
 
+/// The deepest blocks, functions and parentheses can be nested. Anything nested deeper is skipped,
+/// so that deeply nested input cannot overflow the stack, which is particularly small in WebAssembly.
+const MAX_DEPTH: usize = 128;
+
 /**
  *
  * ## Parse strategy
@@ -40,23 +68,25 @@ pub struct Parser {
  *
  * Based on the [BNF grammar](./ast.rs), we can induce the following rules:
  * First Set:
- * - First(<stylesheet>) = First(<rule>) ∪ { EOF } = { identifier, '.', '#', '[', ':', '@media', EOF }
- * - First(<rule>) = First(<selector>) ∪ { '@media' } = { identifier, '.', '#', '[', ':', '@media' }
- * - First(<selectors>) = { identifier, '.', '#', '[', ':' }
+ * - First(<stylesheet>) = First(<rule>) ∪ { EOF } = { identifier, '.', '#', '[', ':', at-keyword, EOF }
+ * - First(<rule>) = First(<selector>) ∪ { at-keyword } = { identifier, '*', '|', '.', '#', '[', ':', '&', at-keyword }
+ * - First(<selectors>) = { identifier, '*', '|', '.', '#', '[', ':', '&' }
  * - First(<combinator>) = { '+', '>', '~', ' ' }
  *
  * Follow Set:
  * - Follow(<stylesheet>) = { EOF }
  * - Follow(<rule>) = { EOF, '{' }
  * - Follow(<selectors>) = { '{', '}' }
- * - Follow(<combinator>) = { identifier, '.', '#', '[', ':' }
+ * - Follow(<combinator>) = { identifier, '*', '|', '.', '#', '[', ':', '&' }
  *
  * Director Set:
- * - Director(selector, element) = { identifier }
+ * - Director(selector, element) = { identifier, '*', '|' }
+ * - Director(selector, attribute) = { '[' }
  * - Director(selector, class) = { '.' }
  * - Director(selector, id) = { '#' }
  * - Director(selector, pseudo-class) = { ':' }
  * - Director(selector, pseudo-element) = { '::' }
+ * - Director(selector, nesting) = { '&' }
  *
  * Director(selector, element) ∩ Director(selector, class) = ∅
  * Director(selector, element) ∩ Director(selector, id) = ∅
@@ -73,272 +103,5115 @@ pub struct Parser {
  *
  */
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Parser {
-        Self { tokens, current: 0 }
+    pub fn new(mut tokens: Vec<Token>) -> Parser {
+        // the parser relies on the tokens always being terminated by Eof, as the lexer produces them
+        if tokens.last().map(|token| &token.token_type) != Some(&TokenType::Eof) {
+            let end = tokens.last().map_or(0, |token| token.span.end);
+            let line = tokens.last().map_or(1, |token| token.line);
+            tokens.push(Token::new(
+                TokenType::Eof,
+                "".to_string(),
+                "".to_string(),
+                Span::new(end, end),
+                line,
+            ));
+        }
+        Self {
+            tokens,
+            current: 0,
+            diagnostics: Vec::new(),
+            nesting_depth: 0,
+            depth: 0,
+        }
+    }
+
+    /// Parse the tokens into a stylesheet.
+    ///
+    /// This never panics. Errors are recovered from the way browsers do:
+    /// a rule which cannot be parsed is skipped up to the end of its block, and
+    /// a declaration which cannot be parsed is skipped up to the next `;` or the end of its block.
+    /// Each error is reported as a diagnostic, followed by a warning spanning the skipped range.
+    pub fn parse(&mut self) -> (StyleSheet, Vec<Diagnostic>) {
+        let rules = self.parse_rules(true);
+        let stylesheet = StyleSheet {
+            rules,
+            span: Span::new(0, self.tokens[self.tokens.len() - 1].span.end),
+        };
+        (stylesheet, std::mem::take(&mut self.diagnostics))
     }
 
-    pub fn parse(&mut self) -> StyleSheet {
+    // <rule>*
+    /// Parse rules up to the end of input, or when `top_level` is false, up to the `}` closing the enclosing block
+    pub fn parse_rules(&mut self, top_level: bool) -> Vec<Rule> {
+        if !top_level && self.nesting_depth > 0 {
+            return self.parse_nested_group_rules();
+        }
         let mut rules = Vec::new();
-        while !self.is_end() {
-            rules.push(self.parse_rule());
+        if !top_level && self.enter_nested().is_err() {
+            self.skip_block_contents();
+            return rules;
+        }
+        loop {
+            self.skip_whitespace();
+            // <!-- and --> are allowed around top-level rules for legacy reasons
+            while top_level && self.match_token(&[TokenType::S, TokenType::Cdo, TokenType::Cdc]) {}
+            if self.is_end() || (!top_level && self.check_token_type(TokenType::CurlyRight)) {
+                break;
+            }
+            self.parse_rule_or_recover(&mut rules, top_level);
+        }
+        if !top_level {
+            self.depth -= 1;
         }
-        StyleSheet { rules }
+        rules
     }
 
-    // <rule> ::= <style-rule> | <media-rule>
-    pub fn parse_rule(&mut self) -> Rule {
-        if self.match_token(&[TokenType::Atkeyword]) {
-            // self.parse_media_rule();
-            panic!("Media rule not implemented yet")
-        } else {
-            Rule::StyleRule(self.parse_style_rule())
+    /// Parse a rule and push it to `rules`, or skip and report it when it cannot be parsed or is not allowed there
+    fn parse_rule_or_recover(&mut self, rules: &mut Vec<Rule>, top_level: bool) {
+        let token = self.current_token();
+        let start = token.span.start;
+        let is_at_rule = self.check_token_type(TokenType::Atkeyword);
+        match self.parse_rule() {
+            Ok(rule) => {
+                match check_rule_position(&rule, rules, top_level, self.nesting_depth > 0) {
+                    None => rules.push(rule),
+                    Some(message) => {
+                        self.diagnostics.push(
+                            ParseError::new(DiagnosticCode::MisplacedAtRule, message, token.span)
+                                .into(),
+                        );
+                        self.report_skipped(DiagnosticCode::IgnoredRule, "Rule is ignored", start);
+                    }
+                }
+            }
+            Err(error) => {
+                self.report(error);
+                if is_at_rule {
+                    self.recover_at_rule();
+                } else {
+                    self.recover_rule(!top_level);
+                }
+                self.report_skipped(DiagnosticCode::IgnoredRule, "Rule is ignored", start);
+            }
         }
     }
 
-    // <style-rule> ::= <selectors> "{" <declarations> "}"
-    pub fn parse_style_rule(&mut self) -> StyleRule {
-        let selectors = self.parse_selectors();
-        self.consume_next_token(TokenType::CurlyLeft)
-            .expect("Expected '{' after selectors");
-        let declarations = self.parse_declarations();
-        self.consume_next_token(TokenType::CurlyRight)
-            .expect("Expected '}' after declarations");
+    // <style-block>
+    /// Parse the body of a conditional rule nested in a style rule, such as `@media print { color: red; }`.
+    /// Its declarations apply to the elements matched by the parent rule, so they are wrapped in a `&` rule.
+    fn parse_nested_group_rules(&mut self) -> Vec<Rule> {
+        let start = self.current_token().span.start;
+        let (declarations, mut rules) = self.parse_style_block();
+        if let (Some(first), Some(last)) = (declarations.first(), declarations.last()) {
+            let span = first.span.to(last.span);
+            let span_at_start = Span::new(start, start);
+            let selector = Selector {
+                compounds: vec![CompoundSelector {
+                    combinator: None,
+                    type_selector: None,
+                    subclass_selectors: vec![SimpleSelector::Nesting(NestingSelector {
+                        span: span_at_start,
+                    })],
+                    span: span_at_start,
+                }],
+                span: span_at_start,
+            };
+            rules.insert(
+                0,
+                Rule::StyleRule(StyleRule {
+                    selector_text: selector.to_string(),
+                    selectors: vec![selector],
+                    declarations,
+                    rules: vec![],
+                    span,
+                }),
+            );
+        }
+        rules
+    }
 
-        let selector_text = selectors
-            .iter()
-            .map(|selector| selector.to_string())
-            .collect::<Vec<String>>()
-            .join(" ");
+    // <rule> ::= <style-rule> | <media-rule> | <supports-rule> | <keyframes-rule> | <font-face-rule> | <page-rule> | <counter-style-rule> | <property-rule> | <layer-rule> | <import-rule> | <container-rule> | <charset-rule> | <namespace-rule> | <unknown-at-rule>
+    pub fn parse_rule(&mut self) -> Result<Rule, ParseError> {
+        if self.check_at_keyword("media") {
+            Ok(Rule::Media(self.parse_media_rule()?))
+        } else if self.check_at_keyword("supports") {
+            Ok(Rule::Supports(self.parse_supports_rule()?))
+        } else if self.keyframes_prefix().is_some() {
+            Ok(Rule::Keyframes(self.parse_keyframes_rule()?))
+        } else if self.check_at_keyword("font-face") {
+            Ok(Rule::FontFace(self.parse_font_face_rule()?))
+        } else if self.check_at_keyword("page") {
+            Ok(Rule::Page(self.parse_page_rule()?))
+        } else if self.check_at_keyword("counter-style") {
+            Ok(Rule::CounterStyle(self.parse_counter_style_rule()?))
+        } else if self.check_at_keyword("property") {
+            Ok(Rule::Property(self.parse_property_rule()?))
+        } else if self.check_at_keyword("layer") {
+            self.parse_layer_rule()
+        } else if self.check_at_keyword("import") {
+            Ok(Rule::Import(self.parse_import_rule()?))
+        } else if self.check_at_keyword("container") {
+            Ok(Rule::Container(self.parse_container_rule()?))
+        } else if self.check_at_keyword("charset") {
+            Ok(Rule::Charset(self.parse_charset_rule()?))
+        } else if self.check_at_keyword("namespace") {
+            Ok(Rule::Namespace(self.parse_namespace_rule()?))
+        } else if self.check_token_type(TokenType::Atkeyword) {
+            Ok(Rule::Unknown(self.parse_unknown_at_rule()))
+        } else {
+            Ok(Rule::StyleRule(self.parse_style_rule()?))
+        }
+    }
 
-        StyleRule {
-            selector_text,
-            selectors,
-            declarations,
+    // <media-rule> ::= "@media" <media-query-list> "{" <rule>* "}"
+    pub fn parse_media_rule(&mut self) -> Result<MediaRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        let query_list = self.parse_media_query_list();
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after media query list",
+        )?;
+        let rules = self.parse_rules(false);
+        self.expect_block_end("Expected '}' after rules");
+        Ok(MediaRule {
+            query_list,
+            rules,
+            span: self.span_from(start),
+        })
+    }
+
+    // <media-query-list> ::= <media-query> | <media-query> "," <media-query-list>
+    /// Parse a media query list up to the `{` or `;` following it.
+    /// Queries which fail to parse are reported and replaced with `not all`, so this never fails.
+    pub fn parse_media_query_list(&mut self) -> MediaQueryList {
+        self.skip_whitespace();
+        let start = self.current_token().span.start;
+        let mut queries = Vec::new();
+        if self.check_media_query_list_end() {
+            return MediaQueryList {
+                queries,
+                span: Span::new(start, start),
+            };
+        }
+        loop {
+            self.skip_whitespace();
+            let query_start = self.current_token().span.start;
+            match self.parse_media_query() {
+                Ok(query) => queries.push(query),
+                Err(error) => {
+                    self.report(error);
+                    while !self.check_media_query_list_end()
+                        && !self.check_token_type(TokenType::Comma)
+                    {
+                        self.skip_component_value();
+                    }
+                    queries.push(MediaQuery {
+                        modifier: Some(MediaQueryModifier::Not),
+                        media_type: Some("all".to_string()),
+                        condition: None,
+                        span: self.span_from(query_start),
+                    });
+                }
+            }
+            if !self.match_token(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        MediaQueryList {
+            queries,
+            span: self.span_from(start),
         }
     }
 
-    // <selectors> ::= <selector> | <selector> <combinator> <selectors>
-    pub fn parse_selectors(&mut self) -> Vec<Selector> {
-        let mut selectors = Vec::new();
-        selectors.push(self.parse_selector());
-        while self.match_token(&[TokenType::Comma]) {
-            selectors.push(self.parse_selector());
+    // <media-query> ::= <media-condition> | [ "not" | "only" ]? <media-type> [ "and" <media-condition-without-or> ]?
+    pub fn parse_media_query(&mut self) -> Result<MediaQuery, ParseError> {
+        let start = self.current_token().span.start;
+        let mut modifier = None;
+        let mut media_type = None;
+        let mut condition = None;
+
+        let starts_with_type = if self.check_ident("not") {
+            // `not screen` is a modifier, while `not (color)` is a condition
+            let next = self.tokens[self.current + 1..]
+                .iter()
+                .find(|token| token.token_type != TokenType::S);
+            next.map(|token| &token.token_type) == Some(&TokenType::Ident)
+        } else {
+            self.check_token_type(TokenType::Ident)
+        };
+
+        if starts_with_type {
+            if self.check_ident("not") {
+                modifier = Some(MediaQueryModifier::Not);
+            } else if self.check_ident("only") {
+                modifier = Some(MediaQueryModifier::Only);
+            }
+            if modifier.is_some() {
+                self.advance();
+                self.skip_whitespace();
+            }
+            let token = self.current_token();
+            let is_reserved = ["not", "only", "and", "or", "layer"]
+                .iter()
+                .any(|keyword| token.value.eq_ignore_ascii_case(keyword));
+            if token.token_type != TokenType::Ident || is_reserved {
+                return Err(
+                    self.error_at_current(DiagnosticCode::InvalidMediaQuery, "Expected media type")
+                );
+            }
+            media_type = Some(self.advance().value.clone());
+            self.skip_whitespace();
+            if self.check_ident("and") {
+                self.advance();
+                self.skip_whitespace();
+                condition = Some(self.parse_media_condition(false)?);
+            }
+        } else {
+            condition = Some(self.parse_media_condition(true)?);
+        }
+
+        self.skip_whitespace();
+        if !self.check_media_query_list_end() && !self.check_token_type(TokenType::Comma) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidMediaQuery,
+                "Unexpected token in media query",
+            ));
         }
-        selectors
+        Ok(MediaQuery {
+            modifier,
+            media_type,
+            condition,
+            span: self.span_from(start),
+        })
     }
 
-    // <selector> ::= <simple-selector> | <selector> <simple-selector>
-    pub fn parse_selector(&mut self) -> Selector {
-        let mut simple_selectors = Vec::new();
-        simple_selectors.push(self.parse_simple_selector());
-        while self.match_token(&[TokenType::Greater, TokenType::Plus, TokenType::Tilde]) {
-            simple_selectors.push(self.parse_simple_selector());
+    // <media-condition> ::= <media-not> | <media-in-parens> [ <media-and>* | <media-or>* ]
+    // <media-condition-without-or> ::= <media-not> | <media-in-parens> <media-and>*
+    pub fn parse_media_condition(&mut self, allow_or: bool) -> Result<MediaCondition, ParseError> {
+        self.skip_whitespace();
+        if self.check_ident("not") {
+            self.advance();
+            self.skip_whitespace();
+            return Ok(MediaCondition::Not(Box::new(self.parse_media_in_parens()?)));
+        }
+
+        let first = self.parse_media_in_parens()?;
+        self.skip_whitespace();
+        let keyword = if self.check_ident("and") {
+            "and"
+        } else if allow_or && self.check_ident("or") {
+            "or"
+        } else {
+            return Ok(first);
+        };
+        let mut conditions = vec![first];
+        while self.check_ident(keyword) {
+            self.advance();
+            self.skip_whitespace();
+            conditions.push(self.parse_media_in_parens()?);
+            self.skip_whitespace();
         }
-        Selector { simple_selectors }
+        Ok(if keyword == "and" {
+            MediaCondition::And(conditions)
+        } else {
+            MediaCondition::Or(conditions)
+        })
     }
 
-    // <simple-selector> ::= <type-selector> | <id-selector> | <class-selector> | <pseudo-class-selector> | <pseudo-element-selector>
-    pub fn parse_simple_selector(&mut self) -> SimpleSelector {
-        if self.match_token(&[TokenType::Ident]) {
-            // e.g. div { ... }
-            SimpleSelector::Type(TypeSelector {
-                element: self.previous_token().lexeme.clone(),
-            })
-        } else if self.match_token(&[TokenType::ClassSelector]) {
-            // e.g. .class { ... }
-            SimpleSelector::Class(ClassSelector {
-                class_name: self.previous_token().lexeme.clone(),
-            })
-        } else if self.match_token(&[TokenType::IdSelector]) {
-            // e.g. #id { ... }
-            SimpleSelector::Id(IdSelector {
-                id: self.previous_token().lexeme.clone(),
-            })
-        } else if self.match_token(&[TokenType::PseudoClassSelector]) {
-            // e.g. :hover { ... }
-            SimpleSelector::PseudoClass(PseudoClassSelector {
-                ident: self.previous_token().lexeme.clone(),
-            })
-        } else if self.match_token(&[TokenType::PseudoElementSelector]) {
-            // e.g. ::before { ... }
-            SimpleSelector::PseudoElement(PseudoElementSelector {
-                ident: self.previous_token().lexeme.clone(),
+    // <media-in-parens> ::= "(" <media-condition> ")" | <media-feature> | <general-enclosed>
+    pub fn parse_media_in_parens(&mut self) -> Result<MediaCondition, ParseError> {
+        if self.check_token_type(TokenType::Function) {
+            let (text, span) = self.parse_general_enclosed();
+            return Ok(MediaCondition::GeneralEnclosed(text, span));
+        }
+        if !self.check_token_type(TokenType::ParenLeft) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidMediaQuery,
+                "Expected '(' in media condition",
+            ));
+        }
+
+        let checkpoint = self.current;
+        let diagnostic_count = self.diagnostics.len();
+        let start = self.advance().span.start;
+        self.skip_whitespace();
+        let result = if self.check_ident("not")
+            || self.check_token_type(TokenType::ParenLeft)
+            || self.check_token_type(TokenType::Function)
+        {
+            self.nested(|parser| parser.parse_media_condition(true))
+        } else {
+            self.parse_media_feature().map(|kind| {
+                MediaCondition::Feature(MediaFeature {
+                    kind,
+                    span: Span::new(start, start),
+                })
             })
+        };
+        self.skip_whitespace();
+        match result {
+            Ok(mut condition) if self.match_token(&[TokenType::ParenRight]) => {
+                if let MediaCondition::Feature(feature) = &mut condition {
+                    feature.span = self.span_from(start);
+                }
+                Ok(condition)
+            }
+            // anything else in parentheses is <general-enclosed>
+            _ => {
+                self.current = checkpoint;
+                self.backtrack_diagnostics(diagnostic_count);
+                let (text, span) = self.parse_general_enclosed();
+                Ok(MediaCondition::GeneralEnclosed(text, span))
+            }
+        }
+    }
+
+    // <media-feature> ::= "(" [ <mf-plain> | <mf-boolean> | <mf-range> ] ")"
+    /// Parse the contents of a media feature, leaving the parentheses to the caller
+    pub fn parse_media_feature(&mut self) -> Result<MediaFeatureKind, ParseError> {
+        let kind = if self.check_token_type(TokenType::Ident) {
+            let name = self.advance().value.clone();
+            self.skip_whitespace();
+            if self.match_token(&[TokenType::Colon]) {
+                // <mf-plain> ::= <ident> ":" <mf-value>
+                self.skip_whitespace();
+                let value = self.parse_media_feature_value()?;
+                MediaFeatureKind::Plain { name, value }
+            } else if let Some(comparison) = self.parse_media_comparison() {
+                // <mf-range> ::= <ident> <mf-comparison> <mf-value>
+                self.skip_whitespace();
+                let value = self.parse_media_feature_value()?;
+                MediaFeatureKind::Range {
+                    name,
+                    start: None,
+                    end: Some((comparison, value)),
+                }
+            } else {
+                // <mf-boolean> ::= <ident>
+                MediaFeatureKind::Boolean { name }
+            }
+        } else {
+            // <mf-range> ::= <mf-value> <mf-comparison> <ident> [ <mf-comparison> <mf-value> ]?
+            let start_value = self.parse_media_feature_value()?;
+            self.skip_whitespace();
+            let start_comparison = self.parse_media_comparison().ok_or_else(|| {
+                self.error_at_current(DiagnosticCode::InvalidMediaQuery, "Expected comparison")
+            })?;
+            self.skip_whitespace();
+            let name = self
+                .expect_token(
+                    TokenType::Ident,
+                    DiagnosticCode::InvalidMediaQuery,
+                    "Expected media feature name",
+                )?
+                .value
+                .clone();
+            self.skip_whitespace();
+            let end = match self.parse_media_comparison() {
+                Some(end_comparison) => {
+                    // both comparisons must point the same way, e.g. `400px < width <= 800px`
+                    let is_less = |comparison| {
+                        matches!(comparison, MediaComparison::Lt | MediaComparison::Le)
+                    };
+                    let is_greater = |comparison| {
+                        matches!(comparison, MediaComparison::Gt | MediaComparison::Ge)
+                    };
+                    if !(is_less(start_comparison) && is_less(end_comparison)
+                        || is_greater(start_comparison) && is_greater(end_comparison))
+                    {
+                        return Err(ParseError::new(
+                            DiagnosticCode::InvalidMediaQuery,
+                            "Comparisons in a range must both be '<' or both be '>'",
+                            self.previous_token().span,
+                        ));
+                    }
+                    self.skip_whitespace();
+                    Some((end_comparison, self.parse_media_feature_value()?))
+                }
+                None => None,
+            };
+            MediaFeatureKind::Range {
+                name,
+                start: Some((start_value, start_comparison)),
+                end,
+            }
+        };
+        Ok(kind)
+    }
+
+    // <mf-value> ::= <number> | <dimension> | <ident> | <number> "/" <number>
+    pub fn parse_media_feature_value(&mut self) -> Result<MediaFeatureValue, ParseError> {
+        let literal = numeric_literal(&self.current_token());
+        if let Some(LiteralValue::Number(numerator)) = literal {
+            self.advance();
+            let checkpoint = self.current;
+            self.skip_whitespace();
+            if self.check_delim('/') {
+                self.advance();
+                self.skip_whitespace();
+                let denominator = self.expect_token(
+                    TokenType::Number,
+                    DiagnosticCode::InvalidMediaQuery,
+                    "Expected number after '/' in ratio",
+                )?;
+                if let Some(denominator) = Number::parse(&denominator.lexeme) {
+                    return Ok(MediaFeatureValue::Ratio(numerator, denominator));
+                }
+            }
+            self.current = checkpoint;
+            Ok(MediaFeatureValue::Literal(LiteralValue::Number(numerator)))
+        } else if let Some(dimension @ LiteralValue::Dimension(_)) = literal {
+            self.advance();
+            Ok(MediaFeatureValue::Literal(dimension))
+        } else if let Some(token) = self.consume_next_token(TokenType::Ident) {
+            Ok(MediaFeatureValue::Literal(LiteralValue::Ident(
+                token.lexeme.clone(),
+            )))
         } else {
-            panic!("Expected simple selector");
+            Err(self.error_at_current(
+                DiagnosticCode::InvalidMediaQuery,
+                "Expected media feature value",
+            ))
         }
     }
 
-    // <declarations> ::= <declaration> | <declaration> <declarations>
-    pub fn parse_declarations(&mut self) -> Vec<Declaration> {
-        let mut declarations = Vec::new();
-        while !self.check_token_type(TokenType::CurlyRight) && !self.is_end() {
-            declarations.push(self.parse_declaration());
+    // <mf-comparison> ::= "<" | "<=" | ">" | ">=" | "="
+    fn parse_media_comparison(&mut self) -> Option<MediaComparison> {
+        let is_followed_by_equals = self.peek_token_type(1) == Some(TokenType::Delim)
+            && self.tokens[self.current + 1].value == "=";
+        let comparison = if self.check_delim('<') {
+            if is_followed_by_equals {
+                MediaComparison::Le
+            } else {
+                MediaComparison::Lt
+            }
+        } else if self.check_token_type(TokenType::Greater) {
+            if is_followed_by_equals {
+                MediaComparison::Ge
+            } else {
+                MediaComparison::Gt
+            }
+        } else if self.check_delim('=') {
+            MediaComparison::Eq
+        } else {
+            return None;
+        };
+        self.advance();
+        if matches!(comparison, MediaComparison::Le | MediaComparison::Ge) {
+            self.advance();
         }
-        declarations
+        Some(comparison)
     }
 
-    // <declaration> ::= <property> ":" <value> ";"
-    // <property> ::= <ident>
-    pub fn parse_declaration(&mut self) -> Declaration {
+    // <general-enclosed> ::= <function-token> <any-value>? ")" | "(" <any-value>? ")"
+    /// Consume a `<general-enclosed>` and return it as written
+    fn parse_general_enclosed(&mut self) -> (String, Span) {
+        let start_index = self.current;
+        let start = self.current_token().span.start;
+        self.skip_component_value();
+        let text = self.tokens[start_index..self.current]
+            .iter()
+            .map(|token| token.lexeme.as_str())
+            .collect::<String>();
+        (text, self.span_from(start))
+    }
+
+    /// Check if the current token ends a media query list, i.e. it is `{`, `;` or the end of input
+    fn check_media_query_list_end(&self) -> bool {
+        self.is_end()
+            || self.check_token_type(TokenType::CurlyLeft)
+            || self.check_token_type(TokenType::Semicolon)
+    }
+
+    // <supports-rule> ::= "@supports" <supports-condition> "{" <rule>* "}"
+    pub fn parse_supports_rule(&mut self) -> Result<SupportsRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        let condition = self.parse_supports_condition()?;
+        self.skip_whitespace();
+        if !self.is_end() && !self.check_token_type(TokenType::CurlyLeft) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidSupportsCondition,
+                "Unexpected token in supports condition",
+            ));
+        }
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after supports condition",
+        )?;
+        let rules = self.parse_rules(false);
+        self.expect_block_end("Expected '}' after rules");
+        Ok(SupportsRule {
+            condition,
+            rules,
+            span: self.span_from(start),
+        })
+    }
+
+    // <supports-condition> ::= "not" <supports-in-parens> | <supports-in-parens> [ [ "and" <supports-in-parens> ]* | [ "or" <supports-in-parens> ]* ]
+    pub fn parse_supports_condition(&mut self) -> Result<SupportsCondition, ParseError> {
+        self.skip_whitespace();
+        if self.check_ident("not") {
+            self.advance();
+            self.skip_whitespace();
+            return Ok(SupportsCondition::Not(Box::new(
+                self.parse_supports_in_parens()?,
+            )));
+        }
+
+        let first = self.parse_supports_in_parens()?;
+        self.skip_whitespace();
+        let keyword = if self.check_ident("and") {
+            "and"
+        } else if self.check_ident("or") {
+            "or"
+        } else {
+            return Ok(first);
+        };
+        let mut conditions = vec![first];
+        while self.check_ident(keyword) {
+            self.advance();
+            self.skip_whitespace();
+            conditions.push(self.parse_supports_in_parens()?);
+            self.skip_whitespace();
+        }
+        Ok(if keyword == "and" {
+            SupportsCondition::And(conditions)
+        } else {
+            SupportsCondition::Or(conditions)
+        })
+    }
+
+    // <supports-in-parens> ::= "(" <supports-condition> ")" | <supports-feature> | <general-enclosed>
+    pub fn parse_supports_in_parens(&mut self) -> Result<SupportsCondition, ParseError> {
+        if self.check_token_type(TokenType::Function) {
+            if self.check_function("selector") {
+                return Ok(self.parse_supports_selector());
+            }
+            let (text, span) = self.parse_general_enclosed();
+            return Ok(SupportsCondition::GeneralEnclosed(text, span));
+        }
+        if !self.check_token_type(TokenType::ParenLeft) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidSupportsCondition,
+                "Expected '(' in supports condition",
+            ));
+        }
+
+        let checkpoint = self.current;
+        let start = self.advance().span.start;
+        self.skip_whitespace();
+        let result = if self.check_ident("not")
+            || self.check_token_type(TokenType::ParenLeft)
+            || self.check_token_type(TokenType::Function)
+        {
+            self.nested(Parser::parse_supports_condition)
+        } else {
+            self.parse_supports_declaration()
+                .map(SupportsCondition::Declaration)
+        };
+        self.skip_whitespace();
+        match result {
+            Ok(mut condition) if self.match_token(&[TokenType::ParenRight]) => {
+                if let SupportsCondition::Declaration(declaration) = &mut condition {
+                    declaration.span = self.span_from(start);
+                }
+                Ok(condition)
+            }
+            // anything else in parentheses is <general-enclosed>
+            _ => {
+                self.current = checkpoint;
+                let (text, span) = self.parse_general_enclosed();
+                Ok(SupportsCondition::GeneralEnclosed(text, span))
+            }
+        }
+    }
+
+    // <supports-decl> ::= "(" <declaration> ")"
+    /// Parse the declaration in a `<supports-decl>`, leaving the parentheses to the caller
+    pub fn parse_supports_declaration(&mut self) -> Result<SupportsDeclaration, ParseError> {
+        let start = self.current_token().span.start;
         let property = self
-            .consume_next_token(TokenType::Ident)
-            .expect("Expected property name")
-            .lexeme
+            .expect_token(
+                TokenType::Ident,
+                DiagnosticCode::ExpectedProperty,
+                "Expected property name",
+            )?
+            .value
             .clone();
-        self.consume_next_token(TokenType::Colon)
-            .expect("Expected colon");
-        let value = self.parse_value();
-        self.consume_next_token(TokenType::Semicolon)
-            .expect("Expected semicolon");
-
-        let css_text = property.clone()
-            + ": "
-            + &match value.value.clone() {
-                LiteralValue::Dimension(val)
-                | LiteralValue::Percentage(val)
-                | LiteralValue::Number(val)
-                | LiteralValue::Ident(val) => val,
-            }
-            + ";";
-
-        Declaration {
+        self.skip_whitespace();
+        self.expect_token(
+            TokenType::Colon,
+            DiagnosticCode::ExpectedColon,
+            "Expected ':' after property name",
+        )?;
+        self.skip_whitespace();
+        let value = self.parse_raw_value()?;
+        Ok(SupportsDeclaration {
             property,
             value,
-            css_text,
+            span: self.span_from(start),
+        })
+    }
+
+    // <supports-selector-fn> ::= "selector(" <selector> ")"
+    /// A selector which cannot be parsed is kept as `<general-enclosed>`
+    fn parse_supports_selector(&mut self) -> SupportsCondition {
+        let checkpoint = self.current;
+        let start = self.advance().span.start;
+        self.skip_whitespace();
+        if let Ok(selector) = self.parse_selector() {
+            self.skip_whitespace();
+            if self.match_token(&[TokenType::ParenRight]) {
+                return SupportsCondition::Selector(selector, self.span_from(start));
+            }
         }
+        self.current = checkpoint;
+        let (text, span) = self.parse_general_enclosed();
+        SupportsCondition::GeneralEnclosed(text, span)
     }
 
-    // <value> ::= <ident> | <number> | <percentage> | <dimension>, more stricly: <ident> | <number> | <percentage> | <length> | <color> | <string> | <function> | <url>
-    pub fn parse_value(&mut self) -> Value {
-        if let Some(token) = self.consume_next_token(TokenType::Ident) {
-            // e.g. { color: red; }
-            Value {
-                value: LiteralValue::Ident(token.lexeme.clone()),
+    // <container-rule> ::= "@container" <container-condition> "{" <rule>* "}"
+    pub fn parse_container_rule(&mut self) -> Result<ContainerRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let condition = self.parse_container_condition()?;
+        self.skip_whitespace();
+        if !self.is_end() && !self.check_token_type(TokenType::CurlyLeft) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidContainerQuery,
+                "Unexpected token in container query",
+            ));
+        }
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after container query",
+        )?;
+        let rules = self.parse_rules(false);
+        self.expect_block_end("Expected '}' after rules");
+        Ok(ContainerRule {
+            condition,
+            rules,
+            span: self.span_from(start),
+        })
+    }
+
+    // <container-condition> ::= <container-name> | <container-name>? <container-query>
+    pub fn parse_container_condition(&mut self) -> Result<ContainerCondition, ParseError> {
+        let start = self.current_token().span.start;
+        let is_reserved = [
+            "none",
+            "and",
+            "not",
+            "or",
+            "initial",
+            "inherit",
+            "unset",
+            "revert",
+            "revert-layer",
+            "default",
+        ]
+        .iter()
+        .any(|keyword| self.check_ident(keyword));
+        let name = if self.check_token_type(TokenType::Ident) && !is_reserved {
+            Some(self.advance().value.clone())
+        } else {
+            None
+        };
+        self.skip_whitespace();
+        let query =
+            if name.is_some() && (self.is_end() || self.check_token_type(TokenType::CurlyLeft)) {
+                None
+            } else {
+                Some(self.parse_container_query()?)
+            };
+        Ok(ContainerCondition {
+            name,
+            query,
+            span: self.span_from(start),
+        })
+    }
+
+    // <container-query> ::= "not" <query-in-parens> | <query-in-parens> [ [ "and" <query-in-parens> ]* | [ "or" <query-in-parens> ]* ]
+    pub fn parse_container_query(&mut self) -> Result<ContainerQuery, ParseError> {
+        self.skip_whitespace();
+        if self.check_ident("not") {
+            self.advance();
+            self.skip_whitespace();
+            return Ok(ContainerQuery::Not(Box::new(
+                self.parse_container_query_in_parens()?,
+            )));
+        }
+
+        let first = self.parse_container_query_in_parens()?;
+        self.skip_whitespace();
+        let keyword = if self.check_ident("and") {
+            "and"
+        } else if self.check_ident("or") {
+            "or"
+        } else {
+            return Ok(first);
+        };
+        let mut queries = vec![first];
+        while self.check_ident(keyword) {
+            self.advance();
+            self.skip_whitespace();
+            queries.push(self.parse_container_query_in_parens()?);
+            self.skip_whitespace();
+        }
+        Ok(if keyword == "and" {
+            ContainerQuery::And(queries)
+        } else {
+            ContainerQuery::Or(queries)
+        })
+    }
+
+    // <query-in-parens> ::= "(" <container-query> ")" | <media-feature> | "style(" <style-query> ")" | <general-enclosed>
+    pub fn parse_container_query_in_parens(&mut self) -> Result<ContainerQuery, ParseError> {
+        if self.check_token_type(TokenType::Function) {
+            if self.check_function("style") {
+                return Ok(self.parse_style_query_function());
             }
-        } else if let Some(token) = self.consume_next_token(TokenType::Number) {
-            // e.g. { flex: 1; }
-            Value {
-                value: LiteralValue::Number(token.lexeme.clone()),
+            let (text, span) = self.parse_general_enclosed();
+            return Ok(ContainerQuery::GeneralEnclosed(text, span));
+        }
+        if !self.check_token_type(TokenType::ParenLeft) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidContainerQuery,
+                "Expected '(' in container query",
+            ));
+        }
+
+        let checkpoint = self.current;
+        let diagnostic_count = self.diagnostics.len();
+        let start = self.advance().span.start;
+        self.skip_whitespace();
+        let result = if self.check_ident("not")
+            || self.check_token_type(TokenType::ParenLeft)
+            || self.check_token_type(TokenType::Function)
+        {
+            self.nested(Parser::parse_container_query)
+        } else {
+            self.parse_media_feature().map(|kind| {
+                ContainerQuery::Size(MediaFeature {
+                    kind,
+                    span: Span::new(start, start),
+                })
+            })
+        };
+        self.skip_whitespace();
+        match result {
+            Ok(mut query) if self.match_token(&[TokenType::ParenRight]) => {
+                if let ContainerQuery::Size(feature) = &mut query {
+                    feature.span = self.span_from(start);
+                }
+                Ok(query)
             }
-        } else if let Some(token) = self.consume_next_token(TokenType::Percentage) {
-            // e.g. { width: 100%; }
-            Value {
-                value: LiteralValue::Percentage(token.lexeme.clone()),
+            // anything else in parentheses is <general-enclosed>
+            _ => {
+                self.current = checkpoint;
+                self.backtrack_diagnostics(diagnostic_count);
+                let (text, span) = self.parse_general_enclosed();
+                Ok(ContainerQuery::GeneralEnclosed(text, span))
             }
-        } else if let Some(token) = self.consume_next_token(TokenType::Dimension) {
-            // e.g. { width: 100px; }
-            Value {
-                value: LiteralValue::Dimension(token.lexeme.clone()),
+        }
+    }
+
+    // "style(" <style-query> ")"
+    /// A style query which cannot be parsed is kept as `<general-enclosed>`
+    fn parse_style_query_function(&mut self) -> ContainerQuery {
+        let checkpoint = self.current;
+        let start = self.advance().span.start;
+        if let Ok(query) = self.parse_style_query() {
+            self.skip_whitespace();
+            if self.match_token(&[TokenType::ParenRight]) {
+                return ContainerQuery::Style(query, self.span_from(start));
             }
+        }
+        self.current = checkpoint;
+        let (text, span) = self.parse_general_enclosed();
+        ContainerQuery::GeneralEnclosed(text, span)
+    }
+
+    // <style-query> ::= "not" <style-in-parens> | <style-in-parens> [ [ "and" <style-in-parens> ]* | [ "or" <style-in-parens> ]* ] | <style-feature>
+    pub fn parse_style_query(&mut self) -> Result<StyleQuery, ParseError> {
+        self.skip_whitespace();
+        if self.check_ident("not") {
+            self.advance();
+            self.skip_whitespace();
+            return Ok(StyleQuery::Not(Box::new(self.parse_style_in_parens()?)));
+        }
+        if self.check_token_type(TokenType::Ident) {
+            return Ok(StyleQuery::Feature(self.parse_style_feature()?));
+        }
+
+        let first = self.parse_style_in_parens()?;
+        self.skip_whitespace();
+        let keyword = if self.check_ident("and") {
+            "and"
+        } else if self.check_ident("or") {
+            "or"
         } else {
-            panic!("Expected value");
+            return Ok(first);
+        };
+        let mut queries = vec![first];
+        while self.check_ident(keyword) {
+            self.advance();
+            self.skip_whitespace();
+            queries.push(self.parse_style_in_parens()?);
+            self.skip_whitespace();
         }
+        Ok(if keyword == "and" {
+            StyleQuery::And(queries)
+        } else {
+            StyleQuery::Or(queries)
+        })
     }
 
-    /// Check if the current token matches any of the given token types
-    pub fn match_token(&mut self, token_types: &[TokenType]) -> bool {
-        for token_type in token_types {
-            if self.check_token_type(token_type.clone()) {
-                self.advance();
-                return true;
+    // <style-in-parens> ::= "(" <style-query> ")" | <general-enclosed>
+    pub fn parse_style_in_parens(&mut self) -> Result<StyleQuery, ParseError> {
+        if self.check_token_type(TokenType::Function) {
+            let (text, span) = self.parse_general_enclosed();
+            return Ok(StyleQuery::GeneralEnclosed(text, span));
+        }
+        if !self.check_token_type(TokenType::ParenLeft) {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidContainerQuery,
+                "Expected '(' in style query",
+            ));
+        }
+
+        let checkpoint = self.current;
+        self.advance();
+        let result = self.nested(Parser::parse_style_query);
+        self.skip_whitespace();
+        match result {
+            Ok(query) if self.match_token(&[TokenType::ParenRight]) => Ok(query),
+            _ => {
+                self.current = checkpoint;
+                let (text, span) = self.parse_general_enclosed();
+                Ok(StyleQuery::GeneralEnclosed(text, span))
             }
         }
-        false
     }
 
-    /// Advance the current token index and return the previous token
-    pub fn advance(&mut self) -> Rc<Token> {
-        if !self.is_end() {
-            self.current += 1;
+    // <style-feature> ::= <property> [ ":" <value> ]?
+    pub fn parse_style_feature(&mut self) -> Result<StyleFeature, ParseError> {
+        let token = self.expect_token(
+            TokenType::Ident,
+            DiagnosticCode::ExpectedProperty,
+            "Expected property name",
+        )?;
+        let checkpoint = self.current;
+        self.skip_whitespace();
+        let value = if self.match_token(&[TokenType::Colon]) {
+            self.skip_whitespace();
+            Some(self.parse_raw_value()?)
+        } else {
+            self.current = checkpoint;
+            None
+        };
+        Ok(StyleFeature {
+            property: token.lexeme.clone(),
+            value,
+            span: self.span_from(token.span.start),
+        })
+    }
+
+    // <unknown-at-rule> ::= <at-keyword> <component-value>* [ "{" <component-value>* "}" | ";" ]
+    /// The prelude ends before a `}` which closes the enclosing block, as the at-rule is then a statement without `;`
+    pub fn parse_unknown_at_rule(&mut self) -> UnknownAtRule {
+        let token = self.advance();
+        let prelude_start = self.current;
+        while !self.is_end()
+            && !self.check_token_type(TokenType::Semicolon)
+            && !self.check_token_type(TokenType::CurlyLeft)
+            && !self.check_token_type(TokenType::CurlyRight)
+        {
+            self.skip_component_value();
+        }
+        let prelude_tokens = self.tokens[prelude_start..self.current].to_vec();
+        let block = if self.match_token(&[TokenType::CurlyLeft]) {
+            let block_start = self.current;
+            while !self.is_end() && !self.check_token_type(TokenType::CurlyRight) {
+                self.skip_component_value();
+            }
+            let block = self.tokens[block_start..self.current].to_vec();
+            self.expect_block_end("Expected '}' after at-rule block");
+            Some(block)
+        } else {
+            self.match_token(&[TokenType::Semicolon]);
+            None
+        };
+        UnknownAtRule {
+            name: token.value.clone(),
+            prelude_tokens,
+            block,
+            span: self.span_from(token.span.start),
         }
-        self.previous_token()
     }
 
-    // Consume the next token if it matches the given token type
-    pub fn consume_next_token(&mut self, token_type: TokenType) -> Option<Rc<Token>> {
-        if self.check_token_type(token_type) {
-            return Some(self.advance());
+    // <keyframes-rule> ::= "@keyframes" <keyframes-name> "{" <keyframe-block>* "}"
+    pub fn parse_keyframes_rule(&mut self) -> Result<KeyframesRule, ParseError> {
+        let start = self.current_token().span.start;
+        let prefix = self.keyframes_prefix().filter(|prefix| !prefix.is_empty());
+        self.advance();
+        self.skip_whitespace();
+        let name = self.parse_keyframes_name()?;
+        self.skip_whitespace();
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after keyframes name",
+        )?;
+        let keyframes = self.parse_keyframe_blocks();
+        self.expect_block_end("Expected '}' after keyframes");
+        Ok(KeyframesRule {
+            name,
+            prefix,
+            keyframes,
+            span: self.span_from(start),
+        })
+    }
+
+    // <keyframes-name> ::= <ident> | <string>
+    pub fn parse_keyframes_name(&mut self) -> Result<String, ParseError> {
+        let token = self.current_token();
+        // CSS-wide keywords and `none` can only be used as names when quoted
+        let is_reserved = [
+            "none",
+            "initial",
+            "inherit",
+            "unset",
+            "revert",
+            "revert-layer",
+            "default",
+        ]
+        .iter()
+        .any(|keyword| token.value.eq_ignore_ascii_case(keyword));
+        match token.token_type {
+            TokenType::Ident if !is_reserved => {}
+            TokenType::String => {}
+            _ => {
+                return Err(self.error_at_current(
+                    DiagnosticCode::ExpectedKeyframesName,
+                    "Expected keyframes name",
+                ))
+            }
         }
-        None
+        self.advance();
+        Ok(token.value.clone())
     }
 
-    /// Check if the current token is of the given type
-    pub fn check_token_type(&self, token_type: TokenType) -> bool {
-        if self.is_end() {
-            return false;
+    // <keyframe-block>*
+    /// Parse keyframe blocks up to the `}` closing the `@keyframes` rule.
+    /// A block which cannot be parsed is skipped and reported, the same as a rule.
+    pub fn parse_keyframe_blocks(&mut self) -> Vec<Keyframe> {
+        let mut keyframes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.is_end() || self.check_token_type(TokenType::CurlyRight) {
+                break;
+            }
+            let start = self.current_token().span.start;
+            match self.parse_keyframe_block() {
+                Ok(keyframe) => keyframes.push(keyframe),
+                Err(error) => {
+                    self.report(error);
+                    self.recover_rule(true);
+                    self.report_skipped(DiagnosticCode::IgnoredRule, "Keyframe is ignored", start);
+                }
+            }
         }
-        self.tokens[self.current].token_type == token_type
+        keyframes
     }
 
-    pub fn current_token(&self) -> Rc<Token> {
-        Rc::new(self.tokens[self.current].clone())
+    // <keyframe-block> ::= <keyframe-selector> [ "," <keyframe-selector> ]* "{" <declarations> "}"
+    pub fn parse_keyframe_block(&mut self) -> Result<Keyframe, ParseError> {
+        let start = self.current_token().span.start;
+        let mut selectors = vec![self.parse_keyframe_selector()?];
+        self.skip_whitespace();
+        while self.match_token(&[TokenType::Comma]) {
+            self.skip_whitespace();
+            selectors.push(self.parse_keyframe_selector()?);
+            self.skip_whitespace();
+        }
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after keyframe selectors",
+        )?;
+        let mut declarations = self.parse_declarations();
+        // keyframes do not take part in the cascade with other declarations, so `!important` is meaningless
+        declarations.retain(|declaration| {
+            if declaration.important {
+                self.diagnostics.push(Diagnostic {
+                    code: DiagnosticCode::IgnoredDeclaration,
+                    message: "Declarations with '!important' are ignored in keyframes".to_string(),
+                    span: declaration.span,
+                    severity: Severity::Warning,
+                });
+            }
+            !declaration.important
+        });
+        self.expect_block_end("Expected '}' after declarations");
+        Ok(Keyframe {
+            selectors,
+            declarations,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn previous_token(&self) -> Rc<Token> {
-        Rc::new(self.tokens[self.current - 1].clone())
+    // <keyframe-selector> ::= "from" | "to" | <percentage>
+    pub fn parse_keyframe_selector(&mut self) -> Result<KeyframeSelector, ParseError> {
+        let kind = if self.check_ident("from") {
+            KeyframeSelectorKind::From
+        } else if self.check_ident("to") {
+            KeyframeSelectorKind::To
+        } else if self.check_token_type(TokenType::Percentage) {
+            let percentage = self
+                .current_token()
+                .lexeme
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .filter(|percentage| (0.0..=100.0).contains(percentage));
+            match percentage {
+                Some(percentage) => KeyframeSelectorKind::Percentage(percentage),
+                None => {
+                    return Err(self.error_at_current(
+                        DiagnosticCode::InvalidKeyframeSelector,
+                        "Keyframe percentage must be between 0% and 100%",
+                    ))
+                }
+            }
+        } else {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidKeyframeSelector,
+                "Expected 'from', 'to' or a percentage",
+            ));
+        };
+        let span = self.advance().span;
+        Ok(KeyframeSelector { kind, span })
     }
 
-    pub fn next_token(&self) -> Rc<Token> {
-        Rc::new(self.tokens[self.current + 1].clone())
+    // <font-face-rule> ::= "@font-face" "{" <declarations> "}"
+    pub fn parse_font_face_rule(&mut self) -> Result<FontFaceRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let descriptors = self.parse_descriptor_block("Expected '{' after '@font-face'")?;
+        Ok(FontFaceRule {
+            descriptors,
+            span: self.span_from(start),
+        })
     }
 
-    pub fn is_end(&self) -> bool {
-        self.current_token().token_type == TokenType::Eof
+    // <page-rule> ::= "@page" [ <page-selector> [ "," <page-selector> ]* ]? "{" [ <declaration> | <page-margin-rule> ]* "}"
+    pub fn parse_page_rule(&mut self) -> Result<PageRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let mut selectors = Vec::new();
+        if !self.check_token_type(TokenType::CurlyLeft) && !self.is_end() {
+            selectors.push(self.parse_page_selector()?);
+            self.skip_whitespace();
+            while self.match_token(&[TokenType::Comma]) {
+                self.skip_whitespace();
+                selectors.push(self.parse_page_selector()?);
+                self.skip_whitespace();
+            }
+        }
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after page selectors",
+        )?;
+
+        let mut descriptors = Vec::new();
+        let mut margin_rules = Vec::new();
+        loop {
+            while self.match_token(&[TokenType::S, TokenType::Semicolon]) {}
+            if self.check_token_type(TokenType::CurlyRight) || self.is_end() {
+                break;
+            }
+            if !self.check_token_type(TokenType::Atkeyword) {
+                if let Some(declaration) = self.parse_declaration_or_recover() {
+                    descriptors.push(declaration);
+                }
+            } else if PAGE_MARGIN_RULES
+                .iter()
+                .any(|name| self.check_at_keyword(name))
+            {
+                let rule_start = self.current_token().span.start;
+                match self.parse_page_margin_rule() {
+                    Ok(margin_rule) => margin_rules.push(margin_rule),
+                    Err(error) => {
+                        self.report(error);
+                        self.recover_at_rule();
+                        self.report_skipped(
+                            DiagnosticCode::IgnoredRule,
+                            "Rule is ignored",
+                            rule_start,
+                        );
+                    }
+                }
+            } else {
+                self.skip_unsupported_at_rule();
+            }
+        }
+        self.expect_block_end("Expected '}' after descriptors");
+        Ok(PageRule {
+            selectors,
+            descriptors,
+            margin_rules,
+            span: self.span_from(start),
+        })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // <page-selector> ::= <ident>? <pseudo-page>*
+    // <pseudo-page> ::= ":" [ "left" | "right" | "first" | "blank" ]
+    pub fn parse_page_selector(&mut self) -> Result<PageSelector, ParseError> {
+        let start = self.current_token().span.start;
+        let name = self
+            .consume_next_token(TokenType::Ident)
+            .map(|token| token.value.clone());
+        let mut pseudo_pages = Vec::new();
+        while self.match_token(&[TokenType::Colon]) {
+            if !["left", "right", "first", "blank"]
+                .iter()
+                .any(|pseudo_page| self.check_ident(pseudo_page))
+            {
+                return Err(self.error_at_current(
+                    DiagnosticCode::InvalidPageSelector,
+                    "Expected 'left', 'right', 'first' or 'blank'",
+                ));
+            }
+            pseudo_pages.push(self.advance().value.to_ascii_lowercase());
+        }
+        if name.is_none() && pseudo_pages.is_empty() {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidPageSelector,
+                "Expected page selector",
+            ));
+        }
+        Ok(PageSelector {
+            name,
+            pseudo_pages,
+            span: self.span_from(start),
+        })
+    }
 
-    #[test]
-    pub fn test_parse_basic_case() {
-        // .hoge { color: red; }
-        let tokens = vec![
-            Token {
-                token_type: TokenType::ClassSelector,
-                lexeme: ".hoge".to_string(),
-                line: 1,
-            },
-            Token {
-                token_type: TokenType::CurlyLeft,
-                lexeme: "{".to_string(),
-                line: 1,
-            },
-            Token {
-                token_type: TokenType::Ident,
-                lexeme: "color".to_string(),
-                line: 2,
-            },
-            Token {
-                token_type: TokenType::Colon,
-                lexeme: ":".to_string(),
-                line: 2,
-            },
-            Token {
-                token_type: TokenType::Ident,
-                lexeme: "red".to_string(),
-                line: 2,
-            },
-            Token {
-                token_type: TokenType::Semicolon,
-                lexeme: ";".to_string(),
-                line: 2,
-            },
-            Token {
-                token_type: TokenType::CurlyRight,
-                lexeme: "}".to_string(),
-                line: 3,
-            },
-            Token {
-                token_type: TokenType::Eof,
-                lexeme: "".to_string(),
-                line: 4,
-            },
-        ];
-        let mut parser = Parser::new(tokens);
-        let stylesheet = parser.parse();
-        assert_eq!(stylesheet.rules.len(), 1);
+    // <page-margin-rule> ::= <margin-at-keyword> "{" <declarations> "}"
+    pub fn parse_page_margin_rule(&mut self) -> Result<PageMarginRule, ParseError> {
+        let start = self.current_token().span.start;
+        let name = self.advance().value.to_ascii_lowercase();
+        self.skip_whitespace();
+        let descriptors = self.parse_descriptor_block("Expected '{' after margin at-rule")?;
+        Ok(PageMarginRule {
+            name,
+            descriptors,
+            span: self.span_from(start),
+        })
+    }
+
+    // <counter-style-rule> ::= "@counter-style" <ident> "{" <declarations> "}"
+    pub fn parse_counter_style_rule(&mut self) -> Result<CounterStyleRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        // the predefined styles which cannot be overridden, `none` and CSS-wide keywords are not valid names
+        let is_reserved = [
+            "none",
+            "decimal",
+            "disc",
+            "square",
+            "circle",
+            "disclosure-open",
+            "disclosure-closed",
+            "initial",
+            "inherit",
+            "unset",
+            "revert",
+            "revert-layer",
+            "default",
+        ]
+        .iter()
+        .any(|keyword| self.check_ident(keyword));
+        if !self.check_token_type(TokenType::Ident) || is_reserved {
+            return Err(self.error_at_current(
+                DiagnosticCode::ExpectedCounterStyleName,
+                "Expected counter style name",
+            ));
+        }
+        let name = self.advance().value.clone();
+        self.skip_whitespace();
+        let descriptors = self.parse_descriptor_block("Expected '{' after counter style name")?;
+        Ok(CounterStyleRule {
+            name,
+            descriptors,
+            span: self.span_from(start),
+        })
+    }
+
+    // <property-rule> ::= "@property" <custom-property-name> "{" <declarations> "}"
+    /// Invalid or missing descriptors are reported, leaving the rule with [`PropertyRule::is_valid`] false
+    pub fn parse_property_rule(&mut self) -> Result<PropertyRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let name_token = self.current_token();
+        if name_token.token_type != TokenType::Ident || !name_token.value.starts_with("--") {
+            return Err(self.error_at_current(
+                DiagnosticCode::ExpectedCustomPropertyName,
+                "Expected custom property name, e.g. '--gap'",
+            ));
+        }
+        self.advance();
+        self.skip_whitespace();
+        let descriptors = self.parse_descriptor_block("Expected '{' after custom property name")?;
+
+        let mut rule = PropertyRule {
+            name: name_token.value.clone(),
+            descriptors,
+            syntax: None,
+            inherits: None,
+            initial_value: None,
+            span: self.span_from(start),
+        };
+        let mut errors = Vec::new();
+        for descriptor in &rule.descriptors {
+            let value = &descriptor.value;
+            if descriptor.property.eq_ignore_ascii_case("syntax") {
+                rule.syntax = match value.literal() {
+                    Some(LiteralValue::String(syntax)) => parse_property_syntax(syntax),
+                    _ => None,
+                };
+                if rule.syntax.is_none() {
+                    errors.push(ParseError::new(
+                        DiagnosticCode::InvalidPropertyRule,
+                        format!("Invalid syntax {}", value.to_string()),
+                        value.span,
+                    ));
+                }
+            } else if descriptor.property.eq_ignore_ascii_case("inherits") {
+                rule.inherits = match value.literal() {
+                    Some(LiteralValue::Ident(ident)) if ident.eq_ignore_ascii_case("true") => {
+                        Some(true)
+                    }
+                    Some(LiteralValue::Ident(ident)) if ident.eq_ignore_ascii_case("false") => {
+                        Some(false)
+                    }
+                    _ => None,
+                };
+                if rule.inherits.is_none() {
+                    errors.push(ParseError::new(
+                        DiagnosticCode::InvalidPropertyRule,
+                        "The 'inherits' descriptor must be 'true' or 'false'",
+                        value.span,
+                    ));
+                }
+            } else if descriptor.property.eq_ignore_ascii_case("initial-value") {
+                rule.initial_value = Some(value.clone());
+            }
+        }
+        // the initial value is checked once the syntax is known, as the descriptors may come in any order
+        if let (Some(syntax), Some(initial_value)) = (&rule.syntax, &rule.initial_value) {
+            let error = if !syntax.matches(initial_value) {
+                Some(format!(
+                    "The initial value '{}' does not match the syntax \"{}\"",
+                    initial_value.to_string(),
+                    syntax.to_string()
+                ))
+            } else if *syntax != PropertySyntax::Universal
+                && !initial_value.is_computationally_independent()
+            {
+                Some(format!(
+                    "The initial value '{}' must be computationally independent, e.g. '10px' rather than '1em'",
+                    initial_value.to_string()
+                ))
+            } else {
+                None
+            };
+            if let Some(message) = error {
+                errors.push(ParseError::new(
+                    DiagnosticCode::InvalidPropertyRule,
+                    message,
+                    initial_value.span,
+                ));
+                rule.initial_value = None;
+            }
+        }
+        let has_descriptor = |name: &str| {
+            rule.descriptors
+                .iter()
+                .any(|descriptor| descriptor.property.eq_ignore_ascii_case(name))
+        };
+        for name in ["syntax", "inherits"] {
+            if !has_descriptor(name) {
+                errors.push(ParseError::new(
+                    DiagnosticCode::InvalidPropertyRule,
+                    format!("Missing '{}' descriptor", name),
+                    name_token.span,
+                ));
+            }
+        }
+        let is_universal = rule.syntax == Some(PropertySyntax::Universal);
+        if rule.syntax.is_some() && !is_universal && !has_descriptor("initial-value") {
+            errors.push(ParseError::new(
+                DiagnosticCode::InvalidPropertyRule,
+                "Missing 'initial-value' descriptor, which is required unless the syntax is '*'",
+                name_token.span,
+            ));
+        }
+        self.diagnostics
+            .extend(errors.into_iter().map(Diagnostic::from));
+        Ok(rule)
+    }
+
+    /// Parse a `{}` block of descriptors, e.g. the body of `@font-face`
+    fn parse_descriptor_block(&mut self, message: &str) -> Result<Vec<Declaration>, ParseError> {
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            message,
+        )?;
+        let descriptors = self.parse_declarations();
+        self.expect_block_end("Expected '}' after descriptors");
+        Ok(descriptors)
+    }
+
+    // <layer-rule> ::= "@layer" <layer-name> [ "," <layer-name> ]* ";" | "@layer" <layer-name>? "{" <rule>* "}"
+    /// Parse either form of `@layer`, returning a [`Rule::LayerStatement`] or a [`Rule::LayerBlock`]
+    pub fn parse_layer_rule(&mut self) -> Result<Rule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let mut names = Vec::new();
+        if !self.check_token_type(TokenType::CurlyLeft)
+            && !self.check_token_type(TokenType::Semicolon)
+        {
+            names.push(self.parse_layer_name()?);
+            self.skip_whitespace();
+            while self.match_token(&[TokenType::Comma]) {
+                self.skip_whitespace();
+                names.push(self.parse_layer_name()?);
+                self.skip_whitespace();
+            }
+        }
+
+        if self.check_token_type(TokenType::Semicolon) || self.is_end() {
+            if names.is_empty() {
+                return Err(
+                    self.error_at_current(DiagnosticCode::InvalidLayerName, "Expected layer name")
+                );
+            }
+            self.advance();
+            return Ok(Rule::LayerStatement(LayerStatementRule {
+                names,
+                span: self.span_from(start),
+            }));
+        }
+        if names.len() > 1 {
+            return Err(ParseError::new(
+                DiagnosticCode::InvalidLayerName,
+                "A layer block can only have one name",
+                names[0].span.to(names[names.len() - 1].span),
+            ));
+        }
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' or ';' after layer name",
+        )?;
+        let rules = self.parse_rules(false);
+        self.expect_block_end("Expected '}' after rules");
+        Ok(Rule::LayerBlock(LayerBlockRule {
+            name: names.pop(),
+            rules,
+            span: self.span_from(start),
+        }))
+    }
+
+    // <layer-name> ::= <ident> [ "." <ident> ]*
+    pub fn parse_layer_name(&mut self) -> Result<LayerName, ParseError> {
+        let start = self.current_token().span.start;
+        let mut names = Vec::new();
+        loop {
+            let token = self.expect_token(
+                TokenType::Ident,
+                DiagnosticCode::InvalidLayerName,
+                "Expected layer name",
+            )?;
+            let is_reserved = ["initial", "inherit", "unset", "revert", "revert-layer"]
+                .iter()
+                .any(|keyword| token.value.eq_ignore_ascii_case(keyword));
+            if is_reserved {
+                return Err(ParseError::new(
+                    DiagnosticCode::InvalidLayerName,
+                    format!("'{}' cannot be used as a layer name", token.lexeme),
+                    token.span,
+                ));
+            }
+            names.push(token.value.clone());
+            // the parts of a nested name are not separated by whitespace
+            if !(self.check_delim('.') && self.peek_token_type(1) == Some(TokenType::Ident)) {
+                break;
+            }
+            self.advance();
+        }
+        Ok(LayerName {
+            names,
+            span: self.span_from(start),
+        })
+    }
+
+    // <import-rule> ::= "@import" [ <url> | <string> ] [ "layer" | "layer(" <layer-name> ")" ]? [ "supports(" [ <supports-condition> | <declaration> ] ")" ]? <media-query-list>? ";"
+    pub fn parse_import_rule(&mut self) -> Result<ImportRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let (url, url_span) = self.parse_url()?;
+        self.skip_whitespace();
+
+        let layer = if self.check_ident("layer") {
+            Some(ImportLayer::Anonymous(self.advance().span))
+        } else if self.check_function("layer") {
+            self.advance();
+            self.skip_whitespace();
+            let name = self.parse_layer_name()?;
+            self.skip_whitespace();
+            self.expect_token(
+                TokenType::ParenRight,
+                DiagnosticCode::InvalidLayerName,
+                "Expected ')' after layer name",
+            )?;
+            Some(ImportLayer::Named(name))
+        } else {
+            None
+        };
+        self.skip_whitespace();
+
+        let supports = if self.check_function("supports") {
+            self.advance();
+            self.skip_whitespace();
+            // a bare declaration is allowed without the parentheses of <supports-decl>
+            let is_declaration = self.check_token_type(TokenType::Ident)
+                && self.tokens[self.current + 1..]
+                    .iter()
+                    .find(|token| token.token_type != TokenType::S)
+                    .map(|token| &token.token_type)
+                    == Some(&TokenType::Colon);
+            let condition = if is_declaration {
+                SupportsCondition::Declaration(self.parse_supports_declaration()?)
+            } else {
+                self.parse_supports_condition()?
+            };
+            self.skip_whitespace();
+            self.expect_token(
+                TokenType::ParenRight,
+                DiagnosticCode::InvalidSupportsCondition,
+                "Expected ')' after supports condition",
+            )?;
+            Some(condition)
+        } else {
+            None
+        };
+
+        let media = self.parse_media_query_list();
+        if !self.is_end() {
+            self.expect_token(
+                TokenType::Semicolon,
+                DiagnosticCode::ExpectedSemicolon,
+                "Expected ';' after import",
+            )?;
+        }
+        Ok(ImportRule {
+            url,
+            url_span,
+            layer,
+            supports,
+            media,
+            span: self.span_from(start),
+        })
+    }
+
+    // <charset-rule> ::= "@charset" <string> ";"
+    pub fn parse_charset_rule(&mut self) -> Result<CharsetRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let encoding = self
+            .expect_token(
+                TokenType::String,
+                DiagnosticCode::ExpectedCharsetEncoding,
+                "Expected encoding name as a string",
+            )?
+            .value
+            .clone();
+        self.skip_whitespace();
+        if !self.is_end() {
+            self.expect_token(
+                TokenType::Semicolon,
+                DiagnosticCode::ExpectedSemicolon,
+                "Expected ';' after charset",
+            )?;
+        }
+        Ok(CharsetRule {
+            encoding,
+            span: self.span_from(start),
+        })
+    }
+
+    // <namespace-rule> ::= "@namespace" <ident>? [ <url> | <string> ] ";"
+    pub fn parse_namespace_rule(&mut self) -> Result<NamespaceRule, ParseError> {
+        let start = self.current_token().span.start;
+        self.advance();
+        self.skip_whitespace();
+        let prefix = self
+            .consume_next_token(TokenType::Ident)
+            .map(|token| token.value.clone());
+        self.skip_whitespace();
+        let (url, url_span) = self.parse_url()?;
+        self.skip_whitespace();
+        if !self.is_end() {
+            self.expect_token(
+                TokenType::Semicolon,
+                DiagnosticCode::ExpectedSemicolon,
+                "Expected ';' after namespace",
+            )?;
+        }
+        Ok(NamespaceRule {
+            prefix,
+            url,
+            url_span,
+            span: self.span_from(start),
+        })
+    }
+
+    // <url> ::= <url-token> | "url(" <string> ")" | <string>
+    /// Parse a URL or a string, returning the URL without quotes along with the span it was parsed from
+    pub fn parse_url(&mut self) -> Result<(String, Span), ParseError> {
+        if let Some(token) = self
+            .consume_next_token(TokenType::Uri)
+            .or_else(|| self.consume_next_token(TokenType::String))
+        {
+            return Ok((token.value.clone(), token.span));
+        }
+        if self.check_function("url") {
+            // `url("a.css")` is a function, as the URL is quoted
+            let start = self.advance().span.start;
+            self.skip_whitespace();
+            let url = self
+                .expect_token(
+                    TokenType::String,
+                    DiagnosticCode::ExpectedUrl,
+                    "Expected string in 'url()'",
+                )?
+                .value
+                .clone();
+            self.skip_whitespace();
+            self.expect_token(
+                TokenType::ParenRight,
+                DiagnosticCode::ExpectedUrl,
+                "Expected ')' after URL",
+            )?;
+            return Ok((url, self.span_from(start)));
+        }
+        Err(self.error_at_current(DiagnosticCode::ExpectedUrl, "Expected URL or string"))
+    }
+
+    // <style-rule> ::= <selectors> "{" <declarations> "}"
+    pub fn parse_style_rule(&mut self) -> Result<StyleRule, ParseError> {
+        let start = self.current_token().span.start;
+        let selectors = self.parse_selectors()?;
+        self.expect_token(
+            TokenType::CurlyLeft,
+            DiagnosticCode::ExpectedCurlyLeft,
+            "Expected '{' after selectors",
+        )?;
+        self.nesting_depth += 1;
+        let (declarations, rules) = self.parse_style_block();
+        self.nesting_depth -= 1;
+        self.expect_block_end("Expected '}' after declarations");
+
+        let selector_text = selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        Ok(StyleRule {
+            selector_text,
+            selectors,
+            declarations,
+            rules,
+            span: self.span_from(start),
+        })
+    }
+
+    // <selectors> ::= <selector> | <selector> <combinator> <selectors>
+    pub fn parse_selectors(&mut self) -> Result<Vec<Selector>, ParseError> {
+        let mut selectors = Vec::new();
+        selectors.push(self.parse_selector()?);
+        while self.match_token(&[TokenType::Comma]) {
+            self.skip_whitespace();
+            selectors.push(self.parse_selector()?);
+        }
+        Ok(selectors)
+    }
+
+    // <selector> ::= <compound-selector> [ <combinator>? <compound-selector> ]*
+    // <relative-selector> ::= <combinator>? <selector>
+    pub fn parse_selector(&mut self) -> Result<Selector, ParseError> {
+        // a nested selector may start with a combinator, relative to the parent rule
+        self.parse_complex_selector(self.nesting_depth > 0)
+    }
+
+    /// Parse a selector, which may start with a combinator if it is `relative`, e.g. `> img` in `:has(> img)`
+    fn parse_complex_selector(&mut self, relative: bool) -> Result<Selector, ParseError> {
+        let start = self.current_token().span.start;
+        let mut combinator = if relative {
+            self.parse_combinator()
+        } else {
+            None
+        };
+        self.skip_whitespace();
+        let mut compounds = Vec::new();
+        loop {
+            compounds.push(self.parse_compound_selector(combinator)?);
+            let has_whitespace = self.check_token_type(TokenType::S);
+            self.skip_whitespace();
+            combinator = self.parse_combinator();
+            if combinator.is_some() {
+                self.skip_whitespace();
+            } else if has_whitespace && self.check_simple_selector() {
+                // whitespace is only a combinator when another compound follows
+                combinator = Some(Combinator::Descendant);
+            } else {
+                break;
+            }
+        }
+        // trailing whitespace is skipped above, so the span ends at the last compound selector
+        let span = Span::new(start, compounds[compounds.len() - 1].span.end);
+        Ok(Selector { compounds, span })
+    }
+
+    // <combinator> ::= ">" | "+" | "~" | "||"
+    pub fn parse_combinator(&mut self) -> Option<Combinator> {
+        let combinator = if self.check_token_type(TokenType::Greater) {
+            Combinator::Child
+        } else if self.check_token_type(TokenType::Plus) {
+            Combinator::NextSibling
+        } else if self.check_token_type(TokenType::Tilde) {
+            Combinator::SubsequentSibling
+        } else if self.check_delim('|') && self.peek_delim(1, '|') {
+            self.advance();
+            Combinator::Column
+        } else {
+            return None;
+        };
+        self.advance();
+        Some(combinator)
+    }
+
+    // <compound-selector> ::= <type-selector>? <subclass-selector>* [ <pseudo-element-selector> <pseudo-class-selector>* ]*
+    /// Parse the simple selectors up to the next whitespace or combinator, e.g. `a.btn:hover`
+    pub fn parse_compound_selector(
+        &mut self,
+        combinator: Option<Combinator>,
+    ) -> Result<CompoundSelector, ParseError> {
+        let start = self.current_token().span.start;
+        let type_selector = self.parse_type_selector();
+        let mut subclass_selectors = Vec::new();
+        if type_selector.is_none() {
+            subclass_selectors.push(self.parse_simple_selector()?);
+        }
+        while self.check_simple_selector() {
+            if self.check_type_selector() {
+                // e.g. .btn.a { ... }
+                return Err(self.error_at_current(
+                    DiagnosticCode::InvalidSelector,
+                    "A type selector must come first in a compound selector",
+                ));
+            }
+            let subclass_selector = self.parse_simple_selector()?;
+            let follows_pseudo_element = subclass_selectors
+                .iter()
+                .any(|selector| matches!(selector, SimpleSelector::PseudoElement(_)));
+            if follows_pseudo_element
+                && !matches!(
+                    subclass_selector,
+                    SimpleSelector::PseudoClass(_) | SimpleSelector::PseudoElement(_)
+                )
+            {
+                // e.g. p::before.a { ... }
+                return Err(ParseError::new(
+                    DiagnosticCode::InvalidSelector,
+                    "Only pseudo-classes and pseudo-elements can follow a pseudo-element",
+                    subclass_selector.span(),
+                ));
+            }
+            subclass_selectors.push(subclass_selector);
+        }
+        Ok(CompoundSelector {
+            combinator,
+            type_selector,
+            subclass_selectors,
+            span: self.span_from(start),
+        })
+    }
+
+    // <type-selector> ::= <ns-prefix>? [ <ident> | "*" ]
+    /// Parse a type or universal selector if there is one, e.g. `div`, `*` or `svg|circle`
+    pub fn parse_type_selector(&mut self) -> Option<TypeSelector> {
+        if !self.check_type_selector() {
+            return None;
+        }
+        let start_index = self.current;
+        let start = self.current_token().span.start;
+        let namespace = self.parse_namespace_prefix();
+        // e.g. div { ... } or * { ... }
+        let element = self.advance().value.clone();
+        let raw = self.tokens[start_index..self.current]
+            .iter()
+            .map(|token| token.lexeme.as_str())
+            .collect();
+        Some(TypeSelector {
+            namespace,
+            element,
+            raw,
+            span: self.span_from(start),
+        })
+    }
+
+    // <ns-prefix> ::= [ <ident> | "*" ]? "|"
+    /// Parse the namespace prefix of a type or attribute selector if there is one, e.g. `svg` in `svg|circle`.
+    /// It is empty for `|circle`, which matches elements without a namespace.
+    fn parse_namespace_prefix(&mut self) -> Option<String> {
+        let is_name = |parser: &Self, n: usize| {
+            parser.peek_token_type(n) == Some(TokenType::Ident) || parser.peek_delim(n, '*')
+        };
+        if self.check_delim('|') && is_name(self, 1) {
+            self.advance();
+            Some(String::new())
+        } else if is_name(self, 0) && self.peek_delim(1, '|') && is_name(self, 2) {
+            let prefix = self.advance().value.clone();
+            self.advance();
+            Some(prefix)
+        } else {
+            None
+        }
+    }
+
+    // <subclass-selector> ::= <id-selector> | <class-selector> | <pseudo-class-selector> | <pseudo-element-selector> | <nesting-selector>
+    pub fn parse_simple_selector(&mut self) -> Result<SimpleSelector, ParseError> {
+        let start = self.current_token().span.start;
+        if self.check_delim('.') && self.peek_token_type(1) == Some(TokenType::Ident) {
+            // e.g. .class { ... }
+            let dot = self.advance();
+            let ident = self.advance();
+            Ok(SimpleSelector::Class(ClassSelector {
+                class_name: ident.value.clone(),
+                raw: dot.lexeme.clone() + &ident.lexeme,
+                span: self.span_from(start),
+            }))
+        } else if self.check_token_type(TokenType::BracketLeft) {
+            // e.g. [data-state="open"] { ... }
+            self.parse_attribute_selector()
+                .map(SimpleSelector::Attribute)
+        } else if self.check_id_hash() {
+            // e.g. #id { ... }, but not #123 { ... }
+            let hash = self.advance();
+            Ok(SimpleSelector::Id(IdSelector {
+                id: hash.value.clone(),
+                raw: hash.lexeme.clone(),
+                span: self.span_from(start),
+            }))
+        } else if self.check_token_type(TokenType::Colon)
+            && self.peek_token_type(1) == Some(TokenType::Colon)
+            && self.peek_token_type(2) == Some(TokenType::Ident)
+        {
+            // e.g. ::before { ... }
+            self.advance();
+            self.advance();
+            Ok(SimpleSelector::PseudoElement(PseudoElementSelector {
+                ident: "::".to_string() + &self.advance().lexeme,
+                argument: None,
+                span: self.span_from(start),
+            }))
+        } else if self.check_token_type(TokenType::Colon)
+            && self.peek_token_type(1) == Some(TokenType::Colon)
+            && self.peek_token_type(2) == Some(TokenType::Function)
+        {
+            // e.g. ::slotted(.a) { ... }
+            self.advance();
+            self.advance();
+            let function = self.advance();
+            let argument =
+                self.nested(|parser| parser.parse_pseudo_element_argument(&function.value))?;
+            self.expect_token(
+                TokenType::ParenRight,
+                DiagnosticCode::InvalidSelector,
+                "Expected ')' after the pseudo-element argument",
+            )?;
+            Ok(SimpleSelector::PseudoElement(PseudoElementSelector {
+                ident: "::".to_string() + function.lexeme.trim_end_matches('('),
+                argument: Some(argument),
+                span: self.span_from(start),
+            }))
+        } else if self.check_token_type(TokenType::Colon)
+            && self.peek_token_type(1) == Some(TokenType::Ident)
+        {
+            self.advance();
+            let ident = self.advance();
+            let is_legacy_pseudo_element = ["before", "after", "first-line", "first-letter"]
+                .iter()
+                .any(|name| ident.value.eq_ignore_ascii_case(name));
+            if is_legacy_pseudo_element {
+                // e.g. :before { ... }, which is the same as ::before
+                return Ok(SimpleSelector::PseudoElement(PseudoElementSelector {
+                    ident: ":".to_string() + &ident.lexeme,
+                    argument: None,
+                    span: self.span_from(start),
+                }));
+            }
+            // e.g. :hover { ... }
+            Ok(SimpleSelector::PseudoClass(PseudoClassSelector {
+                ident: ":".to_string() + &ident.lexeme,
+                argument: None,
+                span: self.span_from(start),
+            }))
+        } else if self.check_token_type(TokenType::Colon)
+            && self.peek_token_type(1) == Some(TokenType::Function)
+        {
+            // e.g. :not(.a) { ... }
+            self.advance();
+            let function = self.advance();
+            let argument =
+                self.nested(|parser| parser.parse_pseudo_class_argument(&function.value))?;
+            self.expect_token(
+                TokenType::ParenRight,
+                DiagnosticCode::InvalidSelector,
+                "Expected ')' after the pseudo-class argument",
+            )?;
+            Ok(SimpleSelector::PseudoClass(PseudoClassSelector {
+                ident: ":".to_string() + function.lexeme.trim_end_matches('('),
+                argument: Some(argument),
+                span: self.span_from(start),
+            }))
+        } else if self.check_delim('&') {
+            // e.g. &:hover { ... }
+            Ok(SimpleSelector::Nesting(NestingSelector {
+                span: self.advance().span,
+            }))
+        } else {
+            Err(self.error_at_current(DiagnosticCode::ExpectedSelector, "Expected selector"))
+        }
+    }
+
+    /// Check if the current token is a hash token whose name would start an identifier, e.g. `#a` but not `#1a`,
+    /// which is the only kind of hash token that is an id selector
+    fn check_id_hash(&self) -> bool {
+        if !self.check_token_type(TokenType::Hash) {
+            return false;
+        }
+        let hash = self.current_token();
+        let mut name = hash.lexeme.chars().skip(1);
+        starts_identifier(name.next(), name.next(), name.next())
+    }
+
+    // <pseudo-class-argument> ::= <selectors> | <relative-selectors> | <an+b> [ "of" <selectors> ]? | <any-value>
+    /// Parse the argument of the functional pseudo-class with the given name, up to the closing `)`
+    fn parse_pseudo_class_argument(
+        &mut self,
+        name: &str,
+    ) -> Result<PseudoClassArgument, ParseError> {
+        self.skip_whitespace();
+        let argument = match name.to_ascii_lowercase().as_str() {
+            "is" | "where" => {
+                PseudoClassArgument::Selectors(self.parse_forgiving_selector_arguments())
+            }
+            "not" | "matches" | "-webkit-any" | "-moz-any" => {
+                PseudoClassArgument::Selectors(self.parse_selector_arguments(false)?)
+            }
+            // e.g. :host(.dark), which takes a compound selector
+            "host" | "host-context" => {
+                PseudoClassArgument::Selectors(vec![self.parse_complex_selector(false)?])
+            }
+            "has" => PseudoClassArgument::RelativeSelectors(self.parse_selector_arguments(true)?),
+            "nth-child" | "nth-last-child" => {
+                PseudoClassArgument::Nth(self.parse_nth_argument(true)?)
+            }
+            "nth-of-type" | "nth-last-of-type" | "nth-col" | "nth-last-col" => {
+                PseudoClassArgument::Nth(self.parse_nth_argument(false)?)
+            }
+            // e.g. :lang(en), :dir(rtl)
+            _ => PseudoClassArgument::Raw(self.parse_raw_value()?),
+        };
+        self.skip_whitespace();
+        Ok(argument)
+    }
+
+    /// Parse a comma-separated list of selectors in a pseudo-class argument,
+    /// which may start with combinators if they are `relative`, e.g. `> img, + p` in `:has(> img, + p)`
+    fn parse_selector_arguments(&mut self, relative: bool) -> Result<Vec<Selector>, ParseError> {
+        let mut selectors = vec![self.parse_complex_selector(relative)?];
+        while self.match_token(&[TokenType::Comma]) {
+            self.skip_whitespace();
+            selectors.push(self.parse_complex_selector(relative)?);
+        }
+        Ok(selectors)
+    }
+
+    /// Parse a [forgiving selector list](https://www.w3.org/TR/selectors-4/#forgiving-selector),
+    /// for `:is()` and `:where()`, where invalid selectors are dropped rather than invalidating the rule,
+    /// e.g. `.a` for `.a, :unknown(`, and which may be empty
+    fn parse_forgiving_selector_arguments(&mut self) -> Vec<Selector> {
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let checkpoint = self.current;
+            let diagnostic_count = self.diagnostics.len();
+            let result = self.parse_complex_selector(false);
+            self.skip_whitespace();
+            match result {
+                Ok(selector)
+                    if self.check_token_type(TokenType::Comma)
+                        || self.check_token_type(TokenType::ParenRight) =>
+                {
+                    selectors.push(selector);
+                }
+                _ => {
+                    self.current = checkpoint;
+                    self.backtrack_diagnostics(diagnostic_count);
+                    while !self.is_end()
+                        && !self.check_token_type(TokenType::Comma)
+                        && !self.check_token_type(TokenType::ParenRight)
+                        && !self.check_token_type(TokenType::CurlyLeft)
+                        && !self.check_token_type(TokenType::CurlyRight)
+                        && !self.check_token_type(TokenType::Semicolon)
+                    {
+                        self.skip_component_value();
+                    }
+                }
+            }
+            if !self.match_token(&[TokenType::Comma]) {
+                return selectors;
+            }
+        }
+    }
+
+    /// Parse the argument of a functional pseudo-element,
+    /// which is a compound selector for `::slotted()` and kept as written otherwise, e.g. `label` in `::part(label)`
+    fn parse_pseudo_element_argument(
+        &mut self,
+        name: &str,
+    ) -> Result<PseudoClassArgument, ParseError> {
+        self.skip_whitespace();
+        let argument = if name.eq_ignore_ascii_case("slotted") {
+            PseudoClassArgument::Selectors(vec![self.parse_complex_selector(false)?])
+        } else {
+            PseudoClassArgument::Raw(self.parse_raw_value()?)
+        };
+        self.skip_whitespace();
+        Ok(argument)
+    }
+
+    // <nth> ::= <an+b> [ "of" <selectors> ]?
+    fn parse_nth_argument(&mut self, has_selectors: bool) -> Result<NthArgument, ParseError> {
+        let (a, b) = self.parse_an_plus_b()?;
+        self.skip_whitespace();
+        let selectors = if has_selectors && self.check_ident("of") {
+            self.advance();
+            self.skip_whitespace();
+            Some(self.parse_selector_arguments(false)?)
+        } else {
+            None
+        };
+        Ok(NthArgument { a, b, selectors })
+    }
+
+    // <an+b> ::= "odd" | "even" | <integer> | <n-dimension> [ ["+" | "-"] <integer> ]? | ...
+    /// Parse the [An+B microsyntax](https://www.w3.org/TR/css-syntax-3/#anb-microsyntax) into `(a, b)`.
+    /// It is tokenized in awkward ways, e.g. `2n-1` is a single dimension token with the unit `n-1`,
+    /// and `2n+1` is the dimension `2n` followed by the number `+1`.
+    fn parse_an_plus_b(&mut self) -> Result<(i32, i32), ParseError> {
+        let message = "Expected an An+B expression, e.g. '2n+1'";
+        if self.check_ident("odd") || self.check_ident("even") {
+            let b = if self.advance().value.eq_ignore_ascii_case("odd") {
+                1
+            } else {
+                0
+            };
+            return Ok((2, b));
+        }
+        if self.check_token_type(TokenType::Number) {
+            let b = parse_integer(&self.current_token().lexeme)
+                .ok_or_else(|| self.error_at_current(DiagnosticCode::InvalidSelector, message))?;
+            self.advance();
+            return Ok((0, b));
+        }
+
+        // the coefficient of `n`, and the rest of the token starting with `n`, e.g. `-2` and `n-1` for `-2n-1`
+        let (a, rest) = if self.check_token_type(TokenType::Dimension) {
+            let token = self.current_token();
+            // the unit may be escaped, e.g. `2\6e`, so the number is split off the start instead
+            let coefficient = &token.lexeme[..number_length(&token.lexeme)];
+            let a = parse_integer(coefficient)
+                .ok_or_else(|| self.error_at_current(DiagnosticCode::InvalidSelector, message))?;
+            (a, token.value.to_ascii_lowercase())
+        } else if self.check_token_type(TokenType::Ident) {
+            let value = self.current_token().value.to_ascii_lowercase();
+            match value.strip_prefix('-') {
+                Some(rest) => (-1, rest.to_string()),
+                None => (1, value),
+            }
+        } else if self.check_token_type(TokenType::Plus)
+            && self.peek_token_type(1) == Some(TokenType::Ident)
+            && !self.tokens[self.current + 1].value.starts_with('-')
+        {
+            // e.g. `+n`, where there cannot be whitespace between the `+` and the `n`
+            self.advance();
+            (1, self.current_token().value.to_ascii_lowercase())
+        } else {
+            return Err(self.error_at_current(DiagnosticCode::InvalidSelector, message));
+        };
+        let error = self.error_at_current(DiagnosticCode::InvalidSelector, message);
+        self.advance();
+
+        let b = match rest.as_str() {
+            "n" => {
+                let checkpoint = self.current;
+                self.skip_whitespace();
+                let is_signed_number = self.check_token_type(TokenType::Number)
+                    && self.current_token().lexeme.starts_with(['+', '-']);
+                if is_signed_number {
+                    // e.g. `2n +1`
+                    let b = parse_integer(&self.current_token().lexeme).ok_or_else(|| {
+                        self.error_at_current(DiagnosticCode::InvalidSelector, message)
+                    })?;
+                    self.advance();
+                    b
+                } else if self.check_token_type(TokenType::Plus) || self.check_delim('-') {
+                    // e.g. `2n + 1`
+                    let sign = if self.advance().token_type == TokenType::Plus {
+                        1
+                    } else {
+                        -1
+                    };
+                    self.skip_whitespace();
+                    sign * self.parse_unsigned_integer(message)?
+                } else {
+                    self.current = checkpoint;
+                    0
+                }
+            }
+            "n-" => {
+                // e.g. `2n- 1`
+                self.skip_whitespace();
+                -self.parse_unsigned_integer(message)?
+            }
+            rest => match rest.strip_prefix("n-") {
+                // e.g. `2n-1`
+                Some(digits)
+                    if !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) =>
+                {
+                    -digits.parse::<i32>().map_err(|_| error.clone())?
+                }
+                _ => return Err(error),
+            },
+        };
+        Ok((a, b))
+    }
+
+    /// Consume an integer without a sign, e.g. `1` in `2n + 1`
+    fn parse_unsigned_integer(&mut self, message: &str) -> Result<i32, ParseError> {
+        let integer = if self.check_token_type(TokenType::Number)
+            && !self.current_token().lexeme.starts_with(['+', '-'])
+        {
+            parse_integer(&self.current_token().lexeme)
+        } else {
+            None
+        };
+        let integer = integer
+            .ok_or_else(|| self.error_at_current(DiagnosticCode::InvalidSelector, message))?;
+        self.advance();
+        Ok(integer)
+    }
+
+    // <attribute-selector> ::= "[" <wq-name> "]" | "[" <wq-name> <attr-matcher> [ <string> | <ident> ] <attr-modifier>? "]"
+    pub fn parse_attribute_selector(&mut self) -> Result<AttributeSelector, ParseError> {
+        let start = self.current_token().span.start;
+        self.expect_token(
+            TokenType::BracketLeft,
+            DiagnosticCode::InvalidSelector,
+            "Expected '['",
+        )?;
+        self.skip_whitespace();
+
+        // <wq-name> ::= <ns-prefix>? <ident>
+        // `|=` is a single token, so `[lang|=en]` is not mistaken for a namespace prefix
+        let namespace = self.parse_namespace_prefix();
+        let name = self
+            .expect_token(
+                TokenType::Ident,
+                DiagnosticCode::InvalidSelector,
+                "Expected attribute name",
+            )?
+            .value
+            .clone();
+        self.skip_whitespace();
+
+        let matcher = if self.check_token_type(TokenType::BracketRight) {
+            None
+        } else {
+            let operator = self.parse_attribute_operator()?;
+            self.skip_whitespace();
+            let quoted = self.check_token_type(TokenType::String);
+            let value = self
+                .consume_next_token(TokenType::String)
+                .or_else(|| self.consume_next_token(TokenType::Ident))
+                .ok_or_else(|| {
+                    self.error_at_current(
+                        DiagnosticCode::InvalidSelector,
+                        "Expected an identifier or a string as the attribute value",
+                    )
+                })?
+                .value
+                .clone();
+            self.skip_whitespace();
+
+            // <attr-modifier> ::= "i" | "s"
+            let modifier = if self.check_ident("i") {
+                Some(AttributeModifier::CaseInsensitive)
+            } else if self.check_ident("s") {
+                Some(AttributeModifier::CaseSensitive)
+            } else if self.check_token_type(TokenType::Ident) {
+                return Err(self.error_at_current(
+                    DiagnosticCode::InvalidSelector,
+                    "Expected 'i' or 's' as the attribute selector modifier",
+                ));
+            } else {
+                None
+            };
+            if modifier.is_some() {
+                self.advance();
+                self.skip_whitespace();
+            }
+            Some(AttributeMatcher {
+                operator,
+                value,
+                quoted,
+                modifier,
+            })
+        };
+        self.expect_token(
+            TokenType::BracketRight,
+            DiagnosticCode::InvalidSelector,
+            "Expected ']'",
+        )?;
+        Ok(AttributeSelector {
+            namespace,
+            name,
+            matcher,
+            span: self.span_from(start),
+        })
+    }
+
+    // <attr-matcher> ::= [ "~" | "|" | "^" | "$" | "*" ]? "="
+    fn parse_attribute_operator(&mut self) -> Result<AttributeOperator, ParseError> {
+        if self.match_token(&[TokenType::Includes]) {
+            return Ok(AttributeOperator::Includes);
+        }
+        if self.match_token(&[TokenType::DashMatch]) {
+            return Ok(AttributeOperator::DashMatch);
+        }
+        let operator = if self.check_delim('=') {
+            AttributeOperator::Equals
+        } else if self.check_delim('^') && self.peek_delim(1, '=') {
+            AttributeOperator::Prefix
+        } else if self.check_delim('$') && self.peek_delim(1, '=') {
+            AttributeOperator::Suffix
+        } else if self.check_delim('*') && self.peek_delim(1, '=') {
+            AttributeOperator::Substring
+        } else {
+            return Err(self.error_at_current(
+                DiagnosticCode::InvalidSelector,
+                "Expected ']' or an attribute matcher such as '='",
+            ));
+        };
+        if operator != AttributeOperator::Equals {
+            self.advance();
+        }
+        self.advance();
+        Ok(operator)
+    }
+
+    // <style-block> ::= [ <declaration> | <nested-rule> ]*
+    /// Parse the contents of a style rule, in which declarations and nested rules can be mixed
+    pub fn parse_style_block(&mut self) -> (Vec<Declaration>, Vec<Rule>) {
+        let mut declarations = Vec::new();
+        let mut rules = Vec::new();
+        if self.enter_nested().is_err() {
+            self.skip_block_contents();
+            return (declarations, rules);
+        }
+        loop {
+            while self.match_token(&[TokenType::S, TokenType::Semicolon]) {}
+            if self.check_token_type(TokenType::CurlyRight) || self.is_end() {
+                break;
+            }
+            if self.check_token_type(TokenType::Atkeyword) || self.check_nested_style_rule() {
+                self.parse_rule_or_recover(&mut rules, false);
+            } else if let Some(declaration) = self.parse_declaration_or_recover() {
+                declarations.push(declaration);
+            }
+        }
+        self.depth -= 1;
+        (declarations, rules)
+    }
+
+    /// Check if a nested style rule starts at the current token rather than a declaration,
+    /// i.e. a `{}` block comes before the `;` or `}` which would end a declaration.
+    /// A custom property is always a declaration, as its value may contain `{}` blocks, e.g. `--x: { a: b };`
+    fn check_nested_style_rule(&mut self) -> bool {
+        if self.check_custom_property() {
+            return false;
+        }
+        let checkpoint = self.current;
+        while !self.is_end()
+            && !self.check_token_type(TokenType::Semicolon)
+            && !self.check_token_type(TokenType::CurlyLeft)
+            && !self.check_token_type(TokenType::CurlyRight)
+        {
+            self.skip_component_value();
+        }
+        let is_rule = self.check_token_type(TokenType::CurlyLeft);
+        self.current = checkpoint;
+        is_rule
+    }
+
+    /// Check if a custom property declaration starts at the current token, e.g. `--gap: 8px`
+    fn check_custom_property(&self) -> bool {
+        if !self.check_token_type(TokenType::Ident) || !self.current_token().value.starts_with("--")
+        {
+            return false;
+        }
+        let mut n = 1;
+        while self.peek_token_type(n) == Some(TokenType::S) {
+            n += 1;
+        }
+        self.peek_token_type(n) == Some(TokenType::Colon)
+    }
+
+    // <declarations> ::= <declaration> | <declaration> <declarations>
+    pub fn parse_declarations(&mut self) -> Vec<Declaration> {
+        let mut declarations = Vec::new();
+        loop {
+            while self.match_token(&[TokenType::S, TokenType::Semicolon]) {}
+            if self.check_token_type(TokenType::CurlyRight) || self.is_end() {
+                break;
+            }
+            if self.check_token_type(TokenType::Atkeyword) {
+                self.skip_unsupported_at_rule();
+                continue;
+            }
+            if let Some(declaration) = self.parse_declaration_or_recover() {
+                declarations.push(declaration);
+            }
+        }
+        declarations
+    }
+
+    /// Parse a declaration, or skip and report it when it cannot be parsed
+    fn parse_declaration_or_recover(&mut self) -> Option<Declaration> {
+        let start = self.current_token().span.start;
+        match self.parse_declaration() {
+            Ok(declaration) => Some(declaration),
+            Err(error) => {
+                self.report(error);
+                self.recover_declaration();
+                self.report_skipped(
+                    DiagnosticCode::IgnoredDeclaration,
+                    "Declaration is ignored",
+                    start,
+                );
+                None
+            }
+        }
+    }
+
+    /// Skip and report an at-rule which is not allowed where it appears
+    fn skip_unsupported_at_rule(&mut self) {
+        let token = self.current_token();
+        self.diagnostics.push(
+            ParseError::new(
+                DiagnosticCode::UnsupportedAtRule,
+                format!("At-rule '{}' is not supported yet", token.lexeme),
+                token.span,
+            )
+            .into(),
+        );
+        self.recover_at_rule();
+        self.report_skipped(
+            DiagnosticCode::IgnoredRule,
+            "Rule is ignored",
+            token.span.start,
+        );
+    }
+
+    // <declaration> ::= <property> ":" <value> <important>? ";"
+    // <property> ::= <ident>
+    pub fn parse_declaration(&mut self) -> Result<Declaration, ParseError> {
+        let start = self.current_token().span.start;
+        let property = self
+            .expect_token(
+                TokenType::Ident,
+                DiagnosticCode::ExpectedProperty,
+                "Expected property name",
+            )?
+            .value
+            .clone();
+        self.skip_whitespace();
+        self.expect_token(
+            TokenType::Colon,
+            DiagnosticCode::ExpectedColon,
+            "Expected ':' after property name",
+        )?;
+        self.skip_whitespace();
+        let value = match self.parse_value() {
+            // the value of a custom property may be empty, e.g. `--x: ;`
+            Err(error)
+                if error.code == DiagnosticCode::ExpectedValue && property.starts_with("--") =>
+            {
+                let position = self.current_token().span.start;
+                Value {
+                    components: Vec::new(),
+                    span: Span::new(position, position),
+                }
+            }
+            result => result?,
+        };
+        // <important> ::= "!" <whitespace>* "important"
+        let important = self.check_important();
+        if important {
+            self.advance();
+            self.skip_whitespace();
+            self.advance();
+        }
+        self.skip_whitespace();
+        // the last declaration in a block may omit its semicolon
+        if !self.check_token_type(TokenType::CurlyRight) && !self.is_end() {
+            self.expect_token(
+                TokenType::Semicolon,
+                DiagnosticCode::ExpectedSemicolon,
+                "Expected ';' after value",
+            )?;
+        }
+
+        let css_text = format!(
+            "{}: {}{};",
+            property,
+            value.to_string(),
+            if important { " !important" } else { "" }
+        );
+
+        Ok(Declaration {
+            property,
+            value,
+            important,
+            css_text,
+            span: self.span_from(start),
+        })
+    }
+
+    // <value> ::= <component-value> [ <whitespace>? [ "," | "/" ]? <whitespace>? <component-value> ]*
+    /// Parse component values up to the `;` or `}` ending the declaration, e.g. `0 auto` or `a 1s, b 2s`
+    pub fn parse_value(&mut self) -> Result<Value, ParseError> {
+        let mut components = Vec::new();
+        while !self.is_end()
+            && !self.check_token_type(TokenType::Semicolon)
+            && !self.check_token_type(TokenType::CurlyRight)
+            && !self.check_important()
+        {
+            components.push(self.parse_component_value());
+        }
+        // the whitespace before `;` is not part of the value
+        if let Some(ComponentValue::Whitespace(_)) = components.last() {
+            components.pop();
+        }
+        let (Some(first), Some(last)) = (components.first(), components.last()) else {
+            return Err(self.error_at_current(DiagnosticCode::ExpectedValue, "Expected value"));
+        };
+        let span = first.span().to(last.span());
+        Ok(Value { components, span })
+    }
+
+    /// Check if `!important` ending the declaration starts at the current token.
+    /// Comments and whitespace may come between the `!` and `important`, e.g. `! /* why */ IMPORTANT`.
+    fn check_important(&self) -> bool {
+        if !self.check_delim('!') {
+            return false;
+        }
+        let mut n = 1;
+        while self.peek_token_type(n) == Some(TokenType::S) {
+            n += 1;
+        }
+        if self.peek_token_type(n) != Some(TokenType::Ident)
+            || !self.tokens[self.current + n]
+                .value
+                .eq_ignore_ascii_case("important")
+        {
+            return false;
+        }
+        n += 1;
+        while self.peek_token_type(n) == Some(TokenType::S) {
+            n += 1;
+        }
+        matches!(
+            self.peek_token_type(n),
+            None | Some(TokenType::Semicolon) | Some(TokenType::CurlyRight) | Some(TokenType::Eof)
+        )
+    }
+
+    // <component-value> ::= <ident> | <number> | <percentage> | <dimension> | <string> | <hash> | <url> | ...
+    fn parse_component_value(&mut self) -> ComponentValue {
+        if self.check_token_type(TokenType::Function) {
+            // a function nested too deeply is skipped and kept as written
+            if self.enter_nested().is_err() {
+                let (text, span) = self.parse_general_enclosed();
+                return ComponentValue::Raw(text, span);
+            }
+            let value = self.parse_function_or_math_function();
+            self.depth -= 1;
+            return value;
+        }
+        let start = self.current_token().span.start;
+        let token = self.advance();
+        match token.token_type {
+            // e.g. { margin: 0 auto; }, where a comment between whitespace leaves two whitespace tokens
+            TokenType::S => {
+                self.skip_whitespace();
+                ComponentValue::Whitespace(self.span_from(start))
+            }
+            // e.g. { transition: opacity 1s, transform 2s; }
+            TokenType::Comma => ComponentValue::Comma(token.span),
+            // e.g. { font: 12px/1.5 sans-serif; }
+            TokenType::Delim if token.value == "/" => ComponentValue::Slash(token.span),
+            // e.g. { color: red; }
+            TokenType::Ident => {
+                ComponentValue::Literal(LiteralValue::Ident(token.value.clone()), token.span)
+            }
+            // e.g. { flex: 1; width: 100%; margin: -0.5em; }
+            TokenType::Number | TokenType::Percentage | TokenType::Dimension => {
+                match numeric_literal(&token) {
+                    Some(literal) => ComponentValue::Literal(literal, token.span),
+                    None => ComponentValue::Raw(token.lexeme.clone(), token.span),
+                }
+            }
+            // e.g. { content: "a"; }
+            TokenType::String => {
+                ComponentValue::Literal(LiteralValue::String(token.value.clone()), token.span)
+            }
+            // e.g. { color: #fff; }
+            TokenType::Hash => ComponentValue::Hash(token.lexeme.clone(), token.span),
+            // e.g. { background: url(a.png); }
+            TokenType::Uri => ComponentValue::Url(token.value.clone(), token.span),
+            _ => {
+                // e.g. a `[]` block, which is kept as written including everything nested in it
+                self.current -= 1;
+                let (text, span) = self.parse_general_enclosed();
+                ComponentValue::Raw(text, span)
+            }
+        }
+    }
+
+    /// Parse a function at the current function token, e.g. `var(--gap)` or `calc(100% - 8px)`.
+    /// A math function which is not a valid calculation is kept as a plain function with a warning,
+    /// e.g. `calc(1px+2px)` where `+` needs whitespace around it.
+    fn parse_function_or_math_function(&mut self) -> ComponentValue {
+        if math_function_arity(&self.current_token().value).is_some() {
+            let checkpoint = self.current;
+            match self.parse_math_function() {
+                Ok(math_function) => return ComponentValue::Math(math_function),
+                Err(error) => {
+                    self.current = checkpoint;
+                    if error.code != DiagnosticCode::NestingTooDeep {
+                        self.diagnostics.push(Diagnostic {
+                            severity: Severity::Warning,
+                            ..error.into()
+                        });
+                    }
+                }
+            }
+        }
+        ComponentValue::Function(self.parse_function())
+    }
+
+    // <function> ::= <function-token> <component-value>* ")"
+    fn parse_function(&mut self) -> FunctionValue {
+        let start = self.current_token().span.start;
+        let name = self.advance().value.clone();
+        self.skip_whitespace();
+        let mut arguments = Vec::new();
+        while !self.is_end() && !self.check_token_type(TokenType::ParenRight) {
+            arguments.push(self.parse_component_value());
+        }
+        if let Some(ComponentValue::Whitespace(_)) = arguments.last() {
+            arguments.pop();
+        }
+        // a function which is still open at the end of input is closed implicitly
+        self.match_token(&[TokenType::ParenRight]);
+        FunctionValue {
+            name,
+            arguments,
+            span: self.span_from(start),
+        }
+    }
+
+    // <math-function> ::= <function-token> <calc-sum> [ "," <calc-sum> ]* ")"
+    pub fn parse_math_function(&mut self) -> Result<MathFunction, ParseError> {
+        let start = self.current_token().span.start;
+        let name = self
+            .expect_token(
+                TokenType::Function,
+                DiagnosticCode::ExpectedValue,
+                "Expected math function",
+            )?
+            .value
+            .clone();
+        self.skip_whitespace();
+        let mut arguments = vec![self.nested(Parser::parse_calc_sum)?];
+        self.skip_whitespace();
+        while self.match_token(&[TokenType::Comma]) {
+            self.skip_whitespace();
+            arguments.push(self.nested(Parser::parse_calc_sum)?);
+            self.skip_whitespace();
+        }
+        self.expect_token(
+            TokenType::ParenRight,
+            DiagnosticCode::ExpectedValue,
+            "Expected ')' after the arguments of the math function",
+        )?;
+        let span = self.span_from(start);
+        match math_function_arity(&name) {
+            Some((min, max)) if (min..=max).contains(&arguments.len()) => Ok(MathFunction {
+                name,
+                arguments,
+                span,
+            }),
+            _ => Err(ParseError::new(
+                DiagnosticCode::ExpectedValue,
+                format!("Wrong number of arguments for {}()", name),
+                span,
+            )),
+        }
+    }
+
+    // <calc-sum> ::= <calc-product> [ [ "+" | "-" ] <calc-product> ]*
+    fn parse_calc_sum(&mut self) -> Result<MathExpression, ParseError> {
+        let mut expression = self.parse_calc_product()?;
+        loop {
+            // `+` and `-` must be surrounded by whitespace, since e.g. `1px -2px` is two values
+            let checkpoint = self.current;
+            self.skip_whitespace();
+            let operator = if self.check_token_type(TokenType::Plus) {
+                MathOperator::Add
+            } else if self.check_delim('-') {
+                MathOperator::Subtract
+            } else {
+                self.current = checkpoint;
+                return Ok(expression);
+            };
+            if self.current == checkpoint || self.peek_token_type(1) != Some(TokenType::S) {
+                return Err(self.error_at_current(
+                    DiagnosticCode::ExpectedValue,
+                    "'+' and '-' must be surrounded by whitespace",
+                ));
+            }
+            self.advance();
+            self.skip_whitespace();
+            let right = self.parse_calc_product()?;
+            expression = MathExpression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+    }
+
+    // <calc-product> ::= <calc-value> [ [ "*" | "/" ] <calc-value> ]*
+    fn parse_calc_product(&mut self) -> Result<MathExpression, ParseError> {
+        let mut expression = self.parse_calc_value()?;
+        loop {
+            let checkpoint = self.current;
+            self.skip_whitespace();
+            let operator = if self.check_delim('*') {
+                MathOperator::Multiply
+            } else if self.check_delim('/') {
+                MathOperator::Divide
+            } else {
+                self.current = checkpoint;
+                return Ok(expression);
+            };
+            self.advance();
+            self.skip_whitespace();
+            let right = self.parse_calc_value()?;
+            expression = MathExpression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+    }
+
+    // <calc-value> ::= <number> | <dimension> | <percentage> | <ident> | "(" <calc-sum> ")" | <function> | <math-function>
+    fn parse_calc_value(&mut self) -> Result<MathExpression, ParseError> {
+        let start = self.current_token().span.start;
+        if self.check_token_type(TokenType::Function) {
+            if math_function_arity(&self.current_token().value).is_some() {
+                return Ok(MathExpression::Math(self.parse_math_function()?));
+            }
+            // e.g. var(--gap), which can only be checked once it is substituted
+            return Ok(MathExpression::Function(self.parse_function()));
+        }
+        if self.match_token(&[TokenType::ParenLeft]) {
+            self.skip_whitespace();
+            let expression = self.nested(Parser::parse_calc_sum)?;
+            self.skip_whitespace();
+            self.expect_token(
+                TokenType::ParenRight,
+                DiagnosticCode::ExpectedValue,
+                "Expected ')' after calculation",
+            )?;
+            return Ok(MathExpression::Parenthesized(
+                Box::new(expression),
+                self.span_from(start),
+            ));
+        }
+        let token = self.current_token();
+        let literal = match numeric_literal(&token) {
+            Some(literal) => literal,
+            // e.g. pi, infinity, or a rounding strategy such as nearest
+            None if token.token_type == TokenType::Ident => {
+                LiteralValue::Ident(token.value.clone())
+            }
+            None => {
+                return Err(self.error_at_current(
+                    DiagnosticCode::ExpectedValue,
+                    "Expected a number, dimension, percentage or '(' in calculation",
+                ))
+            }
+        };
+        self.advance();
+        Ok(MathExpression::Literal(literal, token.span))
+    }
+
+    /// Consume component values up to a `)`, `}` or `;`, returning them as written without trailing whitespace.
+    /// This is used where any value is allowed, e.g. `(transform-origin: 5% 5%)` in `@supports`.
+    fn parse_raw_value(&mut self) -> Result<String, ParseError> {
+        let value_start = self.current;
+        let mut value_end = self.current;
+        while !self.is_end()
+            && !self.check_token_type(TokenType::ParenRight)
+            && !self.check_token_type(TokenType::CurlyRight)
+            && !self.check_token_type(TokenType::Semicolon)
+        {
+            let is_whitespace = self.check_token_type(TokenType::S);
+            self.skip_component_value();
+            if !is_whitespace {
+                value_end = self.current;
+            }
+        }
+        self.current = value_end;
+        if value_start == value_end {
+            return Err(self.error_at_current(DiagnosticCode::ExpectedValue, "Expected value"));
+        }
+        Ok(self.tokens[value_start..value_end]
+            .iter()
+            .map(|token| token.lexeme.as_str())
+            .collect())
+    }
+
+    /// Consume the `}` closing a block.
+    /// It can only be missing at the end of input, where the block is closed implicitly, so this just reports it.
+    fn expect_block_end(&mut self, message: &str) {
+        if let Err(error) = self.expect_token(
+            TokenType::CurlyRight,
+            DiagnosticCode::ExpectedCurlyRight,
+            message,
+        ) {
+            self.report(error);
+        }
+    }
+
+    /// Skip the rest of a style rule after an error, up to and including its `{}` block.
+    /// When `nested`, this stops before a `}` which closes the enclosing block.
+    fn recover_rule(&mut self, nested: bool) {
+        while !(self.is_end() || nested && self.check_token_type(TokenType::CurlyRight)) {
+            if self.check_token_type(TokenType::CurlyLeft) {
+                self.skip_component_value();
+                return;
+            }
+            self.skip_component_value();
+        }
+    }
+
+    /// Skip the rest of an at-rule, up to and including its `;` or `{}` block,
+    /// stopping before a `}` which closes the enclosing block
+    fn recover_at_rule(&mut self) {
+        while !self.is_end() && !self.check_token_type(TokenType::CurlyRight) {
+            if self.match_token(&[TokenType::Semicolon]) {
+                return;
+            }
+            if self.check_token_type(TokenType::CurlyLeft) {
+                self.skip_component_value();
+                return;
+            }
+            self.skip_component_value();
+        }
+    }
+
+    /// Skip the rest of a declaration after an error, up to and including the next `;`,
+    /// stopping before a `}` which closes the enclosing block
+    fn recover_declaration(&mut self) {
+        while !self.is_end() && !self.check_token_type(TokenType::CurlyRight) {
+            if self.match_token(&[TokenType::Semicolon]) {
+                return;
+            }
+            self.skip_component_value();
+        }
+    }
+
+    /// Go one level deeper into nested blocks, functions or parentheses.
+    /// Nesting deeper than [`MAX_DEPTH`] is reported as soon as it is found, since backtracking would drop the error.
+    fn enter_nested(&mut self) -> Result<(), ParseError> {
+        if self.depth < MAX_DEPTH {
+            self.depth += 1;
+            return Ok(());
+        }
+        let error = self.error_at_current(DiagnosticCode::NestingTooDeep, "Nesting is too deep");
+        // the same position is reached again when a caller backtracks to try another production
+        let reported = self.diagnostics.iter().any(|diagnostic| {
+            diagnostic.code == DiagnosticCode::NestingTooDeep && diagnostic.span == error.span
+        });
+        if !reported {
+            self.diagnostics.push(error.clone().into());
+        }
+        Err(error)
+    }
+
+    /// Parse something nested one level deeper, failing once nesting is too deep
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.enter_nested()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Report an error as a diagnostic, unless it is nesting which is too deep and has been reported already
+    fn report(&mut self, error: ParseError) {
+        if error.code != DiagnosticCode::NestingTooDeep {
+            self.diagnostics.push(error.into());
+        }
+    }
+
+    /// Drop the diagnostics reported after `count` when backtracking, except for nesting which is too deep
+    fn backtrack_diagnostics(&mut self, count: usize) {
+        let kept: Vec<Diagnostic> = self
+            .diagnostics
+            .drain(count..)
+            .filter(|diagnostic| diagnostic.code == DiagnosticCode::NestingTooDeep)
+            .collect();
+        self.diagnostics.extend(kept);
+    }
+
+    /// Skip the rest of a block, stopping before the `}` which closes it
+    fn skip_block_contents(&mut self) {
+        while !self.is_end() && !self.check_token_type(TokenType::CurlyRight) {
+            self.skip_component_value();
+        }
+    }
+
+    /// Skip a [component value](https://www.w3.org/TR/css-syntax-3/#component-value):
+    /// a single token, or a whole `{}`, `[]`, `()` block or function including everything nested in it.
+    /// The closing tokens are tracked on a stack rather than by recursion, so deeply nested input cannot overflow.
+    fn skip_component_value(&mut self) {
+        let mut closing_tokens = Vec::new();
+        while !self.is_end() {
+            let token_type = self.advance().token_type.clone();
+            match token_type {
+                TokenType::CurlyLeft => closing_tokens.push(TokenType::CurlyRight),
+                TokenType::BracketLeft => closing_tokens.push(TokenType::BracketRight),
+                TokenType::ParenLeft | TokenType::Function => {
+                    closing_tokens.push(TokenType::ParenRight)
+                }
+                token_type if closing_tokens.last() == Some(&token_type) => {
+                    closing_tokens.pop();
+                }
+                _ => {}
+            }
+            if closing_tokens.is_empty() {
+                break;
+            }
+        }
+    }
+
+    /// Report the range from `start` to the end of the previous token as skipped by error recovery
+    fn report_skipped(&mut self, code: DiagnosticCode, message: &str, start: usize) {
+        self.diagnostics.push(Diagnostic {
+            code,
+            message: message.to_string(),
+            span: self.span_from(start),
+            severity: Severity::Warning,
+        });
+    }
+
+    /// Check if the current token can start a type or universal selector, e.g. `div`, `*` or `|circle`
+    fn check_type_selector(&self) -> bool {
+        self.check_token_type(TokenType::Ident)
+            || self.check_delim('*')
+            || self.check_delim('|')
+                && (self.peek_token_type(1) == Some(TokenType::Ident) || self.peek_delim(1, '*'))
+    }
+
+    /// Check if the current token can start a simple selector
+    fn check_simple_selector(&self) -> bool {
+        self.check_type_selector()
+            || self.check_token_type(TokenType::Hash)
+            || self.check_token_type(TokenType::BracketLeft)
+            || self.check_token_type(TokenType::Colon)
+            || self.check_delim('.')
+            || self.check_delim('&')
+    }
+
+    /// Skip any whitespace tokens at the current position
+    pub fn skip_whitespace(&mut self) {
+        while self.match_token(&[TokenType::S]) {}
+    }
+
+    /// Check if the current token is the given ident, ignoring ASCII case, e.g. `and`
+    pub fn check_ident(&self, ident: &str) -> bool {
+        self.check_token_type(TokenType::Ident)
+            && self.tokens[self.current].value.eq_ignore_ascii_case(ident)
+    }
+
+    /// Check if the current token is the at-keyword with the given name, ignoring ASCII case, e.g. `media`
+    pub fn check_at_keyword(&self, name: &str) -> bool {
+        self.check_token_type(TokenType::Atkeyword)
+            && self.tokens[self.current].value.eq_ignore_ascii_case(name)
+    }
+
+    /// The vendor prefix of the current token if it is a `@keyframes` at-keyword,
+    /// e.g. `Some("")` for `@keyframes` and `Some("-webkit-")` for `@-webkit-keyframes`
+    fn keyframes_prefix(&self) -> Option<String> {
+        if !self.check_token_type(TokenType::Atkeyword) {
+            return None;
+        }
+        let name = self.tokens[self.current].value.to_ascii_lowercase();
+        let prefix = name.strip_suffix("keyframes")?;
+        if prefix.is_empty() || prefix.len() > 2 && prefix.starts_with('-') && prefix.ends_with('-')
+        {
+            Some(prefix.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Check if the current token is the function with the given name, ignoring ASCII case, e.g. `selector(`
+    pub fn check_function(&self, name: &str) -> bool {
+        self.check_token_type(TokenType::Function)
+            && self.tokens[self.current].value.eq_ignore_ascii_case(name)
+    }
+
+    /// Check if the current token is the given delimiter, e.g. `.`
+    pub fn check_delim(&self, delim: char) -> bool {
+        self.check_token_type(TokenType::Delim)
+            && self.tokens[self.current].value == delim.to_string()
+    }
+
+    /// Check if the token `n` positions after the current one is the given delimiter
+    fn peek_delim(&self, n: usize, delim: char) -> bool {
+        self.peek_token_type(n) == Some(TokenType::Delim)
+            && self.tokens[self.current + n].value == delim.to_string()
+    }
+
+    /// Look at the type of the token `n` positions after the current one
+    pub fn peek_token_type(&self, n: usize) -> Option<TokenType> {
+        self.tokens
+            .get(self.current + n)
+            .map(|token| token.token_type.clone())
+    }
+
+    /// Consume the next token if it matches the given token type, or return an error describing what was expected
+    pub fn expect_token(
+        &mut self,
+        token_type: TokenType,
+        code: DiagnosticCode,
+        message: &str,
+    ) -> Result<Rc<Token>, ParseError> {
+        self.consume_next_token(token_type)
+            .ok_or_else(|| self.error_at_current(code, message))
+    }
+
+    /// An error spanning the current token, or the end of input
+    pub fn error_at_current(&self, code: DiagnosticCode, message: &str) -> ParseError {
+        ParseError::new(code, message, self.current_token().span)
+    }
+
+    /// The span from the given byte offset to the end of the previous token
+    pub fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.previous_token().span.end)
+    }
+
+    /// Check if the current token matches any of the given token types
+    pub fn match_token(&mut self, token_types: &[TokenType]) -> bool {
+        for token_type in token_types {
+            if self.check_token_type(token_type.clone()) {
+                self.advance();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Advance the current token index and return the previous token
+    pub fn advance(&mut self) -> Rc<Token> {
+        if !self.is_end() {
+            self.current += 1;
+        }
+        self.previous_token()
+    }
+
+    // Consume the next token if it matches the given token type
+    pub fn consume_next_token(&mut self, token_type: TokenType) -> Option<Rc<Token>> {
+        if self.check_token_type(token_type) {
+            return Some(self.advance());
+        }
+        None
+    }
+
+    /// Check if the current token is of the given type
+    pub fn check_token_type(&self, token_type: TokenType) -> bool {
+        if self.is_end() {
+            return false;
+        }
+        self.tokens[self.current].token_type == token_type
+    }
+
+    pub fn current_token(&self) -> Rc<Token> {
+        Rc::new(self.tokens[self.current].clone())
+    }
+
+    pub fn previous_token(&self) -> Rc<Token> {
+        Rc::new(self.tokens[self.current.saturating_sub(1)].clone())
+    }
+
+    pub fn next_token(&self) -> Rc<Token> {
+        Rc::new(self.tokens[(self.current + 1).min(self.tokens.len() - 1)].clone())
+    }
+
+    pub fn is_end(&self) -> bool {
+        self.current_token().token_type == TokenType::Eof
+    }
+}
+
+/// The at-keywords of the [margin rules](https://www.w3.org/TR/css-page-3/#margin-at-rules) allowed in `@page`
+const PAGE_MARGIN_RULES: [&str; 16] = [
+    "top-left-corner",
+    "top-left",
+    "top-center",
+    "top-right",
+    "top-right-corner",
+    "bottom-left-corner",
+    "bottom-left",
+    "bottom-center",
+    "bottom-right",
+    "bottom-right-corner",
+    "left-top",
+    "left-middle",
+    "left-bottom",
+    "right-top",
+    "right-middle",
+    "right-bottom",
+];
+
+/// The [data type names](https://www.w3.org/TR/css-properties-values-api-1/#supported-names) allowed in `@property` syntax
+const SYNTAX_DATA_TYPES: [&str; 15] = [
+    "angle",
+    "color",
+    "custom-ident",
+    "image",
+    "integer",
+    "length",
+    "length-percentage",
+    "number",
+    "percentage",
+    "resolution",
+    "string",
+    "time",
+    "transform-function",
+    "transform-list",
+    "url",
+];
+
+// <syntax> ::= "*" | <syntax-component> [ "|" <syntax-component> ]*
+// <syntax-component> ::= [ "<" <data-type-name> ">" | <ident> ] [ "+" | "#" ]?
+/// Parse the contents of the `syntax` descriptor of `@property`, returning `None` when it is invalid
+pub(crate) fn parse_property_syntax(syntax: &str) -> Option<PropertySyntax> {
+    let syntax = syntax.trim();
+    if syntax == "*" {
+        return Some(PropertySyntax::Universal);
+    }
+    let mut components = Vec::new();
+    for component in syntax.split('|') {
+        let component = component.trim();
+        let (component, multiplier) = if let Some(component) = component.strip_suffix('+') {
+            (component, Some(SyntaxMultiplier::SpaceSeparated))
+        } else if let Some(component) = component.strip_suffix('#') {
+            (component, Some(SyntaxMultiplier::CommaSeparated))
+        } else {
+            (component, None)
+        };
+        let name = if let Some(data_type) = component
+            .strip_prefix('<')
+            .and_then(|component| component.strip_suffix('>'))
+        {
+            // `<transform-list>` is already a list, so it cannot be multiplied
+            if !SYNTAX_DATA_TYPES.contains(&data_type)
+                || data_type == "transform-list" && multiplier.is_some()
+            {
+                return None;
+            }
+            SyntaxComponentName::DataType(data_type.to_string())
+        } else {
+            let is_ident = component
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '-' || c == '_')
+                && component
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            let is_reserved = [
+                "initial",
+                "inherit",
+                "unset",
+                "revert",
+                "revert-layer",
+                "default",
+            ]
+            .iter()
+            .any(|keyword| component.eq_ignore_ascii_case(keyword));
+            if !is_ident || is_reserved {
+                return None;
+            }
+            SyntaxComponentName::Ident(component.to_string())
+        };
+        components.push(SyntaxComponent { name, multiplier });
+    }
+    Some(PropertySyntax::Components(components))
+}
+
+/// The minimum and maximum number of arguments of a [math function](https://www.w3.org/TR/css-values-4/#math),
+/// or `None` if the function is not one
+fn math_function_arity(name: &str) -> Option<(usize, usize)> {
+    let arity = match name.to_ascii_lowercase().as_str() {
+        "calc" | "-webkit-calc" | "-moz-calc" => (1, 1),
+        "min" | "max" | "hypot" => (1, usize::MAX),
+        "clamp" => (3, 3),
+        // the rounding strategy and the rounding interval are optional
+        "round" => (1, 3),
+        "mod" | "rem" | "atan2" | "pow" => (2, 2),
+        "log" => (1, 2),
+        "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "sqrt" | "exp" | "abs" | "sign" => {
+            (1, 1)
+        }
+        _ => return None,
+    };
+    Some(arity)
+}
+
+/// Parse the lexeme of a number token as an integer, e.g. `+1`, rejecting numbers such as `1.5` and `1e3`
+fn parse_integer(lexeme: &str) -> Option<i32> {
+    if lexeme.contains(['.', 'e', 'E']) {
+        return None;
+    }
+    lexeme.parse().ok()
+}
+
+/// Check that a rule which must come first in a stylesheet only follows the rules allowed before it,
+/// and that a rule nested in a style rule is allowed there, returning why it is misplaced otherwise
+fn check_rule_position(
+    rule: &Rule,
+    preceding: &[Rule],
+    top_level: bool,
+    in_style_rule: bool,
+) -> Option<String> {
+    let only_preceded_by = |allowed: fn(&Rule) -> bool| top_level && preceding.iter().all(allowed);
+    // unknown at-rules such as `@tailwind base;` may be handled by a build step, so they are allowed anywhere
+    match rule {
+        Rule::Charset(_) if !(top_level && preceding.is_empty()) => {
+            Some("'@charset' must be the first rule of the stylesheet".to_string())
+        }
+        Rule::Import(_)
+            if !only_preceded_by(|rule| {
+                matches!(
+                    rule,
+                    Rule::Charset(_) | Rule::LayerStatement(_) | Rule::Import(_) | Rule::Unknown(_)
+                )
+            }) =>
+        {
+            Some(
+                "'@import' must precede all other rules except '@charset' and '@layer' statements"
+                    .to_string(),
+            )
+        }
+        Rule::Namespace(_)
+            if !only_preceded_by(|rule| {
+                matches!(
+                    rule,
+                    Rule::Charset(_)
+                        | Rule::LayerStatement(_)
+                        | Rule::Import(_)
+                        | Rule::Namespace(_)
+                        | Rule::Unknown(_)
+                )
+            }) =>
+        {
+            Some("'@namespace' must precede all other rules except '@charset', '@import' and '@layer' statements".to_string())
+        }
+        // only conditional rules can be nested in style rules
+        Rule::Keyframes(_)
+        | Rule::FontFace(_)
+        | Rule::Page(_)
+        | Rule::CounterStyle(_)
+        | Rule::Property(_)
+            if in_style_rule =>
+        {
+            Some("Only conditional rules can be nested in a style rule".to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::test_helper::parse;
+
+    #[test]
+    pub fn test_parse_basic_case() {
+        // .hoge { color: red; }
+        let tokens = vec![
+            Token {
+                token_type: TokenType::Delim,
+                lexeme: ".".to_string(),
+                value: ".".to_string(),
+                span: Span::new(0, 1),
+                line: 1,
+            },
+            Token {
+                token_type: TokenType::Ident,
+                lexeme: "hoge".to_string(),
+                value: "hoge".to_string(),
+                span: Span::new(1, 5),
+                line: 1,
+            },
+            Token {
+                token_type: TokenType::S,
+                lexeme: " ".to_string(),
+                value: " ".to_string(),
+                span: Span::new(5, 6),
+                line: 1,
+            },
+            Token {
+                token_type: TokenType::CurlyLeft,
+                lexeme: "{".to_string(),
+                value: "{".to_string(),
+                span: Span::new(6, 7),
+                line: 1,
+            },
+            Token {
+                token_type: TokenType::S,
+                lexeme: "\n".to_string(),
+                value: "\n".to_string(),
+                span: Span::new(7, 8),
+                line: 1,
+            },
+            Token {
+                token_type: TokenType::Ident,
+                lexeme: "color".to_string(),
+                value: "color".to_string(),
+                span: Span::new(8, 13),
+                line: 2,
+            },
+            Token {
+                token_type: TokenType::Colon,
+                lexeme: ":".to_string(),
+                value: ":".to_string(),
+                span: Span::new(13, 14),
+                line: 2,
+            },
+            Token {
+                token_type: TokenType::S,
+                lexeme: " ".to_string(),
+                value: " ".to_string(),
+                span: Span::new(14, 15),
+                line: 2,
+            },
+            Token {
+                token_type: TokenType::Ident,
+                lexeme: "red".to_string(),
+                value: "red".to_string(),
+                span: Span::new(15, 18),
+                line: 2,
+            },
+            Token {
+                token_type: TokenType::Semicolon,
+                lexeme: ";".to_string(),
+                value: ";".to_string(),
+                span: Span::new(18, 19),
+                line: 2,
+            },
+            Token {
+                token_type: TokenType::S,
+                lexeme: "\n".to_string(),
+                value: "\n".to_string(),
+                span: Span::new(19, 20),
+                line: 2,
+            },
+            Token {
+                token_type: TokenType::CurlyRight,
+                lexeme: "}".to_string(),
+                value: "}".to_string(),
+                span: Span::new(20, 21),
+                line: 3,
+            },
+            Token {
+                token_type: TokenType::Eof,
+                lexeme: "".to_string(),
+                value: "".to_string(),
+                span: Span::new(21, 21),
+                line: 3,
+            },
+        ];
+        let mut parser = Parser::new(tokens);
+        let (stylesheet, diagnostics) = parser.parse();
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    pub fn test_parse_spans() {
+        let source = ".a > b:hover {\n  color: red;\n}";
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.build();
+        let mut parser = Parser::new(lexer.tokens());
+        let (stylesheet, _) = parser.parse();
+
+        let Rule::StyleRule(rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(&source[rule.span.start..rule.span.end], source);
+        let selector = &rule.selectors[0];
+        assert_eq!(
+            &source[selector.span.start..selector.span.end],
+            ".a > b:hover"
+        );
+        let spans: Vec<&str> = selector
+            .compounds
+            .iter()
+            .flat_map(|compound| {
+                let type_span = compound.type_selector.iter().map(|selector| selector.span);
+                type_span.chain(
+                    compound
+                        .subclass_selectors
+                        .iter()
+                        .map(|selector| selector.span()),
+                )
+            })
+            .map(|span| &source[span.start..span.end])
+            .collect();
+        assert_eq!(spans, vec![".a", "b", ":hover"]);
+
+        let declaration = &rule.declarations[0];
+        assert_eq!(
+            &source[declaration.span.start..declaration.span.end],
+            "color: red;"
+        );
+        assert_eq!(
+            &source[declaration.value.span.start..declaration.value.span.end],
+            "red"
+        );
+        let location = lexer.line_index().location(declaration.span);
+        assert_eq!(location.start.line, 2);
+        assert_eq!(location.start.column, 3);
+    }
+
+    #[test]
+    pub fn test_parse_errors_are_diagnostics() {
+        // (source, expected error, source text of the error span, rules kept after recovery)
+        let cases = [
+            (
+                ".a; color: red; }",
+                DiagnosticCode::ExpectedCurlyLeft,
+                ";",
+                0,
+            ),
+            (
+                ".a { color: red; ",
+                DiagnosticCode::ExpectedCurlyRight,
+                "",
+                1,
+            ),
+            ("{ color: red; }", DiagnosticCode::ExpectedSelector, "{", 0),
+            (".a { : red; }", DiagnosticCode::ExpectedProperty, ":", 1),
+            (".a { color red; }", DiagnosticCode::ExpectedColon, "red", 1),
+            (".a { color: ; }", DiagnosticCode::ExpectedValue, ";", 1),
+            (
+                ".a { 1px: red; }",
+                DiagnosticCode::ExpectedProperty,
+                "1px",
+                1,
+            ),
+            (
+                ".a { @font-face {} }",
+                DiagnosticCode::MisplacedAtRule,
+                "@font-face",
+                1,
+            ),
+        ];
+        for (source, code, lexeme, rules) in cases {
+            let (stylesheet, diagnostics) = parse(source);
+            assert_eq!(stylesheet.rules.len(), rules, "{}", source);
+            assert_eq!(diagnostics[0].code, code, "{}", source);
+            assert_eq!(diagnostics[0].severity, Severity::Error);
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], lexeme, "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_recovers_from_errors() {
+        let source = r#"
+            .a { color: red; width: ; height: 10px }
+            .b > { color: blue; }
+            .c { @keyframes spin {} color: green; }
+            .d { color; margin: 0; }
+        "#;
+        let (stylesheet, diagnostics) = parse(source);
+
+        let rules: Vec<(String, Vec<String>)> = stylesheet
+            .rules
+            .iter()
+            .map(|rule| {
+                let Rule::StyleRule(rule) = rule else {
+                    panic!("expected style rule");
+                };
+                let properties = rule
+                    .declarations
+                    .iter()
+                    .map(|declaration| declaration.property.clone())
+                    .collect();
+                (rule.selector_text.clone(), properties)
+            })
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                (
+                    ".a".to_string(),
+                    vec!["color".to_string(), "height".to_string()]
+                ),
+                (".c".to_string(), vec!["color".to_string()]),
+                (".d".to_string(), vec!["margin".to_string()]),
+            ]
+        );
+
+        let diagnostics: Vec<(DiagnosticCode, Severity, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.code,
+                    diagnostic.severity,
+                    &source[diagnostic.span.start..diagnostic.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (DiagnosticCode::ExpectedValue, Severity::Error, ";"),
+                (
+                    DiagnosticCode::IgnoredDeclaration,
+                    Severity::Warning,
+                    "width: ;"
+                ),
+                (DiagnosticCode::ExpectedSelector, Severity::Error, "{"),
+                (
+                    DiagnosticCode::IgnoredRule,
+                    Severity::Warning,
+                    ".b > { color: blue; }"
+                ),
+                (
+                    DiagnosticCode::MisplacedAtRule,
+                    Severity::Error,
+                    "@keyframes"
+                ),
+                (
+                    DiagnosticCode::IgnoredRule,
+                    Severity::Warning,
+                    "@keyframes spin {}"
+                ),
+                (DiagnosticCode::ExpectedColon, Severity::Error, ";"),
+                (
+                    DiagnosticCode::IgnoredDeclaration,
+                    Severity::Warning,
+                    "color;"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_never_panics() {
+        let sources = [
+            "", "}", ".", ":", "::", "a,", "a >", "#", "a{b", "a{b:", "a{b:c", "a{(((", "@x",
+            "a{@x",
+        ];
+        for source in sources {
+            parse(source);
+        }
+        assert!(Parser::new(vec![]).parse().0.rules.is_empty());
+    }
+
+    #[test]
+    pub fn test_parse_media_rule() {
+        let source = "@media screen and (max-width: 600px) { .a { color: red; } @media print {} }";
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+
+        let Rule::Media(media_rule) = &stylesheet.rules[0] else {
+            panic!("expected media rule");
+        };
+        assert_eq!(&source[media_rule.span.start..media_rule.span.end], source);
+        assert_eq!(
+            media_rule.query_list.to_string(),
+            "screen and (max-width: 600px)"
+        );
+        let query = &media_rule.query_list.queries[0];
+        assert_eq!(query.media_type, Some("screen".to_string()));
+        let Some(MediaCondition::Feature(feature)) = &query.condition else {
+            panic!("expected media feature");
+        };
+        assert_eq!(
+            &source[feature.span.start..feature.span.end],
+            "(max-width: 600px)"
+        );
+        assert_eq!(media_rule.rules.len(), 2);
+        assert!(matches!(media_rule.rules[0], Rule::StyleRule(_)));
+        assert!(matches!(media_rule.rules[1], Rule::Media(_)));
+    }
+
+    #[test]
+    pub fn test_parse_media_queries() {
+        // (prelude, serialized query list)
+        let cases = [
+            ("screen", "screen"),
+            ("not print", "not print"),
+            (
+                "only screen and (min-width:600px) and (orientation: landscape)",
+                "only screen and (min-width: 600px) and (orientation: landscape)",
+            ),
+            ("screen, print", "screen, print"),
+            ("(hover)", "(hover)"),
+            ("(width>=600px)", "(width >= 600px)"),
+            ("(600px <= width)", "(600px <= width)"),
+            ("(400px <= width < 800px)", "(400px <= width < 800px)"),
+            ("(aspect-ratio: 16 / 9)", "(aspect-ratio: 16/9)"),
+            ("not (color)", "not (color)"),
+            ("(not (color)) or (hover)", "(not (color)) or (hover)"),
+            ("((color) and (hover))", "(color) and (hover)"),
+            // anything else in parentheses is kept as <general-enclosed>
+            ("(unknown thing)", "(unknown thing)"),
+            ("(400px > width < 800px)", "(400px > width < 800px)"),
+            ("foo(bar) or (hover)", "foo(bar) or (hover)"),
+        ];
+        for (prelude, expected) in cases {
+            let source = format!("@media {} {{}}", prelude);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(diagnostics.is_empty(), "{}", prelude);
+            let Rule::Media(media_rule) = &stylesheet.rules[0] else {
+                panic!("expected media rule");
+            };
+            assert_eq!(media_rule.query_list.to_string(), expected);
+        }
+
+        let Rule::Media(media_rule) = &parse("@media (400px < width <= 800px) {}").0.rules[0]
+        else {
+            panic!("expected media rule");
+        };
+        let Some(MediaCondition::Feature(feature)) = &media_rule.query_list.queries[0].condition
+        else {
+            panic!("expected media feature");
+        };
+        let MediaFeatureKind::Range { name, start, end } = &feature.kind else {
+            panic!("expected range feature");
+        };
+        assert_eq!(name, "width");
+        assert!(matches!(start, Some((_, MediaComparison::Lt))));
+        assert!(matches!(end, Some((MediaComparison::Le, _))));
+    }
+
+    #[test]
+    pub fn test_parse_invalid_media_queries() {
+        // an invalid query becomes `not all` without affecting the rest of the list or the rule
+        let cases = [
+            ("screen and", "not all"),
+            ("screen foo, print", "not all, print"),
+            ("only (color)", "not all"),
+            ("(color) and (hover) or (pointer)", "not all"),
+        ];
+        for (prelude, expected) in cases {
+            let source = format!("@media {} {{ .a {{ color: red; }} }}", prelude);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert_eq!(diagnostics.len(), 1, "{}", prelude);
+            assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidMediaQuery);
+            let Rule::Media(media_rule) = &stylesheet.rules[0] else {
+                panic!("expected media rule");
+            };
+            assert_eq!(media_rule.query_list.to_string(), expected);
+            assert_eq!(media_rule.rules.len(), 1);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_supports_rule() {
+        // (prelude, serialized condition)
+        let cases = [
+            ("(display: grid)", "(display: grid)"),
+            ("(display:grid)", "(display: grid)"),
+            (
+                "(transform-origin: 5% 5%) and (not (display: flex))",
+                "(transform-origin: 5% 5%) and (not (display: flex))",
+            ),
+            (
+                "((display: grid) or (display: flex))",
+                "(display: grid) or (display: flex)",
+            ),
+            ("not selector(nav a)", "not selector(nav a)"),
+            ("selector(:focus-visible)", "selector(:focus-visible)"),
+            // anything else in parentheses is kept as <general-enclosed>
+            ("font-tech(color-COLRv1)", "font-tech(color-COLRv1)"),
+            (
+                "(unknown thing) or (color: red)",
+                "(unknown thing) or (color: red)",
+            ),
+            ("selector(a, b)", "selector(a, b)"),
+        ];
+        for (prelude, expected) in cases {
+            let source = format!("@supports {} {{ .a {{ color: red; }} }}", prelude);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(diagnostics.is_empty(), "{}", prelude);
+            let Rule::Supports(supports_rule) = &stylesheet.rules[0] else {
+                panic!("expected supports rule");
+            };
+            assert_eq!(supports_rule.condition.to_string(), expected);
+            assert_eq!(supports_rule.rules.len(), 1);
+        }
+
+        let source = "@supports (display: grid) and selector(a) { @media print {} }";
+        let Rule::Supports(supports_rule) = &parse(source).0.rules[0] else {
+            panic!("expected supports rule");
+        };
+        assert_eq!(
+            &source[supports_rule.span.start..supports_rule.span.end],
+            source
+        );
+        let SupportsCondition::And(conditions) = &supports_rule.condition else {
+            panic!("expected and");
+        };
+        let SupportsCondition::Declaration(declaration) = &conditions[0] else {
+            panic!("expected declaration");
+        };
+        assert_eq!(declaration.property, "display");
+        assert_eq!(declaration.value, "grid");
+        assert_eq!(
+            &source[declaration.span.start..declaration.span.end],
+            "(display: grid)"
+        );
+        assert!(matches!(conditions[1], SupportsCondition::Selector(..)));
+        assert!(matches!(supports_rule.rules[0], Rule::Media(_)));
+    }
+
+    #[test]
+    pub fn test_parse_invalid_supports_conditions() {
+        // an invalid condition drops the whole rule
+        let cases = [
+            ("display: grid", "display"),
+            ("(display: grid) and (color: red) or (hover: none)", "or"),
+            ("not", "{"),
+            ("(display: grid) foo", "foo"),
+        ];
+        for (prelude, lexeme) in cases {
+            let source = format!("@supports {} {{ .a {{ color: red; }} }} .b {{}}", prelude);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert_eq!(stylesheet.rules.len(), 1, "{}", prelude);
+            assert_eq!(
+                diagnostics[0].code,
+                DiagnosticCode::InvalidSupportsCondition,
+                "{}",
+                prelude
+            );
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], lexeme, "{}", prelude);
+            assert_eq!(diagnostics[1].code, DiagnosticCode::IgnoredRule);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_keyframes_rule() {
+        let source = r#"
+            @keyframes fade {
+                from { opacity: 0; }
+                50%, 75.5% { opacity: 0.5; }
+                TO { opacity: 1; }
+            }
+            @-webkit-keyframes "spin" { to { transform: none; } }
+        "#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+
+        let Rule::Keyframes(fade) = &stylesheet.rules[0] else {
+            panic!("expected keyframes rule");
+        };
+        assert_eq!(fade.name, "fade");
+        assert_eq!(fade.prefix, None);
+        let selectors: Vec<Vec<String>> = fade
+            .keyframes
+            .iter()
+            .map(|keyframe| {
+                keyframe
+                    .selectors
+                    .iter()
+                    .map(|selector| selector.to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            selectors,
+            vec![
+                vec!["from".to_string()],
+                vec!["50%".to_string(), "75.5%".to_string()],
+                vec!["to".to_string()],
+            ]
+        );
+        let selector = &fade.keyframes[1].selectors[1];
+        assert_eq!(selector.kind.percentage(), 75.5);
+        assert_eq!(&source[selector.span.start..selector.span.end], "75.5%");
+        assert_eq!(fade.keyframes[2].declarations[0].property, "opacity");
+
+        let Rule::Keyframes(spin) = &stylesheet.rules[1] else {
+            panic!("expected keyframes rule");
+        };
+        assert_eq!(spin.name, "spin");
+        assert_eq!(spin.prefix, Some("-webkit-".to_string()));
+        assert_eq!(spin.keyframes[0].selectors[0].kind.percentage(), 100.0);
+    }
+
+    #[test]
+    pub fn test_parse_invalid_keyframes() {
+        let source =
+            "@keyframes none { to {} } @keyframes a { 150% { top: 0; } from { top: 1px; } }";
+        let (stylesheet, diagnostics) = parse(source);
+        assert_eq!(stylesheet.rules.len(), 1);
+        let Rule::Keyframes(keyframes_rule) = &stylesheet.rules[0] else {
+            panic!("expected keyframes rule");
+        };
+        assert_eq!(keyframes_rule.keyframes.len(), 1);
+
+        let diagnostics: Vec<(DiagnosticCode, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.code,
+                    &source[diagnostic.span.start..diagnostic.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (DiagnosticCode::ExpectedKeyframesName, "none"),
+                (DiagnosticCode::IgnoredRule, "@keyframes none { to {} }"),
+                (DiagnosticCode::InvalidKeyframeSelector, "150%"),
+                (DiagnosticCode::IgnoredRule, "150% { top: 0; }"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_descriptor_rules() {
+        let source = r#"
+            @font-face { font-family: "Inter"; font-display: swap; }
+            @page toc, :first:left { margin: 1in; @top-center { content: "TOC"; } }
+            @page { size: A4; }
+            @counter-style thumbs { system: cyclic; suffix: " "; }
+        "#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+
+        let Rule::FontFace(font_face_rule) = &stylesheet.rules[0] else {
+            panic!("expected font-face rule");
+        };
+        assert_eq!(
+            font_face_rule.descriptors[0].css_text,
+            "font-family: \"Inter\";"
+        );
+
+        let Rule::Page(page_rule) = &stylesheet.rules[1] else {
+            panic!("expected page rule");
+        };
+        let selectors: Vec<String> = page_rule
+            .selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect();
+        assert_eq!(selectors, vec!["toc", ":first:left"]);
+        assert_eq!(page_rule.descriptors[0].property, "margin");
+        assert_eq!(page_rule.margin_rules[0].name, "top-center");
+        assert_eq!(page_rule.margin_rules[0].descriptors[0].property, "content");
+        let Rule::Page(page_rule) = &stylesheet.rules[2] else {
+            panic!("expected page rule");
+        };
+        assert!(page_rule.selectors.is_empty());
+
+        let Rule::CounterStyle(counter_style_rule) = &stylesheet.rules[3] else {
+            panic!("expected counter-style rule");
+        };
+        assert_eq!(counter_style_rule.name, "thumbs");
+        assert_eq!(counter_style_rule.descriptors.len(), 2);
+
+        let (_, diagnostics) =
+            parse("@page :middle {} @counter-style decimal {} @page { @bottom {} }");
+        let codes: Vec<DiagnosticCode> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                DiagnosticCode::InvalidPageSelector,
+                DiagnosticCode::ExpectedCounterStyleName,
+                DiagnosticCode::UnsupportedAtRule,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_property_rule() {
+        let source = r#"@property --gap { syntax: "<length>+ | auto"; inherits: FALSE; initial-value: 0px; }"#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+        let Rule::Property(property_rule) = &stylesheet.rules[0] else {
+            panic!("expected property rule");
+        };
+        assert_eq!(property_rule.name, "--gap");
+        assert!(property_rule.is_valid());
+        assert_eq!(property_rule.inherits, Some(false));
+        assert_eq!(
+            property_rule.syntax,
+            Some(PropertySyntax::Components(vec![
+                SyntaxComponent {
+                    name: SyntaxComponentName::DataType("length".to_string()),
+                    multiplier: Some(SyntaxMultiplier::SpaceSeparated),
+                },
+                SyntaxComponent {
+                    name: SyntaxComponentName::Ident("auto".to_string()),
+                    multiplier: None,
+                },
+            ]))
+        );
+
+        // (source, source text of each error)
+        let cases = [
+            (
+                r#"@property gap { syntax: "*"; inherits: true; }"#,
+                vec!["gap"],
+            ),
+            (
+                r#"@property --a { syntax: "<size>"; inherits: true; initial-value: 0; }"#,
+                vec![r#""<size>""#],
+            ),
+            (
+                r#"@property --a { syntax: "<transform-list>#"; inherits: yes; initial-value: none; }"#,
+                vec![r#""<transform-list>#""#, "yes"],
+            ),
+            (
+                r#"@property --a { syntax: <length>; }"#,
+                vec!["<length>", "--a"],
+            ),
+            (
+                r#"@property --a { syntax: "<color>"; inherits: false; }"#,
+                vec!["--a"],
+            ),
+            (
+                r#"@property --a { syntax: '<length>'; inherits: false; initial-value: red }"#,
+                vec!["red"],
+            ),
+            (
+                r#"@property --a { initial-value: 1em; syntax: '<length>'; inherits: false; }"#,
+                vec!["1em"],
+            ),
+            (
+                r#"@property --a { syntax: '<length>'; inherits: false; initial-value: var(--b) }"#,
+                vec!["var(--b)"],
+            ),
+        ];
+        for (source, expected) in cases {
+            let (stylesheet, diagnostics) = parse(source);
+            let errors: Vec<&str> = diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                .map(|diagnostic| &source[diagnostic.span.start..diagnostic.span.end])
+                .collect();
+            assert_eq!(errors, expected, "{}", source);
+            if let Some(Rule::Property(property_rule)) = stylesheet.rules.first() {
+                assert!(!property_rule.is_valid(), "{}", source);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_parse_layer_rules() {
+        let source = "@layer reset, components.card; @layer base { .a { color: red; } } @layer { }";
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+
+        let Rule::LayerStatement(layer_statement_rule) = &stylesheet.rules[0] else {
+            panic!("expected layer statement");
+        };
+        let names: Vec<String> = layer_statement_rule
+            .names
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(names, vec!["reset", "components.card"]);
+        assert_eq!(
+            layer_statement_rule.names[1].names,
+            vec!["components", "card"]
+        );
+        let span = layer_statement_rule.span;
+        assert_eq!(
+            &source[span.start..span.end],
+            "@layer reset, components.card;"
+        );
+
+        let Rule::LayerBlock(layer_block_rule) = &stylesheet.rules[1] else {
+            panic!("expected layer block");
+        };
+        assert_eq!(layer_block_rule.name.as_ref().unwrap().to_string(), "base");
+        assert_eq!(layer_block_rule.rules.len(), 1);
+        let Rule::LayerBlock(layer_block_rule) = &stylesheet.rules[2] else {
+            panic!("expected layer block");
+        };
+        assert!(layer_block_rule.name.is_none());
+
+        // (source, source text of the error)
+        let cases = [
+            ("@layer;", ";"),
+            ("@layer a, b { }", "a, b"),
+            ("@layer a b;", "b"),
+            ("@layer inherit;", "inherit"),
+            ("@layer a. b;", "."),
+        ];
+        for (source, lexeme) in cases {
+            let (stylesheet, diagnostics) = parse(&format!("{} .x {{}}", source));
+            assert_eq!(stylesheet.rules.len(), 1, "{}", source);
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], lexeme, "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_import_rule() {
+        let source = r#"@import url("theme.css") layer(base.theme) supports(display: grid) screen and (min-width: 600px), print;"#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+        let Rule::Import(import_rule) = &stylesheet.rules[0] else {
+            panic!("expected import rule");
+        };
+        assert_eq!(
+            &source[import_rule.span.start..import_rule.span.end],
+            source
+        );
+        assert_eq!(import_rule.url, "theme.css");
+        let url_span = import_rule.url_span;
+        assert_eq!(&source[url_span.start..url_span.end], "url(\"theme.css\")");
+        let Some(ImportLayer::Named(layer)) = &import_rule.layer else {
+            panic!("expected named layer");
+        };
+        assert_eq!(layer.to_string(), "base.theme");
+        assert_eq!(
+            import_rule.supports.as_ref().unwrap().to_string(),
+            "(display: grid)"
+        );
+        assert_eq!(
+            import_rule.media.to_string(),
+            "screen and (min-width: 600px), print"
+        );
+
+        // (source, url, is anonymous layer, supports)
+        let cases = [
+            ("@import 'a.css';", "a.css", false, None),
+            ("@import url(b.css) layer;", "b.css", true, None),
+            (
+                "@import \"c.css\" supports(not (display: grid));",
+                "c.css",
+                false,
+                Some("not (display: grid)"),
+            ),
+        ];
+        for (source, url, anonymous, supports) in cases {
+            let (stylesheet, diagnostics) = parse(source);
+            assert!(diagnostics.is_empty(), "{}", source);
+            let Rule::Import(import_rule) = &stylesheet.rules[0] else {
+                panic!("expected import rule");
+            };
+            assert_eq!(import_rule.url, url);
+            assert_eq!(
+                matches!(import_rule.layer, Some(ImportLayer::Anonymous(_))),
+                anonymous
+            );
+            assert_eq!(
+                import_rule
+                    .supports
+                    .as_ref()
+                    .map(|supports| supports.to_string())
+                    .as_deref(),
+                supports
+            );
+            assert!(import_rule.media.queries.is_empty());
+        }
+
+        let (stylesheet, diagnostics) = parse("@import foo; .a {}");
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::ExpectedUrl);
+    }
+
+    #[test]
+    pub fn test_parse_container_rule() {
+        // (prelude, name, serialized query)
+        let cases = [
+            (
+                "card (min-width: 400px)",
+                Some("card"),
+                Some("(min-width: 400px)"),
+            ),
+            ("(inline-size>30em)", None, Some("(inline-size > 30em)")),
+            ("sidebar", Some("sidebar"), None),
+            (
+                "card not ((width < 400px) or (orientation: portrait))",
+                Some("card"),
+                Some("not ((width < 400px) or (orientation: portrait))"),
+            ),
+            ("style(--theme: dark)", None, Some("style(--theme: dark)")),
+            (
+                "card (width > 0) and style((--a) and (not (--b:  1px 2px )))",
+                Some("card"),
+                Some("(width > 0) and style((--a) and (not (--b: 1px 2px)))"),
+            ),
+            // anything else is kept as <general-enclosed>
+            (
+                "scroll-state(stuck: top) or style(--a:)",
+                None,
+                Some("scroll-state(stuck: top) or style(--a:)"),
+            ),
+        ];
+        for (prelude, name, query) in cases {
+            let source = format!("@container {} {{ .a {{ color: red; }} }}", prelude);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(diagnostics.is_empty(), "{}", prelude);
+            let Rule::Container(container_rule) = &stylesheet.rules[0] else {
+                panic!("expected container rule");
+            };
+            let condition = &container_rule.condition;
+            assert_eq!(condition.name.as_deref(), name, "{}", prelude);
+            assert_eq!(
+                condition
+                    .query
+                    .as_ref()
+                    .map(|query| query.to_string())
+                    .as_deref(),
+                query,
+                "{}",
+                prelude
+            );
+            assert_eq!(container_rule.rules.len(), 1);
+        }
+
+        let source = "@container card (width >= 400px) {}";
+        let Rule::Container(container_rule) = &parse(source).0.rules[0] else {
+            panic!("expected container rule");
+        };
+        let Some(ContainerQuery::Size(feature)) = &container_rule.condition.query else {
+            panic!("expected size feature");
+        };
+        assert_eq!(
+            &source[feature.span.start..feature.span.end],
+            "(width >= 400px)"
+        );
+
+        // (prelude, source text of the error)
+        let cases = [
+            ("", "{"),
+            ("none", "none"),
+            ("card foo", "foo"),
+            ("a (width) b", "b"),
+        ];
+        for (prelude, lexeme) in cases {
+            let source = format!("@container {} {{ .a {{}} }} .b {{}}", prelude);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert_eq!(stylesheet.rules.len(), 1, "{}", prelude);
+            assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidContainerQuery);
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], lexeme, "{}", prelude);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_unknown_at_rules() {
+        let source = r#"
+            @tailwind base;
+            @custom-media --narrow (max-width: 30em);
+            @scope (.card) to (.content) { img { border: 0; } }
+            @-moz-document url-prefix() { .a { color: red; } }
+            @media print { @page-x }
+            .b { color: blue; }
+        "#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(stylesheet.rules.len(), 6);
+
+        // (name, prelude, block, source text)
+        let expected = [
+            ("tailwind", "base", None, "@tailwind base;"),
+            (
+                "custom-media",
+                "--narrow (max-width: 30em)",
+                None,
+                "@custom-media --narrow (max-width: 30em);",
+            ),
+            (
+                "scope",
+                "(.card) to (.content)",
+                Some(" img { border: 0; } "),
+                "@scope (.card) to (.content) { img { border: 0; } }",
+            ),
+            (
+                "-moz-document",
+                "url-prefix()",
+                Some(" .a { color: red; } "),
+                "@-moz-document url-prefix() { .a { color: red; } }",
+            ),
+        ];
+        for (rule, (name, prelude, block, text)) in stylesheet.rules.iter().zip(expected) {
+            let Rule::Unknown(unknown_at_rule) = rule else {
+                panic!("expected unknown at-rule");
+            };
+            assert_eq!(unknown_at_rule.name, name);
+            assert_eq!(unknown_at_rule.prelude(), prelude);
+            let block_text = unknown_at_rule.block.as_ref().map(|tokens| {
+                tokens
+                    .iter()
+                    .map(|token| token.lexeme.as_str())
+                    .collect::<String>()
+            });
+            assert_eq!(block_text.as_deref(), block);
+            assert_eq!(unknown_at_rule.to_string(), text);
+            let span = unknown_at_rule.span;
+            assert_eq!(&source[span.start..span.end], text);
+        }
+
+        // the prelude can be inspected token by token, e.g. to find the name of a custom media query
+        let Rule::Unknown(custom_media) = &stylesheet.rules[1] else {
+            panic!("expected unknown at-rule");
+        };
+        let token_types: Vec<TokenType> = custom_media
+            .prelude_tokens
+            .iter()
+            .map(|token| token.token_type.clone())
+            .filter(|token_type| *token_type != TokenType::S)
+            .collect();
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Ident,
+                TokenType::ParenLeft,
+                TokenType::Ident,
+                TokenType::Colon,
+                TokenType::Dimension,
+                TokenType::ParenRight,
+            ]
+        );
+        assert_eq!(custom_media.prelude_tokens[1].value, "--narrow");
+
+        // a statement at-rule without ';' ends before the '}' of the enclosing block
+        let Rule::Media(media_rule) = &stylesheet.rules[4] else {
+            panic!("expected media rule");
+        };
+        let Rule::Unknown(unknown_at_rule) = &media_rule.rules[0] else {
+            panic!("expected unknown at-rule");
+        };
+        assert_eq!(unknown_at_rule.name, "page-x");
+        assert!(unknown_at_rule.block.is_none());
+        assert!(matches!(stylesheet.rules[5], Rule::StyleRule(_)));
+
+        let (stylesheet, diagnostics) = parse("@unknown { .a {}");
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::ExpectedCurlyRight);
+    }
+
+    #[test]
+    pub fn test_parse_charset_and_namespace_rules() {
+        let source = r#"@charset "UTF-8";
+            @layer base;
+            @import "a.css";
+            @import url(b.css);
+            @namespace url(http://www.w3.org/1999/xhtml);
+            @namespace svg "http://www.w3.org/2000/svg";
+            @import "c.css";
+            @namespace svg url(http://example.com/svg);
+            .a { color: red; }
+            @charset "UTF-8";
+            @namespace math url(http://www.w3.org/1998/Math/MathML);
+            @media print { @import "print.css"; }
+        "#;
+        let (stylesheet, diagnostics) = parse(source);
+        let Rule::Charset(charset_rule) = &stylesheet.rules[0] else {
+            panic!("expected charset rule");
+        };
+        assert_eq!(charset_rule.encoding, "UTF-8");
+
+        let imports: Vec<(&str, &str)> = stylesheet
+            .imports()
+            .iter()
+            .map(|import_rule| {
+                let span = import_rule.url_span;
+                (import_rule.url.as_str(), &source[span.start..span.end])
+            })
+            .collect();
+        assert_eq!(
+            imports,
+            vec![("a.css", "\"a.css\""), ("b.css", "url(b.css)")]
+        );
+        assert_eq!(
+            stylesheet.namespace(None),
+            Some("http://www.w3.org/1999/xhtml")
+        );
+        assert_eq!(
+            stylesheet.namespace(Some("svg")),
+            Some("http://example.com/svg")
+        );
+        assert_eq!(stylesheet.namespace(Some("math")), None);
+
+        let diagnostics: Vec<(DiagnosticCode, &str)> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| {
+                (
+                    diagnostic.code,
+                    &source[diagnostic.span.start..diagnostic.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                // an import after a namespace is ignored
+                (DiagnosticCode::MisplacedAtRule, "@import"),
+                (DiagnosticCode::MisplacedAtRule, "@charset"),
+                (DiagnosticCode::MisplacedAtRule, "@namespace"),
+                (DiagnosticCode::MisplacedAtRule, "@import"),
+            ]
+        );
+
+        // (source, error code)
+        let cases = [
+            ("@charset utf-8;", DiagnosticCode::ExpectedCharsetEncoding),
+            ("@charset \"utf-8\" x;", DiagnosticCode::ExpectedSemicolon),
+            ("@namespace svg;", DiagnosticCode::ExpectedUrl),
+            ("@namespace svg \"a\" b;", DiagnosticCode::ExpectedSemicolon),
+        ];
+        for (source, code) in cases {
+            let (stylesheet, diagnostics) = parse(source);
+            assert!(stylesheet.rules.is_empty(), "{}", source);
+            assert_eq!(diagnostics[0].code, code, "{}", source);
+        }
+
+        // unknown at-rules do not make a following import misplaced
+        let (stylesheet, diagnostics) =
+            parse("@tailwind base; @import \"x.css\"; @namespace svg url(b); .a {}");
+        assert!(diagnostics.is_empty());
+        assert_eq!(stylesheet.imports().len(), 1);
+        assert_eq!(stylesheet.namespace(Some("svg")), Some("b"));
+    }
+
+    #[test]
+    pub fn test_parse_nested_rules() {
+        let source = r#".card {
+            color: red;
+            &:hover { color: blue; }
+            > .title { margin: 0; }
+            a:hover { color: green; }
+            padding: 0;
+            @media (width > 600px) {
+                gap: 1rem;
+                & + & { margin: 0; }
+            }
+        }"#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let properties: Vec<&str> = style_rule
+            .declarations
+            .iter()
+            .map(|declaration| declaration.property.as_str())
+            .collect();
+        assert_eq!(properties, vec!["color", "padding"]);
+
+        let selectors: Vec<String> = style_rule.rules[..3]
+            .iter()
+            .map(|rule| {
+                let Rule::StyleRule(nested_rule) = rule else {
+                    panic!("expected nested style rule");
+                };
+                nested_rule.selector_text.clone()
+            })
+            .collect();
+        assert_eq!(selectors, vec!["&:hover", "> .title", "a:hover"]);
+        let Rule::StyleRule(hover_rule) = &style_rule.rules[0] else {
+            panic!("expected nested style rule");
+        };
+        let SimpleSelector::Nesting(nesting_selector) =
+            &hover_rule.selectors[0].compounds[0].subclass_selectors[0]
+        else {
+            panic!("expected nesting selector");
+        };
+        assert_eq!(
+            &source[nesting_selector.span.start..nesting_selector.span.end],
+            "&"
+        );
+
+        // declarations directly in a nested conditional rule are wrapped in a `&` rule
+        let Rule::Media(media_rule) = &style_rule.rules[3] else {
+            panic!("expected media rule");
+        };
+        let Rule::StyleRule(nested_declarations) = &media_rule.rules[0] else {
+            panic!("expected nested declarations");
+        };
+        assert_eq!(nested_declarations.selector_text, "&");
+        assert_eq!(
+            &source[nested_declarations.span.start..nested_declarations.span.end],
+            "gap: 1rem;"
+        );
+        let Rule::StyleRule(sibling_rule) = &media_rule.rules[1] else {
+            panic!("expected nested style rule");
+        };
+        assert_eq!(sibling_rule.selector_text, "& + &");
+
+        // (source, error code, source text of the error)
+        let cases = [
+            ("> .a {}", DiagnosticCode::ExpectedSelector, ">"),
+            (
+                ".a { @counter-style x {} }",
+                DiagnosticCode::MisplacedAtRule,
+                "@counter-style",
+            ),
+            (
+                ".a { .b { color red; } }",
+                DiagnosticCode::ExpectedColon,
+                "red",
+            ),
+            (".a { .b > { } }", DiagnosticCode::ExpectedSelector, "{"),
+        ];
+        for (source, code, lexeme) in cases {
+            let (_, diagnostics) = parse(source);
+            assert_eq!(diagnostics[0].code, code, "{}", source);
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], lexeme, "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_combinators() {
+        // (selector, serialized, combinators between the compounds)
+        let cases = [
+            ("div span", "div span", vec![Combinator::Descendant]),
+            ("div>span", "div > span", vec![Combinator::Child]),
+            (
+                "h1 + p ~ ul",
+                "h1 + p ~ ul",
+                vec![Combinator::NextSibling, Combinator::SubsequentSibling],
+            ),
+            (
+                "col.selected||td",
+                "col.selected || td",
+                vec![Combinator::Column],
+            ),
+            (
+                "ul  li.active\n a:hover",
+                "ul li.active a:hover",
+                vec![Combinator::Descendant, Combinator::Descendant],
+            ),
+            (".a.b", ".a.b", vec![]),
+        ];
+        for (selector, serialized, combinators) in cases {
+            let source = format!("{} {{}}", selector);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(diagnostics.is_empty(), "{}", selector);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            let parsed = &style_rule.selectors[0];
+            assert_eq!(parsed.to_string(), serialized);
+            assert_eq!(&source[parsed.span.start..parsed.span.end], selector);
+            assert_eq!(parsed.compounds[0].combinator, None);
+            let parsed_combinators: Vec<Combinator> = parsed.compounds[1..]
+                .iter()
+                .map(|compound| compound.combinator.unwrap())
+                .collect();
+            assert_eq!(parsed_combinators, combinators, "{}", selector);
+        }
+
+        let (stylesheet, _) = parse("a > b, a b {}");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(style_rule.selector_text, "a > b, a b");
+
+        let (_, diagnostics) = parse("a > > b {} a | b {}");
+        assert_eq!(diagnostics[0].code, DiagnosticCode::ExpectedSelector);
+        assert_eq!(diagnostics[2].code, DiagnosticCode::ExpectedCurlyLeft);
+    }
+
+    #[test]
+    pub fn test_parse_compound_selectors() {
+        let source = "a.btn#main:hover::before {}";
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty());
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let compounds = &style_rule.selectors[0].compounds;
+        assert_eq!(compounds.len(), 1);
+        let type_selector = compounds[0].type_selector.as_ref().unwrap();
+        assert_eq!(type_selector.element, "a");
+        let subclass_selectors: Vec<String> = compounds[0]
+            .subclass_selectors
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect();
+        assert_eq!(
+            subclass_selectors,
+            vec![".btn", "#main", ":hover", "::before"]
+        );
+        assert_eq!(compounds[0].to_string(), "a.btn#main:hover::before");
+
+        // (source, the rejected selector)
+        let cases = [
+            (".a { &div {} }", "div"),
+            ("p::before.a {}", ".a"),
+            ("p::before#a {}", "#a"),
+        ];
+        for (source, rejected) in cases {
+            let (_, diagnostics) = parse(source);
+            assert_eq!(
+                diagnostics[0].code,
+                DiagnosticCode::InvalidSelector,
+                "{}",
+                source
+            );
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], rejected);
+        }
+        let (_, diagnostics) = parse("p::before:hover, p::after::marker {}");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    pub fn test_parse_attribute_selectors() {
+        // (selector, serialized)
+        let cases = [
+            ("[disabled]", "[disabled]"),
+            ("[ data-state = \"open\" ]", "[data-state=\"open\"]"),
+            ("a[href^='https:']", "a[href^=\"https:\"]"),
+            ("[href$=\".pdf\" i]", "[href$=\".pdf\" i]"),
+            ("[class*=btn]", "[class*=btn]"),
+            ("[class~=active S]", "[class~=active s]"),
+            ("[lang|=en]", "[lang|=en]"),
+            ("[xlink|href]", "[xlink|href]"),
+            ("[*|href]", "[*|href]"),
+            ("[|href=a]", "[|href=a]"),
+            (".a[data-x][data-y]:hover", ".a[data-x][data-y]:hover"),
+        ];
+        for (selector, serialized) in cases {
+            let (stylesheet, diagnostics) = parse(&format!("{} {{}}", selector));
+            assert!(diagnostics.is_empty(), "{}: {:?}", selector, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            assert_eq!(style_rule.selector_text, serialized);
+        }
+
+        let (stylesheet, _) = parse("[data-state='open' i] {}");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let SimpleSelector::Attribute(attribute_selector) =
+            &style_rule.selectors[0].compounds[0].subclass_selectors[0]
+        else {
+            panic!("expected attribute selector");
+        };
+        assert_eq!(attribute_selector.namespace, None);
+        assert_eq!(attribute_selector.name, "data-state");
+        let matcher = attribute_selector.matcher.as_ref().unwrap();
+        assert_eq!(matcher.operator, AttributeOperator::Equals);
+        assert_eq!(matcher.value, "open");
+        assert!(matcher.quoted);
+        assert_eq!(matcher.modifier, Some(AttributeModifier::CaseInsensitive));
+
+        // (source, the token the error is reported at)
+        let cases = [
+            ("[] {}", "]"),
+            ("[a=] {}", "]"),
+            ("[a=1] {}", "1"),
+            ("[a=b x] {}", "x"),
+            ("[a b] {}", "b"),
+            ("[a {}", "{"),
+        ];
+        for (source, at) in cases {
+            let (_, diagnostics) = parse(source);
+            assert_eq!(
+                diagnostics[0].code,
+                DiagnosticCode::InvalidSelector,
+                "{}",
+                source
+            );
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], at, "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_functional_pseudo_classes() {
+        // (selector, serialized)
+        let cases = [
+            (":not(.a)", ":not(.a)"),
+            ("li:not( .a , #b )", "li:not(.a, #b)"),
+            (":is(ul, ol) > li", ":is(ul, ol) > li"),
+            (":where(.a .b)", ":where(.a .b)"),
+            ("a:has(> img, + p)", "a:has(> img, + p)"),
+            (":has(.a)", ":has(.a)"),
+            (":not(:is(.a, .b))", ":not(:is(.a, .b))"),
+            (":lang(en)", ":lang(en)"),
+            ("p:before", "p:before"),
+            ("li:nth-child(odd)", "li:nth-child(2n+1)"),
+            (
+                "li:nth-child(even of .item, .x)",
+                "li:nth-child(2n of .item, .x)",
+            ),
+            ("li:nth-of-type(3)", "li:nth-of-type(3)"),
+            (":host(.dark)", ":host(.dark)"),
+            // invalid selectors in :is() and :where() are dropped
+            (":is(.a,)", ":is(.a)"),
+            (":is()", ":is()"),
+            (":where(.a, 1x .b, #c)", ":where(.a, #c)"),
+            // functional pseudo-elements
+            ("::slotted( .x )", "::slotted(.x)"),
+            ("::part(label)", "::part(label)"),
+            ("::highlight(x):hover", "::highlight(x):hover"),
+        ];
+        for (selector, serialized) in cases {
+            let (stylesheet, diagnostics) = parse(&format!("{} {{}}", selector));
+            assert!(diagnostics.is_empty(), "{}: {:?}", selector, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            assert_eq!(style_rule.selector_text, serialized);
+        }
+
+        let (stylesheet, _) = parse("a:before, a:hover {}");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        assert!(matches!(
+            style_rule.selectors[0].compounds[0].subclass_selectors[0],
+            SimpleSelector::PseudoElement(_)
+        ));
+        assert!(matches!(
+            style_rule.selectors[1].compounds[0].subclass_selectors[0],
+            SimpleSelector::PseudoClass(_)
+        ));
+
+        let (_, diagnostics) = parse(":not() {} :is(.a {} :nth-child(2n+) {}");
+        let codes: Vec<DiagnosticCode> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                DiagnosticCode::ExpectedSelector,
+                DiagnosticCode::InvalidSelector,
+                DiagnosticCode::InvalidSelector,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_an_plus_b() {
+        // (An+B, a, b)
+        let cases = [
+            ("odd", 2, 1),
+            ("EVEN", 2, 0),
+            ("3", 0, 3),
+            ("-3", 0, -3),
+            ("n", 1, 0),
+            ("+n", 1, 0),
+            ("-n", -1, 0),
+            ("2n", 2, 0),
+            ("2n+1", 2, 1),
+            ("2n + 1", 2, 1),
+            ("2n +1", 2, 1),
+            ("2n-1", 2, -1),
+            ("2n- 1", 2, -1),
+            ("2n - 1", 2, -1),
+            ("-2n+3", -2, 3),
+            ("+3n-2", 3, -2),
+            ("-n+6", -1, 6),
+            ("n-10", 1, -10),
+            // escapes are resolved, e.g. `\6e` is `n` and `\2d` is `-`
+            (r"2\6e +1", 2, 1),
+            (r"-3\6e", -3, 0),
+            (r"2n\2d 1", 2, -1),
+        ];
+        for (an_plus_b, a, b) in cases {
+            let (stylesheet, diagnostics) = parse(&format!(":nth-child({}) {{}}", an_plus_b));
+            assert!(diagnostics.is_empty(), "{}: {:?}", an_plus_b, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            let SimpleSelector::PseudoClass(pseudo_class_selector) =
+                &style_rule.selectors[0].compounds[0].subclass_selectors[0]
+            else {
+                panic!("expected pseudo-class");
+            };
+            let Some(PseudoClassArgument::Nth(nth)) = &pseudo_class_selector.argument else {
+                panic!("expected An+B argument");
+            };
+            assert_eq!((nth.a, nth.b), (a, b), "{}", an_plus_b);
+        }
+
+        for an_plus_b in ["1.5", "2.5n", "+ n", "2n+ +1", "2m", "n-a", "2n 1"] {
+            let (_, diagnostics) = parse(&format!(":nth-child({}) {{}}", an_plus_b));
+            assert!(!diagnostics.is_empty(), "{}", an_plus_b);
+            assert_eq!(
+                diagnostics[0].code,
+                DiagnosticCode::InvalidSelector,
+                "{}",
+                an_plus_b
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_parse_type_selectors() {
+        // (selector, serialized)
+        let cases = [
+            ("*", "*"),
+            ("* + *", "* + *"),
+            ("*.a", "*.a"),
+            ("ul *", "ul *"),
+            ("svg|circle", "svg|circle"),
+            ("*|*", "*|*"),
+            ("|a", "|a"),
+            ("svg|*:hover", "svg|*:hover"),
+            ("[xlink|href] > svg|a", "[xlink|href] > svg|a"),
+            ("col || td", "col || td"),
+        ];
+        for (selector, serialized) in cases {
+            let (stylesheet, diagnostics) = parse(&format!("{} {{}}", selector));
+            assert!(diagnostics.is_empty(), "{}: {:?}", selector, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            assert_eq!(style_rule.selector_text, serialized);
+        }
+
+        let (stylesheet, _) = parse("svg|* {}");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let type_selector = style_rule.selectors[0].compounds[0]
+            .type_selector
+            .as_ref()
+            .unwrap();
+        assert_eq!(type_selector.namespace.as_deref(), Some("svg"));
+        assert!(type_selector.is_universal());
+
+        // escapes are resolved in element and namespace names, but kept when printed
+        let (stylesheet, diagnostics) = parse(r"\64 iv, sv\67|\63 ircle {}");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let div = style_rule.selectors[0].compounds[0]
+            .type_selector
+            .as_ref()
+            .unwrap();
+        assert_eq!(div.element, "div");
+        let circle = style_rule.selectors[1].compounds[0]
+            .type_selector
+            .as_ref()
+            .unwrap();
+        assert_eq!(circle.namespace.as_deref(), Some("svg"));
+        assert_eq!(circle.element, "circle");
+        assert_eq!(
+            stylesheet.rules[0].to_string(),
+            r"\64 iv, sv\67|\63 ircle {}"
+        );
+
+        let (_, diagnostics) = parse(".a* {}");
+        assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidSelector);
+
+        // only a hash token which would start an identifier is an id selector
+        let (stylesheet, diagnostics) = parse("#123 {} #-a {}");
+        assert_eq!(diagnostics[0].code, DiagnosticCode::ExpectedSelector);
+        assert_eq!(stylesheet.rules.len(), 1);
+    }
+
+    #[test]
+    pub fn test_parse_multi_component_values() {
+        // (value, serialized)
+        let cases = [
+            ("0 auto", "0 auto"),
+            ("12px/1.5 sans-serif", "12px/1.5 sans-serif"),
+            ("12px / 1.5  sans-serif ", "12px / 1.5 sans-serif"),
+            ("opacity 1s, transform 2s", "opacity 1s, transform 2s"),
+            ("0 /* top */ auto", "0 auto"),
+            ("url(a.png) no-repeat, #fff", "url(a.png) no-repeat, #fff"),
+            ("[full-start] 1fr [full-end]", "[full-start] 1fr [full-end]"),
+            ("calc(100% - 10px)", "calc(100% - 10px)"),
+            ("\"a\" 'b'", "\"a\" \"b\""),
+        ];
+        for (value, serialized) in cases {
+            let source = format!(".a {{ margin: {}; }}", value);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(diagnostics.is_empty(), "{}: {:?}", value, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            let declaration = &style_rule.declarations[0];
+            assert_eq!(declaration.value.to_string(), serialized);
+            assert_eq!(declaration.css_text, format!("margin: {};", serialized));
+            let span = declaration.value.span;
+            assert_eq!(&source[span.start..span.end], value.trim_end());
+        }
+
+        let (stylesheet, _) = parse(".a { font: italic 12px/1.5 a, b }");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let components: Vec<&str> = style_rule.declarations[0]
+            .value
+            .components
+            .iter()
+            .map(|component| match component {
+                ComponentValue::Literal(_, _) => "literal",
+                ComponentValue::Whitespace(_) => "whitespace",
+                ComponentValue::Comma(_) => "comma",
+                ComponentValue::Slash(_) => "slash",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            components,
+            vec![
+                "literal",
+                "whitespace",
+                "literal",
+                "slash",
+                "literal",
+                "whitespace",
+                "literal",
+                "comma",
+                "whitespace",
+                "literal",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_important() {
+        // (declaration, important, value, css text)
+        let cases = [
+            (
+                "color: red !important",
+                true,
+                "red",
+                "color: red !important;",
+            ),
+            (
+                "margin: 0 auto!important",
+                true,
+                "0 auto",
+                "margin: 0 auto !important;",
+            ),
+            (
+                "color: red ! /* why */ IMPORTANT ",
+                true,
+                "red",
+                "color: red !important;",
+            ),
+            ("color: red", false, "red", "color: red;"),
+            // `!important` only counts at the end of a declaration
+            (
+                "--x: a !important b",
+                false,
+                "a !important b",
+                "--x: a !important b;",
+            ),
+            ("--x: a !", false, "a !", "--x: a !;"),
+        ];
+        for (declaration, important, value, css_text) in cases {
+            let source = format!(".a {{ {}; }}", declaration);
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(diagnostics.is_empty(), "{}: {:?}", declaration, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            let parsed = &style_rule.declarations[0];
+            assert_eq!(parsed.important, important, "{}", declaration);
+            assert_eq!(parsed.value.to_string(), value);
+            assert_eq!(parsed.css_text, css_text);
+        }
+
+        let (_, diagnostics) = parse(".a { color: !important; }");
+        assert_eq!(diagnostics[0].code, DiagnosticCode::ExpectedValue);
+
+        let source = "@keyframes fade { to { opacity: 0 !important; color: red; } }";
+        let (stylesheet, diagnostics) = parse(source);
+        let Rule::Keyframes(keyframes_rule) = &stylesheet.rules[0] else {
+            panic!("expected keyframes rule");
+        };
+        assert_eq!(keyframes_rule.keyframes[0].declarations.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::IgnoredDeclaration);
+        let span = diagnostics[0].span;
+        assert_eq!(&source[span.start..span.end], "opacity: 0 !important;");
+    }
+
+    #[test]
+    pub fn test_parse_escaped_declarations() {
+        let source = r".a { col\6f r: \72 ed; width: calc(1px * p\69 ) }";
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let color = &style_rule.declarations[0];
+        assert_eq!(color.property, "color");
+        assert!(matches!(
+            color.value.literal(),
+            Some(LiteralValue::Ident(ident)) if ident == "red"
+        ));
+        let colors: Vec<String> = color
+            .value
+            .colors()
+            .into_iter()
+            .map(|(color, _)| color.to_hex())
+            .collect();
+        assert_eq!(colors, vec!["#ff0000"]);
+        assert_eq!(color.css_text, "color: red;");
+
+        let width = &style_rule.declarations[1];
+        let [ComponentValue::Math(math_function)] = width.value.components.as_slice() else {
+            panic!("expected math function");
+        };
+        let MathExpression::Binary(_, _, right) = &math_function.arguments[0] else {
+            panic!("expected binary expression");
+        };
+        assert!(matches!(
+            right.as_ref(),
+            MathExpression::Literal(LiteralValue::Ident(ident), _) if ident == "pi"
+        ));
+
+        let (stylesheet, _) = parse(r"@supports (displ\61 y: grid) {}");
+        assert_eq!(stylesheet.to_string(), "@supports (display: grid) {}");
+    }
+
+    #[test]
+    pub fn test_parse_function_values() {
+        fn parse_value(value: &str) -> Value {
+            let (stylesheet, diagnostics) = parse(&format!(".a {{ width: {}; }}", value));
+            assert!(diagnostics.is_empty(), "{}: {:?}", value, diagnostics);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            style_rule.declarations[0].value.clone()
+        }
+
+        let value = parse_value("rgb( 0 0 0 / 50% ) var(--gap, 8px)");
+        let [ComponentValue::Function(rgb), ComponentValue::Whitespace(_), ComponentValue::Function(var)] =
+            value.components.as_slice()
+        else {
+            panic!("expected two functions");
+        };
+        assert_eq!(rgb.name, "rgb");
+        assert_eq!(rgb.to_string(), "rgb(0 0 0 / 50%)");
+        assert_eq!(var.name, "var");
+        assert_eq!(var.to_string(), "var(--gap, 8px)");
+        assert!(matches!(
+            var.arguments[0],
+            ComponentValue::Literal(LiteralValue::Ident(_), _)
+        ));
+
+        /// The calculation with every operation parenthesized, to show how it is grouped
+        fn grouped(expression: &MathExpression) -> String {
+            match expression {
+                MathExpression::Binary(operator, left, right) => format!(
+                    "({} {} {})",
+                    grouped(left),
+                    operator.to_string(),
+                    grouped(right)
+                ),
+                MathExpression::Parenthesized(expression, _) => grouped(expression),
+                MathExpression::Math(math_function) => {
+                    let arguments: Vec<String> =
+                        math_function.arguments.iter().map(grouped).collect();
+                    format!("{}({})", math_function.name, arguments.join(", "))
+                }
+                _ => expression.to_string(),
+            }
+        }
+        // (math function, serialized, grouped)
+        let cases = [
+            (
+                "calc(1px + 2px * 3)",
+                "calc(1px + 2px * 3)",
+                "calc((1px + (2px * 3)))",
+            ),
+            (
+                "calc(1px - 2px - 3px)",
+                "calc(1px - 2px - 3px)",
+                "calc(((1px - 2px) - 3px))",
+            ),
+            (
+                "calc((1px + 2px)*3)",
+                "calc((1px + 2px) * 3)",
+                "calc(((1px + 2px) * 3))",
+            ),
+            (
+                "calc(100% - 2 * var(--gap))",
+                "calc(100% - 2 * var(--gap))",
+                "calc((100% - (2 * var(--gap))))",
+            ),
+            (
+                "clamp(1rem, 2.5vw, 2rem)",
+                "clamp(1rem, 2.5vw, 2rem)",
+                "clamp(1rem, 2.5vw, 2rem)",
+            ),
+            (
+                "min(10px, max(5vw, 1em) / 2)",
+                "min(10px, max(5vw, 1em) / 2)",
+                "min(10px, (max(5vw, 1em) / 2))",
+            ),
+            (
+                "round(nearest, 10.5px, 1px)",
+                "round(nearest, 10.5px, 1px)",
+                "round(nearest, 10.5px, 1px)",
+            ),
+            (
+                "calc(sin(pi / 4) * 1px)",
+                "calc(sin(pi / 4) * 1px)",
+                "calc((sin((pi / 4)) * 1px))",
+            ),
+        ];
+        for (math_function, serialized, expected) in cases {
+            let value = parse_value(math_function);
+            let [ComponentValue::Math(parsed)] = value.components.as_slice() else {
+                panic!("expected a math function: {}", math_function);
+            };
+            assert_eq!(parsed.to_string(), serialized);
+            assert_eq!(
+                grouped(&MathExpression::Math(parsed.clone())),
+                expected,
+                "{}",
+                math_function
+            );
+        }
+
+        // a math function which is not a valid calculation is kept as a plain function with a warning
+        // (math function, the text the warning points at)
+        let cases = [
+            ("calc(1px -2px)", "-2px"),
+            ("calc(1px+2px)", "+2px"),
+            ("calc()", ")"),
+            ("clamp(1px, 2px)", "clamp(1px, 2px)"),
+            ("calc(1px + )", ")"),
+        ];
+        for (invalid, flagged) in cases {
+            let source = format!(".a {{ width: {}; }}", invalid);
+            let (stylesheet, diagnostics) = parse(&source);
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            assert!(
+                matches!(
+                    style_rule.declarations[0].value.components.as_slice(),
+                    [ComponentValue::Function(_)]
+                ),
+                "{}",
+                invalid
+            );
+            assert_eq!(diagnostics.len(), 1, "{}: {:?}", invalid, diagnostics);
+            assert_eq!(diagnostics[0].severity, Severity::Warning);
+            let span = diagnostics[0].span;
+            assert_eq!(&source[span.start..span.end], flagged, "{}", invalid);
+        }
+        let (_, diagnostics) = parse(".a { width: clamp(1px, 2px); }");
+        assert_eq!(
+            diagnostics[0].message,
+            "Wrong number of arguments for clamp()"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_deep_nesting() {
+        let depth = 10000;
+        let nested = |open: &str, inner: &str, close: &str| {
+            format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+        };
+        // (source, whether the rule at the end is kept)
+        let cases = [
+            (
+                format!("@media {} {{}} .b {{}}", nested("(", "width", ")")),
+                true,
+            ),
+            (
+                format!("@supports {} {{}} .b {{}}", nested("(", "a: b", ")")),
+                true,
+            ),
+            (format!("{} {{}} .b {{}}", nested(":is(", ".a", ")")), true),
+            (format!("{} .b {{}}", nested(".a {", "", "}")), true),
+            (
+                format!("{} .b {{}}", nested("@media print {", "", "}")),
+                true,
+            ),
+            (
+                format!(".a {{ width: {}; }} .b {{}}", nested("calc(", "1px", ")")),
+                true,
+            ),
+            (
+                format!(".a {{ width: calc({}); }} .b {{}}", nested("(", "1px", ")")),
+                true,
+            ),
+            (
+                format!(".a {{ width: {}; }} .b {{}}", nested("var(", "--x", ")")),
+                true,
+            ),
+            // an unclosed block runs to the end of input
+            (nested(".a {", "", ""), false),
+        ];
+        for (source, keeps_last_rule) in cases {
+            let (stylesheet, diagnostics) = parse(&source);
+            assert!(
+                diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.code == DiagnosticCode::NestingTooDeep),
+                "{}",
+                &source[..20]
+            );
+            let last_rule_is_b = matches!(
+                stylesheet.rules.last(),
+                Some(Rule::StyleRule(style_rule)) if style_rule.selector_text == ".b"
+            );
+            assert_eq!(last_rule_is_b, keeps_last_rule, "{}", &source[..20]);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_custom_property() {
+        let (stylesheet, diagnostics) =
+            parse(".a { --x: { a: b }; --empty: ; --y : 1px; b { color: red } }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        let declarations: Vec<&str> = style_rule
+            .declarations
+            .iter()
+            .map(|declaration| declaration.css_text.as_str())
+            .collect();
+        assert_eq!(
+            declarations,
+            vec!["--x: { a: b };", "--empty: ;", "--y: 1px;"]
+        );
+        assert!(style_rule.declarations[1].value.components.is_empty());
+        assert_eq!(style_rule.rules.len(), 1);
+
+        // only custom properties may be empty
+        let (_, diagnostics) = parse(".a { color: ; }");
+        assert_eq!(diagnostics[0].code, DiagnosticCode::ExpectedValue);
+    }
+
+    #[test]
+    pub fn test_print_stylesheet() {
+        let source = r#"@charset "utf-8";
+@layer reset, base.x;
+@import url(a.css) layer(base) supports(display: grid) screen and (color);
+@namespace svg url(http://www.w3.org/2000/svg);
+.a, #b > c::before { color: red; margin: 0 auto !important; &:hover { color: blue } }
+@media print { .a { display: none } }
+@supports not (display: grid) { .a { float: left } }
+@container card (min-width: 400px) { .b { gap: 1rem } }
+@layer base { @layer { .c {} } }
+@keyframes "fade in" { from { opacity: 0 } 50%, to { opacity: 1 } }
+@font-face { font-family: Inter; }
+@page :first { margin: 1in; @top-center { content: "a\"b"; } }
+@counter-style thumbs { system: cyclic; }
+@property --gap { syntax: "<length>"; inherits: false; initial-value: 0px; }
+@scope (.card) { img { border: 0; } }"#;
+        let (stylesheet, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let printed = stylesheet.to_string();
+        assert_eq!(
+            printed,
+            r#"@charset "utf-8";
+@layer reset, base.x;
+@import "a.css" layer(base) supports(display: grid) screen and (color);
+@namespace svg "http://www.w3.org/2000/svg";
+.a, #b > c::before { color: red; margin: 0 auto !important; &:hover { color: blue; } }
+@media print { .a { display: none; } }
+@supports not (display: grid) { .a { float: left; } }
+@container card (min-width: 400px) { .b { gap: 1rem; } }
+@layer base { @layer { .c {} } }
+@keyframes "fade in" { from { opacity: 0; } 50%, to { opacity: 1; } }
+@font-face { font-family: Inter; }
+@page :first { margin: 1in; @top-center { content: "a\"b"; } }
+@counter-style thumbs { system: cyclic; }
+@property --gap { syntax: "<length>"; inherits: false; initial-value: 0px; }
+@scope (.card) { img { border: 0; } }"#
+        );
+        let (reparsed, diagnostics) = parse(&printed);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(reparsed.to_string(), printed);
     }
 }
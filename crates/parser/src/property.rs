@@ -0,0 +1,357 @@
+use crate::bnf::{
+    ComponentValue, FunctionValue, LiteralValue, MathExpression, MathFunction, PropertySyntax,
+    SyntaxComponent, SyntaxComponentName, SyntaxMultiplier, Value,
+};
+use crate::color::Color;
+use crate::number::UnitKind;
+
+impl PropertySyntax {
+    /// Whether the value is valid for the [syntax](https://www.w3.org/TR/css-properties-values-api-1/#syntax-strings),
+    /// e.g. `0px 1in` for `<length>+ | auto`.
+    /// Substitution functions such as `var()` cannot be checked before they are substituted, so they never match.
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            PropertySyntax::Universal => true,
+            PropertySyntax::Components(components) => components
+                .iter()
+                .any(|component| component.matches(&value.components)),
+        }
+    }
+}
+
+impl SyntaxComponent {
+    fn matches(&self, components: &[ComponentValue]) -> bool {
+        let is_whitespace =
+            |component: &ComponentValue| matches!(component, ComponentValue::Whitespace(_));
+        // `<transform-list>` is a space-separated list of `<transform-function>`
+        let (data_type, multiplier) = match &self.name {
+            SyntaxComponentName::DataType(data_type) if data_type == "transform-list" => {
+                ("transform-function", Some(SyntaxMultiplier::SpaceSeparated))
+            }
+            SyntaxComponentName::DataType(data_type) => (data_type.as_str(), self.multiplier),
+            SyntaxComponentName::Ident(_) => ("", self.multiplier),
+        };
+        let items: Vec<Vec<&ComponentValue>> = match multiplier {
+            None => vec![components.iter().collect()],
+            Some(SyntaxMultiplier::SpaceSeparated) => components
+                .split(is_whitespace)
+                .map(|item| item.iter().collect())
+                .collect(),
+            Some(SyntaxMultiplier::CommaSeparated) => components
+                .split(|component| matches!(component, ComponentValue::Comma(_)))
+                .map(|item| {
+                    item.iter()
+                        .filter(|component| !is_whitespace(component))
+                        .collect()
+                })
+                .collect(),
+        };
+        items
+            .iter()
+            .all(|item| match (item.as_slice(), &self.name) {
+                (
+                    [ComponentValue::Literal(LiteralValue::Ident(ident), _)],
+                    SyntaxComponentName::Ident(name),
+                ) => ident == name,
+                ([component], SyntaxComponentName::DataType(_)) => {
+                    matches_data_type(data_type, component)
+                }
+                _ => false,
+            })
+    }
+}
+
+/// Whether a single component value is of a [supported data type](https://www.w3.org/TR/css-properties-values-api-1/#supported-names),
+/// e.g. `length` for `1px`
+fn matches_data_type(data_type: &str, component: &ComponentValue) -> bool {
+    match data_type {
+        "length" => matches_numeric(component, Some(UnitKind::Length), false, false),
+        "length-percentage" => matches_numeric(component, Some(UnitKind::Length), true, false),
+        "angle" => matches_numeric(component, Some(UnitKind::Angle), false, false),
+        "time" => matches_numeric(component, Some(UnitKind::Time), false, false),
+        "resolution" => matches_numeric(component, Some(UnitKind::Resolution), false, false),
+        "percentage" => matches_numeric(component, None, true, false),
+        "number" => matches_numeric(component, None, false, false),
+        "integer" => matches_numeric(component, None, false, true),
+        "color" => match component {
+            ComponentValue::Literal(LiteralValue::Ident(ident), _)
+                if ident.eq_ignore_ascii_case("currentcolor") =>
+            {
+                true
+            }
+            _ => Color::from_component_value(component).is_some(),
+        },
+        "custom-ident" => match component {
+            ComponentValue::Literal(LiteralValue::Ident(ident), _) => ![
+                "initial",
+                "inherit",
+                "unset",
+                "revert",
+                "revert-layer",
+                "default",
+            ]
+            .iter()
+            .any(|keyword| ident.eq_ignore_ascii_case(keyword)),
+            _ => false,
+        },
+        "string" => matches!(
+            component,
+            ComponentValue::Literal(LiteralValue::String(_), _)
+        ),
+        "url" => match component {
+            ComponentValue::Url(..) => true,
+            ComponentValue::Function(function) => function.name.eq_ignore_ascii_case("url"),
+            _ => false,
+        },
+        "image" => match component {
+            ComponentValue::Url(..) => true,
+            ComponentValue::Function(function) => {
+                let name = function.name.to_ascii_lowercase();
+                let name = name.strip_prefix("-webkit-").unwrap_or(&name);
+                name.ends_with("-gradient")
+                    || [
+                        "url",
+                        "image",
+                        "image-set",
+                        "cross-fade",
+                        "element",
+                        "paint",
+                    ]
+                    .contains(&name)
+            }
+            _ => false,
+        },
+        "transform-function" => match component {
+            ComponentValue::Function(function) => TRANSFORM_FUNCTIONS
+                .iter()
+                .any(|name| function.name.eq_ignore_ascii_case(name)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+const TRANSFORM_FUNCTIONS: [&str; 21] = [
+    "matrix",
+    "matrix3d",
+    "perspective",
+    "rotate",
+    "rotate3d",
+    "rotateX",
+    "rotateY",
+    "rotateZ",
+    "scale",
+    "scale3d",
+    "scaleX",
+    "scaleY",
+    "scaleZ",
+    "skew",
+    "skewX",
+    "skewY",
+    "translate",
+    "translate3d",
+    "translateX",
+    "translateY",
+    "translateZ",
+];
+
+/// Whether the component is a number, a percentage or a dimension of `kind`, or a math function resolving to one.
+/// A unitless zero is also a length.
+fn matches_numeric(
+    component: &ComponentValue,
+    kind: Option<UnitKind>,
+    percentage: bool,
+    integer: bool,
+) -> bool {
+    match component {
+        ComponentValue::Literal(literal, _) => match literal {
+            LiteralValue::Number(number) => {
+                kind.is_none() && !percentage && (!integer || number.is_integer)
+                    || kind == Some(UnitKind::Length) && number.value == 0.0
+            }
+            LiteralValue::Percentage(_) => percentage,
+            LiteralValue::Dimension(dimension) => kind.is_some() && dimension.unit.kind() == kind,
+            _ => false,
+        },
+        // the type of a math function is taken from its operands, e.g. a length for `calc(1px + 2 * 3px)`
+        ComponentValue::Math(math_function) => {
+            let mut operands = Vec::new();
+            if !collect_operands(math_function, &mut operands) {
+                return false;
+            }
+            let is_typed = |literal: &&LiteralValue| match literal {
+                LiteralValue::Percentage(_) => percentage,
+                LiteralValue::Dimension(dimension) => {
+                    kind.is_some() && dimension.unit.kind() == kind
+                }
+                _ => false,
+            };
+            let all_allowed = operands.iter().all(|literal| {
+                matches!(literal, LiteralValue::Number(_) | LiteralValue::Ident(_))
+                    || is_typed(literal)
+            });
+            // a number needs no typed operands, anything else needs at least one
+            let is_number = kind.is_none() && !percentage;
+            all_allowed && (is_number || operands.iter().any(is_typed))
+        }
+        _ => false,
+    }
+}
+
+/// Collect the literals a math function calculates with, returning `false` if it has other operands such as `var()`
+fn collect_operands<'a>(
+    math_function: &'a MathFunction,
+    operands: &mut Vec<&'a LiteralValue>,
+) -> bool {
+    fn collect<'a>(expression: &'a MathExpression, operands: &mut Vec<&'a LiteralValue>) -> bool {
+        match expression {
+            MathExpression::Literal(literal, _) => {
+                operands.push(literal);
+                true
+            }
+            MathExpression::Function(_) => false,
+            MathExpression::Math(math_function) => collect_operands(math_function, operands),
+            MathExpression::Parenthesized(expression, _) => collect(expression, operands),
+            MathExpression::Binary(_, left, right) => {
+                collect(left, operands) && collect(right, operands)
+            }
+        }
+    }
+    math_function
+        .arguments
+        .iter()
+        .all(|argument| collect(argument, operands))
+}
+
+impl Value {
+    /// Whether the value can be computed without knowing the element it applies to,
+    /// as required for the `initial-value` of `@property`, e.g. `10px` but not `1em` or `var(--gap)`
+    pub fn is_computationally_independent(&self) -> bool {
+        self.components.iter().all(is_independent_component)
+    }
+}
+
+fn is_independent_component(component: &ComponentValue) -> bool {
+    match component {
+        ComponentValue::Literal(literal, _) => is_independent_literal(literal),
+        ComponentValue::Function(function) => is_independent_function(function),
+        ComponentValue::Math(math_function) => math_function
+            .arguments
+            .iter()
+            .all(is_independent_expression),
+        _ => true,
+    }
+}
+
+fn is_independent_function(function: &FunctionValue) -> bool {
+    let is_substitution = ["var", "env", "attr"]
+        .iter()
+        .any(|name| function.name.eq_ignore_ascii_case(name));
+    !is_substitution && function.arguments.iter().all(is_independent_component)
+}
+
+fn is_independent_expression(expression: &MathExpression) -> bool {
+    match expression {
+        MathExpression::Literal(literal, _) => is_independent_literal(literal),
+        MathExpression::Function(function) => is_independent_function(function),
+        MathExpression::Math(math_function) => math_function
+            .arguments
+            .iter()
+            .all(is_independent_expression),
+        MathExpression::Parenthesized(expression, _) => is_independent_expression(expression),
+        MathExpression::Binary(_, left, right) => {
+            is_independent_expression(left) && is_independent_expression(right)
+        }
+    }
+}
+
+/// Lengths relative to the font or the viewport, e.g. `1em` or `10vw`, depend on the element
+fn is_independent_literal(literal: &LiteralValue) -> bool {
+    match literal {
+        LiteralValue::Dimension(dimension) => {
+            dimension.unit.kind() != Some(UnitKind::Length)
+                || dimension.unit.canonical_factor().is_some()
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bnf::PropertySyntax;
+    use crate::helper::ToString;
+    use crate::parser::parse_property_syntax;
+    use crate::test_helper::parse_value as value;
+
+    #[test]
+    fn test_syntax_matches() {
+        // (syntax, value, matches)
+        let cases = [
+            ("<length>", "0", true),
+            ("<length>", "1.5in", true),
+            ("<length>", "red", false),
+            ("<length>", "10%", false),
+            ("<length>", "calc(1px + 2 * 3em)", true),
+            ("<length>", "calc(1px + 10deg)", false),
+            ("<length-percentage>", "calc(100% - 8px)", true),
+            ("<length>+ | auto", "1px 2px", true),
+            ("<length>+ | auto", "auto", true),
+            ("<length>+ | auto", "1px, 2px", false),
+            ("<length>#", "1px , 2px", true),
+            ("<integer>", "2", true),
+            ("<integer>", "2.5", false),
+            ("<number>", "calc(2 * 3)", true),
+            ("<percentage>", "50%", true),
+            ("<angle>", "0.5turn", true),
+            ("<time>", "1s", true),
+            ("<resolution>", "2x", true),
+            ("<color>", "red", true),
+            ("<color>", "rgb(0 0 0 / 50%)", true),
+            ("<color>", "1px", false),
+            ("<custom-ident>", "fade", true),
+            ("<custom-ident>", "inherit", false),
+            ("<string>", "\"a\"", true),
+            ("<url>", "url(a.png)", true),
+            ("<image>", "linear-gradient(red, blue)", true),
+            ("<transform-function>", "rotate(45deg)", true),
+            ("<transform-list>", "rotate(45deg) scale(2)", true),
+            ("<length>", "var(--gap)", false),
+            ("*", "anything at all", true),
+        ];
+        for (syntax, source, matches) in cases {
+            let syntax = parse_property_syntax(syntax).unwrap();
+            assert_eq!(
+                syntax.matches(&value(source)),
+                matches,
+                "{} {}",
+                syntax.to_string(),
+                source
+            );
+        }
+        assert!(PropertySyntax::Universal.matches(&value("1em")));
+    }
+
+    #[test]
+    fn test_computationally_independent() {
+        // (value, computationally independent)
+        let cases = [
+            ("10px", true),
+            ("1in 2cm", true),
+            ("50%", true),
+            ("1em", false),
+            ("10vw", false),
+            ("calc(1px + 1rem)", false),
+            ("var(--gap)", false),
+            ("rgb(0 0 0)", true),
+            ("1s", true),
+        ];
+        for (source, independent) in cases {
+            assert_eq!(
+                value(source).is_computationally_independent(),
+                independent,
+                "{}",
+                source
+            );
+        }
+    }
+}
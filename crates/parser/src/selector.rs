@@ -0,0 +1,223 @@
+use crate::bnf::{Selector, SimpleSelector};
+use serde::{Deserialize, Serialize};
+
+/// The [specificity](https://www.w3.org/TR/selectors-4/#specificity-rules) of a selector.
+/// Specificities compare by their ids first, then classes, then types, e.g. `#a` beats `.b.c.d`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+pub struct Specificity {
+    /// The number of id selectors
+    pub ids: u32,
+    /// The number of class, attribute and pseudo-class selectors
+    pub classes: u32,
+    /// The number of type and pseudo-element selectors
+    pub types: u32,
+}
+
+impl std::ops::Add for Specificity {
+    type Output = Specificity;
+
+    fn add(self, other: Specificity) -> Specificity {
+        Specificity {
+            ids: self.ids + other.ids,
+            classes: self.classes + other.classes,
+            types: self.types + other.types,
+        }
+    }
+}
+
+impl Selector {
+    /// The specificity of the selector, e.g. `(1, 2, 1)` for `a#main.btn:hover`.
+    /// `&` counts for nothing, so nested selectors should be [resolved](Selector::resolve) first.
+    pub fn specificity(&self) -> Specificity {
+        let mut specificity = Specificity::default();
+        for compound in &self.compounds {
+            // the universal selector counts for nothing
+            if let Some(type_selector) = &compound.type_selector {
+                if !type_selector.is_universal() {
+                    specificity.types += 1;
+                }
+            }
+            for subclass_selector in &compound.subclass_selectors {
+                specificity = specificity + simple_selector_specificity(subclass_selector);
+            }
+        }
+        specificity
+    }
+
+    /// The class names used in the selector without the leading `.`, in order of first appearance,
+    /// e.g. `["card", "active"]` for `.card > a.active:hover`.
+    /// This includes those in the arguments of pseudo-classes, e.g. `hidden` in `:not(.hidden)`.
+    pub fn class_names(&self) -> Vec<String> {
+        let mut class_names: Vec<String> = Vec::new();
+        self.collect_class_names(&mut class_names);
+        class_names
+    }
+
+    fn collect_class_names(&self, class_names: &mut Vec<String>) {
+        for compound in &self.compounds {
+            for subclass_selector in &compound.subclass_selectors {
+                match subclass_selector {
+                    SimpleSelector::Class(class_selector)
+                        if !class_names.contains(&class_selector.class_name) =>
+                    {
+                        class_names.push(class_selector.class_name.clone());
+                    }
+                    SimpleSelector::PseudoClass(pseudo_class_selector) => {
+                        for selector in pseudo_class_selector.selectors() {
+                            selector.collect_class_names(class_names);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn simple_selector_specificity(simple_selector: &SimpleSelector) -> Specificity {
+    let class = Specificity {
+        ids: 0,
+        classes: 1,
+        types: 0,
+    };
+    match simple_selector {
+        SimpleSelector::Id(_) => Specificity {
+            ids: 1,
+            classes: 0,
+            types: 0,
+        },
+        SimpleSelector::Class(_) | SimpleSelector::Attribute(_) => class,
+        // e.g. `::slotted(.a)` counts as a class and a type
+        SimpleSelector::PseudoElement(pseudo_element_selector) => {
+            let pseudo_element = Specificity {
+                ids: 0,
+                classes: 0,
+                types: 1,
+            };
+            pseudo_element + most_specific(pseudo_element_selector.selectors())
+        }
+        SimpleSelector::Nesting(_) => Specificity::default(),
+        SimpleSelector::PseudoClass(pseudo_class_selector) => {
+            let most_specific = most_specific(pseudo_class_selector.selectors());
+            let name = pseudo_class_selector.ident.trim_start_matches(':');
+            match name.to_ascii_lowercase().as_str() {
+                _ if pseudo_class_selector.argument.is_none() => class,
+                "where" => Specificity::default(),
+                // the most specific of the arguments stands in for the pseudo-class
+                "is" | "not" | "has" | "matches" | "-webkit-any" | "-moz-any" => most_specific,
+                // e.g. `:nth-child(2n of .item)` and `:host(.dark)` count as two classes
+                _ => class + most_specific,
+            }
+        }
+    }
+}
+
+/// The specificity of the most specific of the selectors, e.g. `(1, 0, 0)` for `.a, #b`
+fn most_specific(selectors: &[Selector]) -> Specificity {
+    selectors
+        .iter()
+        .map(Selector::specificity)
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Specificity;
+    use crate::bnf::{Rule, SimpleSelector};
+    use crate::test_helper::parse_valid;
+
+    #[test]
+    fn test_specificity() {
+        // (selector, specificity as ids, classes and types)
+        let cases = [
+            ("a", (0, 0, 1)),
+            (".btn", (0, 1, 0)),
+            ("a.btn#main:hover::before", (1, 2, 2)),
+            ("ul > li.active a", (0, 1, 3)),
+            ("#a #b", (2, 0, 0)),
+            ("input[type=text]:focus", (0, 2, 1)),
+            ("li:not(.a, #b)", (1, 0, 1)),
+            (":is(ul, .list) > li", (0, 1, 1)),
+            (":where(#a .b) p", (0, 0, 1)),
+            ("a:has(> img.icon)", (0, 1, 2)),
+            ("li:nth-child(2n+1 of .item)", (0, 2, 1)),
+            ("li:nth-of-type(odd)", (0, 1, 1)),
+            ("p:before", (0, 0, 2)),
+            ("* + *", (0, 0, 0)),
+            ("svg|circle.dot", (0, 1, 1)),
+            (":host(.x)", (0, 2, 0)),
+            (":host", (0, 1, 0)),
+            ("::slotted(.x)", (0, 1, 1)),
+            ("::part(label)", (0, 0, 1)),
+            (":is(.a, :unknown(), #b)", (1, 0, 0)),
+            (":where(.a,)", (0, 0, 0)),
+        ];
+        for (selector, (ids, classes, types)) in cases {
+            let stylesheet = parse_valid(&format!("{} {{}}", selector));
+            let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+                panic!("expected style rule");
+            };
+            assert_eq!(
+                style_rule.selectors[0].specificity(),
+                Specificity {
+                    ids,
+                    classes,
+                    types
+                },
+                "{}",
+                selector
+            );
+        }
+        let id = Specificity {
+            ids: 1,
+            classes: 0,
+            types: 0,
+        };
+        let classes = Specificity {
+            ids: 0,
+            classes: 3,
+            types: 0,
+        };
+        assert!(id > classes);
+    }
+
+    #[test]
+    fn test_class_names() {
+        let stylesheet = parse_valid(
+            ".card > a.active:hover, .card .title.active, .a:not(.hidden, .b:is(.c)) {}",
+        );
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(
+            style_rule.selectors[0].class_names(),
+            vec!["card", "active"]
+        );
+        assert_eq!(
+            style_rule.selectors[1].class_names(),
+            vec!["card", "title", "active"]
+        );
+        assert_eq!(
+            style_rule.selectors[2].class_names(),
+            vec!["a", "hidden", "b", "c"]
+        );
+
+        // escapes are resolved in class names, but kept in the selector text
+        let stylesheet = parse_valid(".sm\\:p-4, .\\31 0, #a\\.b {}");
+        let Rule::StyleRule(style_rule) = &stylesheet.rules[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(style_rule.selectors[0].class_names(), vec!["sm:p-4"]);
+        assert_eq!(style_rule.selectors[1].class_names(), vec!["10"]);
+        assert_eq!(style_rule.selector_text, ".sm\\:p-4, .\\31 0, #a\\.b");
+        let SimpleSelector::Id(id_selector) =
+            &style_rule.selectors[2].compounds[0].subclass_selectors[0]
+        else {
+            panic!("expected id selector");
+        };
+        assert_eq!(id_selector.id, "a.b");
+    }
+}
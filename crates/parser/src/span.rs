@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+/// A range of the source text as byte offsets, `start` inclusive and `end` exclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// A 1-based line and column.
+/// The column counts UTF-16 code units, which is what editors and JavaScript strings index by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The start and end [`Position`] of a [`Span`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Location {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A lookup table from byte offsets to [`Position`]s.
+///
+/// `\r\n`, `\r` and `\f` each end a line, the same as in the [tokenizer](crate::lexer::Lexer).
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    source: String,
+    // byte offset of the first character of each line
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut chars = source.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '\r' if chars.peek().map(|(_, c)| *c) == Some('\n') => {}
+                '\n' | '\r' | '\x0C' => line_starts.push(offset + c.len_utf8()),
+                _ => {}
+            }
+        }
+        Self {
+            source: source.to_string(),
+            line_starts,
+        }
+    }
+
+    /// The 1-based line number the byte offset is on
+    pub fn line(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line + 1,
+            Err(line) => line,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = self.line(offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self.source[line_start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>()
+            + 1;
+        Position { line, column }
+    }
+
+    pub fn location(&self, span: Span) -> Location {
+        Location {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_index() {
+        let index = LineIndex::new("a {\r\n  b: c;\n}\x0Cd");
+        assert_eq!(index.position(0), Position { line: 1, column: 1 });
+        // `\r\n` is a single line break
+        assert_eq!(index.position(5), Position { line: 2, column: 1 });
+        assert_eq!(index.position(7), Position { line: 2, column: 3 });
+        assert_eq!(index.position(13), Position { line: 3, column: 1 });
+        assert_eq!(index.position(15), Position { line: 4, column: 1 });
+    }
+
+    #[test]
+    fn test_columns_count_utf16_code_units() {
+        // `é` is 2 bytes and 1 UTF-16 code unit, `😀` is 4 bytes and 2 UTF-16 code units
+        let source = "é😀a";
+        let index = LineIndex::new(source);
+        assert_eq!(index.position(2).column, 2);
+        assert_eq!(index.position(6).column, 4);
+        assert_eq!(
+            index.location(Span::new(0, source.len())),
+            Location {
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 5 },
+            }
+        );
+    }
+}
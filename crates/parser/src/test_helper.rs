@@ -0,0 +1,36 @@
+use crate::bnf::{StyleSheet, Value};
+use crate::diagnostic::Diagnostic;
+use crate::hir::{HIRGenerator, HIR};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+/// Parse a stylesheet along with the diagnostics reported for it
+pub fn parse(source: &str) -> (StyleSheet, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new(source.to_string());
+    lexer.build();
+    Parser::new(lexer.tokens()).parse()
+}
+
+/// Parse a stylesheet which must not have any diagnostics
+pub fn parse_valid(source: &str) -> StyleSheet {
+    let (stylesheet, diagnostics) = parse(source);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    stylesheet
+}
+
+/// Parse a declaration value, e.g. `0 auto`
+pub fn parse_value(source: &str) -> Value {
+    let mut lexer = Lexer::new(source.to_string());
+    lexer.build();
+    Parser::new(lexer.tokens())
+        .parse_value()
+        .expect("a valid value")
+}
+
+/// Generate the HIR of a stylesheet
+pub fn generate_hir(source: &str) -> HIR {
+    let mut lexer = Lexer::new(source.to_string());
+    lexer.build();
+    let (ast, diagnostics) = Parser::new(lexer.tokens()).parse();
+    HIRGenerator::new(ast, diagnostics, lexer.line_index()).generate()
+}
@@ -1,59 +1,72 @@
+use crate::span::Span;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-/// [CSS Tokenization](https://www.w3.org/TR/CSS21/syndata.html#tokenization)
+/// [CSS Tokenization](https://www.w3.org/TR/css-syntax-3/#tokenization)
 pub enum TokenType {
-    Ident,        // {ident}
-    Atkeyword,    // @{ident}
-    String,       // {string}
-    BadString,    // {badstring}
-    BadUri,       // {baduri}
-    BadComment,   // {badcomment}
-    Hash,         // #{name}
-    Number,       // {num}
-    Percentage,   // {num}%
-    Dimension,    // {num}{ident}
-    Uri,          // url\({w}{string}{w}\) | |url\({w}([!#$%&*-\[\]-~]|{nonascii}|{escape})*{w}\)
-    UnicodeRange, // u\+[0-9a-f?]{1,6}(-[0-9a-f]{1,6})?
-    Cdo,          // <!--
-    Cdc,          // -->
-    Colon,        // :
-    Semicolon,    // ;
-    CurlyLeft,    // \{
-    CurlyRight,   // \}
-    ParenLeft,    // \(
-    ParenRight,   // \)
-    BracketLeft,  // \[
-    BracketRight, // \]
-    S,            // [ \t\r\n\f]+
-    Comment,      // \/\*[^*]*\*+([^/*][^*]*\*+)*\/
-    Function,     // {ident}\(
+    Ident,        // <ident-token>
+    Atkeyword,    // <at-keyword-token>
+    String,       // <string-token>
+    BadString,    // <bad-string-token>
+    BadUri,       // <bad-url-token>
+    BadComment,   // not produced: comments are discarded by the tokenizer
+    Hash,         // <hash-token>
+    Number,       // <number-token>
+    Percentage,   // <percentage-token>
+    Dimension,    // <dimension-token>
+    Uri,          // <url-token>
+    UnicodeRange, // not produced: unicode-range is a microsyntax over ident/number tokens in Level 3
+    Cdo,          // <CDO-token>, <!--
+    Cdc,          // <CDC-token>, -->
+    Colon,        // <colon-token>
+    Semicolon,    // <semicolon-token>
+    CurlyLeft,    // <{-token>
+    CurlyRight,   // <}-token>
+    ParenLeft,    // <(-token>
+    ParenRight,   // <)-token>
+    BracketLeft,  // <[-token>
+    BracketRight, // <]-token>
+    S,            // <whitespace-token>
+    Comment,      // not produced: comments are discarded by the tokenizer
+    Function,     // <function-token>
     Includes,     // ~=
     DashMatch,    // |=
-    Delim, // any other character not matched by the above rules, and neither a single nor a double quote
-    Eof,   // EOF
+    Delim,        // <delim-token>, any other single code point
+    Comma,        // <comma-token>
+    Eof,          // <EOF-token>
 
-    // below are not part of the CSS Tokenization specification but are used in the lexer & parser
-    ClassSelector,         // .{ident}
-    IdSelector,            // #{ident}
-    PseudoClassSelector,   // :{ident}
-    PseudoElementSelector, // ::{ident}
-    Comma,                 // ,
-    Plus,                  // +
-    Greater,               // >
-    Tilde,                 // ~
+    // below are <delim-token>s which are given their own type because the parser matches on them
+    Plus,    // +
+    Greater, // >
+    Tilde,   // ~
 }
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
+    /// The source text the token was consumed from, e.g. `url( "a.png" )`
     pub lexeme: String,
+    /// The token's value after escapes are resolved:
+    /// the name of an ident, function, at-keyword or hash token,
+    /// the contents of a string or url token, the unit of a dimension token,
+    /// and the lexeme itself for everything else.
+    pub value: String,
+    pub span: Span,
     pub line: usize,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        value: String,
+        span: Span,
+        line: usize,
+    ) -> Self {
         Token {
             token_type,
             lexeme,
+            value,
+            span,
             line,
         }
     }
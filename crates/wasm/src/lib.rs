@@ -12,16 +12,130 @@ use wasm_bindgen::prelude::*;
 const TYPES: &'static str = r#"
 export type CssInfo = {
   rules: Rule[];
+  /** fully qualified cascade layer names, from the lowest to the highest priority */
+  layers: string[];
+  keyframes: Keyframes[];
+  /** custom properties registered with a valid `@property` rule */
+  properties: Property[];
+  /** stylesheets imported with `@import`, in source order */
+  imports: Import[];
+  diagnostics: Diagnostic[];
 };
 
 export type Rule = {
+  /** the selector as written, e.g. `&:hover` for a nested rule */
   selector: string;
+  /** the selector with `&` resolved against the enclosing style rules */
+  resolved_selector: string;
+  /** class names in the resolved selectors, without the leading `.` */
+  class_names: string[];
+  /** specificity of each of the resolved selectors */
+  specificity: Specificity[];
+  /** index in `rules` of the style rule this rule is nested in */
+  parent: number | null;
   declarations: Declaration[];
+  /** media query lists of the enclosing `@media` rules, outermost first */
+  media: string[];
+  /** conditions of the enclosing `@supports` rules, outermost first */
+  supports: string[];
+  /** conditions of the enclosing `@container` rules, outermost first */
+  container: string[];
+  /** fully qualified name of the enclosing cascade layer, `null` when unlayered */
+  layer: string | null;
+  span: Span;
+  location: Location;
+};
+
+export type Specificity = {
+  ids: number;
+  classes: number;
+  types: number;
 };
 
 export type Declaration = {
   property: string;
+  /** the value without `!important` */
   value: string;
+  important: boolean;
+  /** colors in the value */
+  colors: Color[];
+  span: Span;
+  location: Location;
+};
+
+/** a color in sRGB, each channel from 0 to 1 */
+export type Color = {
+  red: number;
+  green: number;
+  blue: number;
+  alpha: number;
+  span: Span;
+  location: Location;
+};
+
+/** a `@keyframes` rule, whose name is locally scoped in CSS Modules */
+export type Keyframes = {
+  name: string;
+  keyframes: Keyframe[];
+  span: Span;
+  location: Location;
+};
+
+export type Keyframe = {
+  /** e.g. `from, 50%` */
+  selector: string;
+  declarations: Declaration[];
+  span: Span;
+  location: Location;
+};
+
+export type Property = {
+  name: string;
+  /** the declared type, e.g. `<length> | auto` */
+  syntax: string;
+  inherits: boolean;
+  /** omitted only for the universal syntax `*` */
+  initial_value: string | null;
+  span: Span;
+  location: Location;
+};
+
+export type Import = {
+  url: string;
+  /** span of the URL or string, e.g. `url("theme.css")` */
+  url_span: Span;
+  /** fully qualified name of the cascade layer the stylesheet is imported into */
+  layer: string | null;
+  supports: string | null;
+  /** `null` when the import is unconditional */
+  media: string | null;
+  span: Span;
+  location: Location;
+};
+
+export type Diagnostic = {
+  code: string;
+  message: string;
+  severity: "error" | "warning";
+  span: Span;
+  location: Location;
+};
+
+/** byte offsets into the source, `end` exclusive */
+export type Span = {
+  start: number;
+  end: number;
+};
+
+export type Location = {
+  start: Position;
+  end: Position;
+};
+
+/** 1-based line and 1-based column in UTF-16 code units */
+export type Position = {
+  line: number;
+  column: number;
 };
 
 export function getCssInfo(code: string): CssInfo;
@@ -31,10 +145,10 @@ export function getCssInfo(code: string): CssInfo;
 pub fn get_css_info(code: &str) -> Result<JsValue, JsValue> {
     let mut lexer = Lexer::new(code.to_string());
     lexer.build();
-    let mut tokens = lexer.tokens();
+    let tokens = lexer.tokens();
     let mut parser = Parser::new(tokens);
-    let mut style_sheet = parser.parse();
-    let hir = HIRGenerator::new(style_sheet).generate();
+    let (style_sheet, diagnostics) = parser.parse();
+    let hir = HIRGenerator::new(style_sheet, diagnostics, lexer.line_index()).generate();
 
     JsValue::from_serde(&hir).map_err(|e| JsValue::from_str(&e.to_string()))
 }